@@ -2,24 +2,25 @@
 
 use std::sync::{atomic::AtomicI32, Arc};
 
-use alcro::{Content, UIBuilder, UI};
+use alcro::{Content, UIBuilder};
 use anyhow::Context;
 use serde_json::to_value;
 
-fn new_window() -> anyhow::Result<Arc<UI>> {
-    let ui = Arc::new(
+const CONTENT: Content = Content::Html(include_str!("./multiple-windows.html"));
+
+fn main() -> anyhow::Result<()> {
+    let count = Arc::new(AtomicI32::new(0));
+    let ui1 = Arc::new(
         UIBuilder::new()
-            .content(Content::Html(include_str!("./multiple-windows.html")))
+            .content(CONTENT)
             .run()
             .context("Failed to create new window")?,
     );
-    Ok(ui)
-}
-
-fn main() -> anyhow::Result<()> {
-    let count = Arc::new(AtomicI32::new(0));
-    let ui1 = new_window()?;
-    let ui2 = new_window()?;
+    // The second window is opened in the browser of the first one
+    let ui2 = Arc::new(
+        ui1.new_window(CONTENT)
+            .context("Failed to create new window")?,
+    );
 
     ui1.bind("increment", {
         let count = count.clone();
@@ -1,15 +1,17 @@
 use std::{
     fmt::Display,
-    sync::{Arc, Mutex},
+    sync::{Arc, Condvar, Mutex, Weak},
+    time::Duration,
 };
 
+use base64::Engine;
 use crossbeam_channel::{bounded, Sender};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 
 mod devtools;
-use devtools::{readloop, recv_msg, send, send_msg};
+use devtools::{readloop, recv_msg, send, send_browser, send_msg, send_with_timeout};
 mod os;
 #[cfg(target_family = "windows")]
 use os::close_process_handle;
@@ -26,35 +28,125 @@ pub type JSObject = serde_json::Value;
 /// * An error type is returned
 pub type JSResult = Result<JSObject, JSObject>;
 
-/// An error from chrome in JSON format
-#[derive(Debug)]
-pub struct JSError(JSObject);
+/// An error when communicating with chrome
+#[derive(Debug, Clone)]
+pub enum JSError {
+    /// Chrome rejected the DevTools protocol command
+    Protocol {
+        /// The error code, for example -32000 for a generic server error
+        code: i64,
+        /// The error message
+        message: String,
+        /// Additional details about the error, if any
+        data: Option<String>,
+    },
+    /// The JS code threw an exception or returned a rejected promise
+    Exception {
+        /// The text of the exception details, for example "Uncaught"
+        text: String,
+        /// Line number of the exception location (0-based)
+        line: i64,
+        /// Column number of the exception location (0-based)
+        column: i64,
+        /// The thrown value, or its description if it can not be serialized
+        value: JSObject,
+    },
+    /// Reading from or writing to the pipe to chrome failed
+    Transport(String),
+    /// The browser exited and closed the pipe
+    BrowserExited,
+    /// The page was closed or crashed
+    TargetDestroyed,
+    /// Chrome did not answer within the given duration
+    Timeout(Duration),
+    /// Any other error in JSON format
+    Value(JSObject),
+}
 impl JSError {
+    /// Converts the error to a JS object. Exceptions become the thrown value and the other
+    /// errors a string describing them.
     pub fn source(self) -> JSObject {
-        self.0
+        match self {
+            JSError::Value(v) => v,
+            JSError::Exception { value, .. } => value,
+            JSError::Protocol { message, .. } => JSObject::String(message),
+            e => JSObject::String(e.to_string()),
+        }
+    }
+
+    /// Returns true if the browser or page is gone, so that retrying the call is useless and the
+    /// window has to be created again.
+    pub fn needs_restart(&self) -> bool {
+        matches!(
+            self,
+            JSError::Transport(_) | JSError::BrowserExited | JSError::TargetDestroyed
+        )
+    }
+
+    pub(crate) fn from_exception_details(details: &JSObject) -> Self {
+        let exception = &details["exception"];
+        let value = if exception["value"] != JSObject::Null {
+            exception["value"].clone()
+        } else {
+            exception["description"].clone()
+        };
+        JSError::Exception {
+            text: details["text"].as_str().unwrap_or_default().to_string(),
+            line: details["lineNumber"].as_i64().unwrap_or_default(),
+            column: details["columnNumber"].as_i64().unwrap_or_default(),
+            value,
+        }
+    }
+
+    pub(crate) fn from_protocol_error(error: &JSObject) -> Self {
+        JSError::Protocol {
+            code: error["code"].as_i64().unwrap_or_default(),
+            message: error["message"].as_str().unwrap_or_default().to_string(),
+            data: error["data"].as_str().map(str::to_string),
+        }
     }
 }
 impl std::error::Error for JSError {}
 impl Display for JSError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match self {
+            JSError::Protocol {
+                code,
+                message,
+                data,
+            } => {
+                write!(f, "Protocol error {}: {}", code, message)?;
+                if let Some(data) = data {
+                    write!(f, " ({})", data)?;
+                }
+                Ok(())
+            }
+            JSError::Exception {
+                text,
+                line,
+                column,
+                value,
+            } => write!(f, "{} {} at {}:{}", text, value, line, column),
+            JSError::Transport(e) => write!(f, "Cannot communicate with chrome: {}", e),
+            JSError::BrowserExited => write!(f, "The browser has exited"),
+            JSError::TargetDestroyed => write!(f, "The page was closed or crashed"),
+            JSError::Timeout(t) => write!(f, "No response from chrome within {:?}", t),
+            JSError::Value(v) => write!(f, "{}", v),
+        }
     }
 }
 impl From<JSObject> for JSError {
     fn from(o: JSObject) -> Self {
-        Self(o)
+        Self::Value(o)
     }
 }
 
 trait ToResultOfJSError {
     fn to_result_of_jserror(self) -> Result<(), JSError>;
 }
-impl ToResultOfJSError for JSResult {
+impl ToResultOfJSError for Result<JSObject, JSError> {
     fn to_result_of_jserror(self) -> Result<(), JSError> {
-        match self {
-            Ok(_) => Ok(()),
-            Err(e) => Err(e.into()),
-        }
+        self.map(|_| ())
     }
 }
 
@@ -100,11 +192,278 @@ impl BindingContext {
 impl Drop for BindingContext {
     fn drop(&mut self) {
         if let Some(incomplete) = self.active.take() {
-            complete_binding(incomplete, Ok(JSObject::Null))
+            // Dropped while unwinding from a panic in the binding function: reject the promise
+            // instead of resolving it with null.
+            let result = if std::thread::panicking() {
+                Err(JSObject::String("Rust binding panicked".to_string()))
+            } else {
+                Ok(JSObject::Null)
+            };
+            complete_binding(incomplete, result)
+        }
+    }
+}
+
+/// A request paused by [`UI::intercept`](crate::UI::intercept)
+#[derive(Debug, Clone)]
+pub struct InterceptedRequest {
+    id: String,
+    /// The URL of the request, including the fragment
+    pub url: String,
+    /// The HTTP method, for example "GET"
+    pub method: String,
+    /// The request headers
+    pub headers: Vec<(String, String)>,
+    /// How the page uses the resource, for example "Document", "Stylesheet" or "Font"
+    pub resource_type: String,
+    /// The response, if the request was paused at [`InterceptStage::Response`]
+    pub response: Option<InterceptedResponse>,
+}
+
+/// The response of a request paused at [`InterceptStage::Response`]
+#[derive(Debug, Clone)]
+pub struct InterceptedResponse {
+    /// The HTTP status code, or `None` if the request failed
+    pub status: Option<u16>,
+    /// The response headers
+    pub headers: Vec<(String, String)>,
+    /// The network error if the request failed, for example "InternetDisconnected" or
+    /// "NameNotResolved"
+    pub error: Option<String>,
+    /// The body of a successful (2xx) response. It is `None` for other responses or if the
+    /// browser could not provide it.
+    pub body: Option<Vec<u8>>,
+}
+
+/// When a request is paused by [`UI::intercept_at`](crate::UI::intercept_at)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum InterceptStage {
+    /// Before the request is sent
+    Request,
+    /// After the response headers were received, or the request failed
+    Response,
+}
+
+impl InterceptedRequest {
+    fn from_params(params: &JSObject) -> Self {
+        let request = &params["request"];
+        let headers = request["headers"]
+            .as_object()
+            .map(|headers| {
+                headers
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.as_str().unwrap_or_default().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // 暂停在响应阶段的请求带有 responseStatusCode 或 responseErrorReason
+        let error = params["responseErrorReason"].as_str().map(str::to_string);
+        let status = params["responseStatusCode"].as_u64().map(|s| s as u16);
+        let response = (error.is_some() || status.is_some()).then(|| InterceptedResponse {
+            status,
+            headers: params["responseHeaders"]
+                .as_array()
+                .map(|headers| {
+                    headers
+                        .iter()
+                        .map(|h| {
+                            (
+                                h["name"].as_str().unwrap_or_default().to_string(),
+                                h["value"].as_str().unwrap_or_default().to_string(),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            error,
+            body: None,
+        });
+        InterceptedRequest {
+            id: params["requestId"].as_str().unwrap_or_default().to_string(),
+            url: request["url"].as_str().unwrap_or_default().to_string(),
+            method: request["method"].as_str().unwrap_or_default().to_string(),
+            headers,
+            resource_type: params["resourceType"].as_str().unwrap_or_default().to_string(),
+            response,
+        }
+    }
+
+    /// Returns true if the URL matches `pattern`, using the same wildcards as the patterns given
+    /// to [`UI::intercept`](crate::UI::intercept): `*` matches any number of characters, `?`
+    /// exactly one, and a backslash escapes the next character.
+    pub fn matches(&self, pattern: &str) -> bool {
+        url_matches(pattern, &self.url)
+    }
+}
+
+/// What to do with an [`InterceptedRequest`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterceptAction {
+    /// Send the request unchanged, or at [`InterceptStage::Response`] pass the response on to the
+    /// page
+    Continue,
+    /// Send the request with some of its parts replaced. The page does not see the change of
+    /// URL, so it can not be used to redirect. Only allowed at [`InterceptStage::Request`].
+    Modify {
+        /// The new URL, which must have the same scheme
+        url: Option<String>,
+        /// The new HTTP method
+        method: Option<String>,
+        /// The new request headers, replacing all the original ones
+        headers: Option<Vec<(String, String)>>,
+    },
+    /// Answer the request without contacting the server, or replace the response
+    Fulfill {
+        /// The HTTP status code, for example 200
+        status: u16,
+        /// The response headers, for example `("Content-Type", "text/css")`
+        headers: Vec<(String, String)>,
+        /// The response body
+        body: Vec<u8>,
+    },
+    /// Fail the request with a network error, for example "Failed", "BlockedByClient" or
+    /// "InternetDisconnected"
+    Fail(String),
+}
+
+type InterceptFunc = Arc<dyn Fn(&InterceptedRequest) -> InterceptAction + Sync + Send>;
+
+/// Image format of the frames captured with [`UI::start_screencast`](crate::UI::start_screencast)
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScreencastFormat {
+    Jpeg,
+    Png,
+}
+
+/// A frame of the page captured with [`UI::start_screencast`](crate::UI::start_screencast)
+#[derive(Debug, Clone)]
+pub struct ScreencastFrame {
+    /// The encoded image, in the format passed to `start_screencast`
+    pub data: Vec<u8>,
+    /// Width of the viewport in CSS pixels when the frame was captured
+    pub device_width: f64,
+    /// Height of the viewport in CSS pixels when the frame was captured
+    pub device_height: f64,
+    /// When the frame was captured, in seconds since the epoch, if the browser reports it
+    pub timestamp: Option<f64>,
+}
+
+type ScreencastFunc = Arc<dyn Fn(ScreencastFrame) + Sync + Send>;
+
+/// What happened in a [`MouseEvent`]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MouseEventKind {
+    MousePressed,
+    MouseReleased,
+    MouseMoved,
+    MouseWheel,
+}
+
+/// A mouse button in a [`MouseEvent`]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MouseButton {
+    None,
+    Left,
+    Middle,
+    Right,
+    Back,
+    Forward,
+}
+
+/// A mouse event sent to the page with [`UI::dispatch_mouse_event`](crate::UI::dispatch_mouse_event),
+/// handled as if the user used the mouse on the viewport
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct MouseEvent {
+    #[serde(rename = "type")]
+    pub kind: MouseEventKind,
+    /// x coordinate in CSS pixels from the left of the viewport
+    pub x: f64,
+    /// y coordinate in CSS pixels from the top of the viewport
+    pub y: f64,
+    /// The button pressed or released, `MouseButton::None` for moves and wheel events
+    pub button: MouseButton,
+    /// The buttons held down: 1 for left, 2 for right, 4 for middle, 8 for back and 16 for
+    /// forward, added together
+    pub buttons: u32,
+    /// 1 for a single click, 2 for a double click and so on; 0 for moves and wheel events
+    pub click_count: u32,
+    /// Horizontal scroll of a wheel event in CSS pixels
+    pub delta_x: f64,
+    /// Vertical scroll of a wheel event in CSS pixels
+    pub delta_y: f64,
+}
+
+impl MouseEvent {
+    /// A `MouseMoved` event at (`x`, `y`) with the given `buttons` held down
+    pub fn moved(x: f64, y: f64, buttons: u32) -> Self {
+        MouseEvent {
+            kind: MouseEventKind::MouseMoved,
+            x,
+            y,
+            button: MouseButton::None,
+            buttons,
+            click_count: 0,
+            delta_x: 0.0,
+            delta_y: 0.0,
         }
     }
 }
 
+fn url_matches(pattern: &str, url: &str) -> bool {
+    enum Token {
+        Any,
+        One,
+        Char(char),
+    }
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(ch) = chars.next() {
+        tokens.push(match ch {
+            '*' => Token::Any,
+            '?' => Token::One,
+            '\\' => Token::Char(chars.next().unwrap_or('\\')),
+            ch => Token::Char(ch),
+        });
+    }
+    let text: Vec<char> = url.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // 最近一个 * 的位置和它当前匹配到的文本位置，匹配失败时让它多吃一个字符再试
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match tokens.get(p) {
+            Some(Token::One) => {
+                p += 1;
+                t += 1;
+            }
+            Some(Token::Char(ch)) if *ch == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            Some(Token::Any) => {
+                star = Some((p, t));
+                p += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    p = sp + 1;
+                    t = st + 1;
+                    star = Some((sp, st + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[p..].iter().all(|token| matches!(token, Token::Any))
+}
+
 struct ActiveBindingContext {
     chrome: Arc<Chrome>,
     payload: JSObject,
@@ -113,7 +472,15 @@ struct ActiveBindingContext {
 
 type BindingFunc = Arc<dyn Fn(BindingContext) + Sync + Send>;
 
-pub struct Chrome {
+/// A command waiting for its response. `session` is `None` for commands sent to the browser
+/// itself instead of to a page.
+struct Pending {
+    session: Option<String>,
+    reschan: Sender<Result<JSObject, JSError>>,
+}
+
+/// The browser process and the pipe to it, shared by all the pages opened in it.
+pub struct Browser {
     id: AtomicI32,
     #[cfg(target_family = "unix")]
     pid: Process,
@@ -121,12 +488,60 @@ pub struct Chrome {
     pid: usize,
     psend: Mutex<PipeWriter>,
     precv: Mutex<PipeReader>,
+    _kill_send: Sender<()>,
+    pending: dashmap::DashMap<i32, Pending>,
+    closed: Mutex<Option<JSError>>,
+    /// Notified whenever the browser or one of its pages is closed, see [`Chrome::wait_closed`]
+    closed_signal: Condvar,
+    headless: bool,
+    version: once_cell::sync::OnceCell<BrowserVersion>,
+    /// The attached pages by session id, used to route events and binding calls
+    targets: dashmap::DashMap<String, Weak<Chrome>>,
+}
+
+/// A page (window) of the browser with its own DevTools session.
+pub struct Chrome {
+    browser: Arc<Browser>,
     target: String,
     session: String,
-    _kill_send: Sender<()>,
-    pending: dashmap::DashMap<i32, Sender<JSResult>>,
+    closed: Mutex<Option<JSError>>,
+    timeout: Option<Duration>,
+    crashed: AtomicBool,
     window: AtomicI32,
     bindings: dashmap::DashMap<String, BindingFunc>,
+    interceptor: Mutex<Option<InterceptFunc>>,
+    screencast: Mutex<Option<ScreencastFunc>>,
+    /// Identifier of the script that applies the zoom set with [`set_zoom`] to new documents
+    zoom_script: Mutex<Option<String>>,
+    /// The clock set with [`set_virtual_time`], and the script that installs it in new documents
+    clock: Mutex<Option<VirtualClock>>,
+    clock_script: Mutex<Option<String>>,
+}
+
+/// The virtual time of a page is `base` milliseconds since the epoch at the real time `since`,
+/// and goes on from there if `ticking`
+#[derive(Debug, Clone, Copy)]
+struct VirtualClock {
+    base: f64,
+    since: f64,
+    ticking: bool,
+}
+
+impl VirtualClock {
+    fn now(&self) -> f64 {
+        if self.ticking {
+            self.base + (real_now() - self.since)
+        } else {
+            self.base
+        }
+    }
+}
+
+fn real_now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
 }
 
 /// A struct that stores the size, position and window state of the browser window.
@@ -146,6 +561,47 @@ pub struct Bounds {
     pub window_state: WindowState,
 }
 
+/// Screen metrics emulated for the page with [`UI::set_device_metrics`](crate::UI::set_device_metrics)
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DeviceMetrics {
+    /// Width of the viewport in CSS pixels, 0 to keep the width of the window
+    pub width: i32,
+    /// Height of the viewport in CSS pixels, 0 to keep the height of the window
+    pub height: i32,
+    /// The ratio of device pixels to CSS pixels (`window.devicePixelRatio`), 0 to keep the one of
+    /// the display
+    pub device_scale_factor: f64,
+    /// Whether to emulate a mobile device, with a meta viewport and overlay scrollbars
+    pub mobile: bool,
+}
+
+/// The version information reported by the browser with `Browser.getVersion`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct BrowserVersion {
+    /// Product name and version, for example "Chrome/120.0.6099.109"
+    pub product: String,
+    /// The DevTools protocol version
+    pub protocol_version: String,
+    /// Revision of the browser build
+    pub revision: String,
+    /// The default user agent
+    pub user_agent: String,
+    /// Version of the V8 JavaScript engine
+    pub js_version: String,
+}
+
+impl BrowserVersion {
+    /// The major version of the browser, for example 120 for "Chrome/120.0.6099.109"
+    pub fn major(&self) -> Option<u32> {
+        let version = self.product.rsplit('/').next()?;
+        version.split('.').next()?.parse().ok()
+    }
+}
+
 /// The state of the window
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -171,7 +627,11 @@ impl WindowState {
 }
 
 impl Chrome {
-    pub fn new_with_args(chrome_binary: &str, args: &[&str]) -> Result<Arc<Chrome>, JSError> {
+    pub fn new_with_args(
+        chrome_binary: &str,
+        args: &[&str],
+        timeout: Option<Duration>,
+    ) -> Result<Arc<Chrome>, JSError> {
         println!("\n========== [alcro-debug] 启动浏览器 ==========");
         println!("[alcro-debug] 浏览器路径: {}", chrome_binary);
         println!("[alcro-debug] 启动参数:");
@@ -180,34 +640,35 @@ impl Chrome {
         }
         println!("==========================================\n");
         let (pid, precv, psend) =
-            new_process(chrome_binary, &args).expect("Unable to launch chrome");
+            new_process(chrome_binary, args).expect("Unable to launch chrome");
         let (_kill_send, _kill_recv) = bounded(1);
 
-        let mut c = Chrome {
+        let browser = Browser {
             id: AtomicI32::new(2),
             precv: Mutex::new(precv),
             psend: Mutex::new(psend),
-            target: String::new(),
-            session: String::new(),
             _kill_send,
             pending: dashmap::DashMap::new(),
-            bindings: dashmap::DashMap::new(),
-            window: AtomicI32::new(0),
+            closed: Mutex::new(None),
+            closed_signal: Condvar::new(),
+            headless: args.contains(&"--headless"),
+            version: once_cell::sync::OnceCell::new(),
+            targets: dashmap::DashMap::new(),
             #[cfg(target_family = "windows")]
             pid: pid as usize,
             #[cfg(target_family = "unix")]
-            pid: pid,
+            pid,
         };
 
         // 只保留关键日志
         // println!("[alcro-debug] => 调用 find_target...");
-        c.target = c.find_target();
-        println!("[alcro-debug] Target found: {}", c.target);
+        let target = browser.find_target()?;
+        println!("[alcro-debug] Target found: {}", target);
         // println!("[alcro-debug] => 调用 start_session...");
-        c.session = c.start_session()?;
-        println!("[alcro-debug] Session started: {}", c.session);
+        let session = browser.start_session(&target)?;
+        println!("[alcro-debug] Session started: {}", session);
 
-        let c_arc = Arc::new(c);
+        let browser = Arc::new(browser);
 
         #[cfg(target_family = "unix")]
         std::thread::spawn(move || {
@@ -215,11 +676,55 @@ impl Chrome {
             kill_proc(pid).expect("Unable to kill process");
         });
 
-        let c_arc_clone = c_arc.clone();
-        std::thread::spawn(move || readloop(c_arc_clone));
+        let browser_clone = browser.clone();
+        std::thread::spawn(move || readloop(browser_clone));
+
+        match send_browser(&browser, "Browser.getVersion", &json!({}), timeout) {
+            Ok(v) => match serde_json::from_value::<BrowserVersion>(v) {
+                Ok(version) => {
+                    println!("[alcro-debug] 浏览器版本: {}", version.product);
+                    let _ = browser.version.set(version);
+                }
+                Err(e) => eprintln!("[alcro-debug] 无法解析浏览器版本: {}", e),
+            },
+            Err(e) => eprintln!("[alcro-debug] 获取浏览器版本失败: {}", e),
+        }
+
+        Chrome::attach(browser, target, session, timeout)
+    }
+
+    /// Registers the page so that the read loop routes its messages here, then enables the
+    /// DevTools domains alcro needs.
+    fn attach(
+        browser: Arc<Browser>,
+        target: String,
+        session: String,
+        timeout: Option<Duration>,
+    ) -> Result<Arc<Chrome>, JSError> {
+        let headless = browser.headless;
+        let c_arc = Arc::new(Chrome {
+            browser,
+            target,
+            session,
+            closed: Mutex::new(None),
+            timeout,
+            crashed: AtomicBool::new(false),
+            bindings: dashmap::DashMap::new(),
+            interceptor: Mutex::new(None),
+            screencast: Mutex::new(None),
+            zoom_script: Mutex::new(None),
+            clock: Mutex::new(None),
+            clock_script: Mutex::new(None),
+            window: AtomicI32::new(0),
+        });
+        c_arc
+            .browser
+            .targets
+            .insert(c_arc.session.clone(), Arc::downgrade(&c_arc));
 
         for (method, args) in [
             ("Page.enable", JSObject::Null),
+            ("Inspector.enable", JSObject::Null),
             (
                 "Target.setAutoAttach",
                 json!({"autoAttach": true, "waitForDebuggerOnStart": false}),
@@ -241,7 +746,7 @@ impl Chrome {
             send(Arc::clone(&c_arc), method, args)?;
         }
 
-        if !args.contains(&"--headless") {
+        if !headless {
             // 只保留 Target.targetDestroyed 关键日志
             println!("[alcro-debug] 获取窗口ID...");
             let win_id = get_window_for_target(Arc::clone(&c_arc))?;
@@ -251,7 +756,47 @@ impl Chrome {
         Ok(c_arc)
     }
 
-    fn find_target(&self) -> String {
+    /// Returns true if the browser process exited
+    pub fn done(&self) -> bool {
+        self.browser.done()
+    }
+
+    /// Returns true if the page was closed or crashed, or the browser is gone
+    pub fn closed(&self) -> bool {
+        self.closed.lock().expect("Unable to lock").is_some()
+            || self.browser.closed.lock().expect("Unable to lock").is_some()
+    }
+
+    /// Blocks until the page is closed or crashed, or the browser is gone
+    pub fn wait_closed(&self) {
+        let mut browser_closed = self.browser.closed.lock().expect("Unable to lock");
+        while browser_closed.is_none() && self.closed.lock().expect("Unable to lock").is_none() {
+            browser_closed = self
+                .browser
+                .closed_signal
+                .wait(browser_closed)
+                .expect("Unable to lock");
+        }
+    }
+
+    /// Returns true if the renderer of the page crashed. The browser process may still be
+    /// running, but the page will not respond anymore.
+    pub fn crashed(&self) -> bool {
+        self.crashed.load(Ordering::Relaxed)
+    }
+
+    /// The version reported by the browser at startup, if it could be queried
+    pub fn version(&self) -> Option<&BrowserVersion> {
+        self.browser.version.get()
+    }
+
+    pub fn wait_finish(&self) {
+        self.browser.wait_finish()
+    }
+}
+
+impl Browser {
+    fn find_target(&self) -> Result<String, JSError> {
         // 只保留 Target.setDiscoverTargets 关键日志
         println!("[alcro-debug] 发送 Target.setDiscoverTargets...");
         send_msg(
@@ -264,32 +809,30 @@ impl Chrome {
             }
             )
             .to_string(),
-        );
+        )?;
 
         loop {
-            let raw = recv_msg(&self.precv);
+            let raw = recv_msg(&self.precv)?;
             // 只保留 Target.targetDestroyed 关键日志
             // println!("    [alcro-debug] find_target 收到: {}", raw);
-            let pmsg: JSObject = match serde_json::from_str(&raw) {
-                Ok(obj) => obj,
-                Err(_) => {
-                    break;
-                }
-            };
+            let pmsg: JSObject = serde_json::from_str(&raw)
+                .map_err(|e| JSError::Transport(format!("Invalid JSON: {}", e)))?;
+            if pmsg["id"] == 0 && pmsg["error"] != JSObject::Null {
+                return Err(JSError::from_protocol_error(&pmsg["error"]));
+            }
             if pmsg["method"] == "Target.targetCreated" {
                 let params = &pmsg["params"];
                 if params["targetInfo"]["type"] == "page" {
-                    return params["targetInfo"]["targetId"]
+                    return Ok(params["targetInfo"]["targetId"]
                         .as_str()
                         .expect("Value not of string datatype")
-                        .to_string();
+                        .to_string());
                 }
             }
         }
-        String::new()
     }
 
-    fn start_session(&self) -> Result<String, JSError> {
+    fn start_session(&self, target: &str) -> Result<String, JSError> {
         // 只保留 Target.attachToTarget 关键日志
         println!("[alcro-debug] 发送 Target.attachToTarget...");
         send_msg(
@@ -298,23 +841,19 @@ impl Chrome {
             {
             "id": 1,
             "method": "Target.attachToTarget",
-            "params": {"targetId": self.target}
+            "params": {"targetId": target}
             }
             )
             .to_string(),
-        );
+        )?;
 
         loop {
-            let raw = recv_msg(&self.precv);
-            let pmsg: JSObject = match serde_json::from_str(&raw) {
-                Ok(obj) => obj,
-                Err(_) => {
-                    break;
-                }
-            };
+            let raw = recv_msg(&self.precv)?;
+            let pmsg: JSObject = serde_json::from_str(&raw)
+                .map_err(|e| JSError::Transport(format!("Invalid JSON: {}", e)))?;
             if pmsg["id"] == 1 {
                 if pmsg["error"] != JSObject::Null {
-                    return Err(pmsg["error"].clone().into());
+                    return Err(JSError::from_protocol_error(&pmsg["error"]));
                 }
                 let session = &pmsg["result"];
                 return Ok(session["sessionId"]
@@ -323,7 +862,6 @@ impl Chrome {
                     .to_string());
             }
         }
-        Err(JSError(json!({"error": "start_session failed"})))
     }
 
     pub fn done(&self) -> bool {
@@ -335,7 +873,7 @@ impl Chrome {
     }
 }
 
-fn get_window_for_target(c: Arc<Chrome>) -> Result<i32, JSObject> {
+fn get_window_for_target(c: Arc<Chrome>) -> Result<i32, JSError> {
     match send(
         Arc::clone(&c),
         "Browser.getWindowForTarget",
@@ -352,16 +890,97 @@ pub fn load(c: Arc<Chrome>, url: &str) -> Result<(), JSError> {
     send(Arc::clone(&c), "Page.navigate", &json!({ "url": url })).to_result_of_jserror()
 }
 
-pub fn eval(c: Arc<Chrome>, expr: &str) -> JSResult {
-    send(
+/// Opens a new window showing `url` in the browser of `c`. The new page has its own session,
+/// bindings and window but shares the browser process.
+pub fn new_window(c: Arc<Chrome>, url: &str) -> Result<Arc<Chrome>, JSError> {
+    let browser = Arc::clone(&c.browser);
+    let created = send_browser(
+        &browser,
+        "Target.createTarget",
+        &json!({ "url": url, "newWindow": true }),
+        c.timeout,
+    )?;
+    let target = created["targetId"]
+        .as_str()
+        .expect("Value not of string datatype")
+        .to_string();
+    let attached = send_browser(
+        &browser,
+        "Target.attachToTarget",
+        &json!({ "targetId": target }),
+        c.timeout,
+    )?;
+    let session = attached["sessionId"]
+        .as_str()
+        .expect("Value not of string datatype")
+        .to_string();
+    println!("[alcro-debug] New window target: {}, session: {}", target, session);
+    Chrome::attach(browser, target, session, c.timeout)
+}
+
+/// Reloads the page bypassing the cache. The command is handled by the browser process, so it
+/// also works when the page itself does not respond.
+pub fn reload(c: Arc<Chrome>, timeout: Option<Duration>) -> Result<(), JSError> {
+    let timeout = timeout.or(c.timeout);
+    send_with_timeout(c, "Page.reload", &json!({ "ignoreCache": true }), timeout)
+        .to_result_of_jserror()
+}
+
+/// Evaluates `expr`, waiting at most `timeout` for the result. `None` falls back to the default
+/// timeout of the browser.
+pub fn eval(c: Arc<Chrome>, expr: &str, timeout: Option<Duration>) -> Result<JSObject, JSError> {
+    let timeout = timeout.or(c.timeout);
+    send_with_timeout(
         c,
         "Runtime.evaluate",
         &json!({
             "expression": expr, "awaitPromise": true, "returnByValue": true
         }),
+        timeout,
     )
 }
 
+/// Dispatches the event from [`emit`]; `this` is the `window` of the page.
+const EMIT_FUNCTION: &str = "function (name, detail) { \
+    return this.dispatchEvent(new CustomEvent(name, { detail, cancelable: true })); }";
+
+/// Dispatches a cancelable `CustomEvent` named `name` on `window` with `detail` as its data.
+/// Returns false if a listener called `preventDefault`.
+pub fn emit(c: Arc<Chrome>, name: &str, detail: &JSObject) -> Result<bool, JSError> {
+    // The name and detail are passed as arguments of a fixed function instead of being written
+    // into the script, so their content never changes the code that runs
+    let window = send(
+        Arc::clone(&c),
+        "Runtime.evaluate",
+        &json!({ "expression": "window" }),
+    )?;
+    let object_id = match window["objectId"].as_str() {
+        Some(id) => id.to_string(),
+        None => return Err(JSError::Value(window)),
+    };
+    let result = send(
+        Arc::clone(&c),
+        "Runtime.callFunctionOn",
+        &json!({
+            "functionDeclaration": EMIT_FUNCTION,
+            "objectId": object_id,
+            "arguments": [{ "value": name }, { "value": detail }],
+            "returnByValue": true
+        }),
+    );
+    let _ = send(c, "Runtime.releaseObject", &json!({ "objectId": object_id }));
+    result.map(|dispatched| dispatched.as_bool().unwrap_or(true))
+}
+
+pub fn dispatch_mouse_event(c: Arc<Chrome>, event: MouseEvent) -> Result<(), JSError> {
+    send(
+        c,
+        "Input.dispatchMouseEvent",
+        &serde_json::to_value(event).unwrap(),
+    )
+    .to_result_of_jserror()
+}
+
 pub fn set_bounds(c: Arc<Chrome>, b: Bounds) -> Result<(), JSError> {
     let param = json!({
         "windowId": c.window,
@@ -384,7 +1003,7 @@ pub fn bounds(c: Arc<Chrome>) -> Result<Bounds, JSObject> {
             "windowId": c.window.load(Ordering::Relaxed)
         }),
     ) {
-        Err(e) => Err(e),
+        Err(e) => Err(e.source()),
         Ok(result) => {
             let ret: Bounds = serde_json::from_value(result["bounds"].clone())
                 .expect("Value not of bounds datatype");
@@ -393,35 +1012,185 @@ pub fn bounds(c: Arc<Chrome>) -> Result<Bounds, JSObject> {
     }
 }
 
+pub fn set_device_metrics(c: Arc<Chrome>, metrics: DeviceMetrics) -> Result<(), JSError> {
+    send(
+        c,
+        "Emulation.setDeviceMetricsOverride",
+        &serde_json::to_value(metrics).unwrap(),
+    )
+    .to_result_of_jserror()
+}
+
+pub fn clear_device_metrics(c: Arc<Chrome>) -> Result<(), JSError> {
+    send(c, "Emulation.clearDeviceMetricsOverride", &json!({})).to_result_of_jserror()
+}
+
+pub fn set_timezone(c: Arc<Chrome>, timezone_id: &str) -> Result<(), JSError> {
+    send(
+        c,
+        "Emulation.setTimezoneOverride",
+        &json!({ "timezoneId": timezone_id }),
+    )
+    .to_result_of_jserror()
+}
+
+/// Replaces the script in `slot` that is evaluated in every new document of the page with
+/// `script`, and evaluates `script` in the current document
+fn replace_document_script(
+    c: Arc<Chrome>,
+    slot: &Mutex<Option<String>>,
+    script: &str,
+    keep: bool,
+) -> Result<(), JSError> {
+    let mut slot = slot.lock().expect("Unable to lock");
+    if let Some(identifier) = slot.take() {
+        send(
+            Arc::clone(&c),
+            "Page.removeScriptToEvaluateOnNewDocument",
+            &json!({ "identifier": identifier }),
+        )?;
+    }
+    if keep {
+        let added = send(
+            Arc::clone(&c),
+            "Page.addScriptToEvaluateOnNewDocument",
+            &json!({ "source": script }),
+        )?;
+        *slot = added["identifier"].as_str().map(str::to_string);
+    }
+    drop(slot);
+    eval(c, script, None).to_result_of_jserror()
+}
+
+/// Zooms the page like the zoom of the browser, with the CSS `zoom` of the root element. The
+/// DevTools protocol has no command for the real browser zoom.
+pub fn set_zoom(c: Arc<Chrome>, factor: f64) -> Result<(), JSError> {
+    let zoom = if factor == 1.0 {
+        String::new()
+    } else {
+        factor.to_string()
+    };
+    // 新文档的脚本执行时根元素可能还不存在，等它出现后再设置
+    let script = format!(
+        r"(()=>{{
+        const apply = () => {{ document.documentElement.style.zoom = '{zoom}'; }};
+        if (document.documentElement) {{
+            apply();
+        }} else {{
+            new MutationObserver((_, observer) => {{
+                if (document.documentElement) {{
+                    observer.disconnect();
+                    apply();
+                }}
+            }}).observe(document, {{ childList: true }});
+        }}
+        }})();",
+        zoom = zoom
+    );
+    let chrome = Arc::clone(&c);
+    replace_document_script(c, &chrome.zoom_script, &script, factor != 1.0)
+}
+
+/// Installs `clock` in the page by replacing `Date`, or restores the real `Date` with `None`.
+/// Timers such as `setTimeout` keep running in real time.
+fn install_clock(c: Arc<Chrome>, clock: Option<VirtualClock>) -> Result<(), JSError> {
+    let script = match clock {
+        Some(clock) => format!(
+            r"(()=>{{
+        const clock = window.__alcroClock || (window.__alcroClock = {{ RealDate: Date }});
+        Object.assign(clock, {{ base: {base}, since: {since}, ticking: {ticking} }});
+        if (clock.installed) return;
+        clock.installed = true;
+        const RealDate = clock.RealDate;
+        const realNow = RealDate.now;
+        const now = () => clock.ticking ? clock.base + (realNow() - clock.since) : clock.base;
+        class VirtualDate extends RealDate {{
+            constructor(...args) {{
+                if (args.length === 0) {{
+                    super(now());
+                }} else {{
+                    super(...args);
+                }}
+            }}
+            static now() {{
+                return Math.floor(now());
+            }}
+        }}
+        // Date() called without new returns the current time as a string
+        window.Date = new Proxy(VirtualDate, {{ apply: () => new VirtualDate().toString() }});
+        }})();",
+            base = clock.base,
+            since = clock.since,
+            ticking = clock.ticking
+        ),
+        None => r"(()=>{
+        if (window.__alcroClock && window.__alcroClock.installed) {
+            window.Date = window.__alcroClock.RealDate;
+        }
+        delete window.__alcroClock;
+        })();"
+            .to_string(),
+    };
+    *c.clock.lock().expect("Unable to lock") = clock;
+    let chrome = Arc::clone(&c);
+    replace_document_script(c, &chrome.clock_script, &script, clock.is_some())
+}
+
+/// Sets the virtual time of the page to `time`, parsed by `Date.parse` in the page
+pub fn set_virtual_time(c: Arc<Chrome>, time: &str, ticking: bool) -> Result<(), JSError> {
+    let parsed = eval(
+        Arc::clone(&c),
+        &format!("Date.parse({})", JSObject::String(time.to_string())),
+        None,
+    )?;
+    let base = parsed
+        .as_f64()
+        .ok_or_else(|| JSError::from(JSObject::String(format!("Invalid time: {}", time))))?;
+    let clock = VirtualClock {
+        base,
+        since: real_now(),
+        ticking,
+    };
+    install_clock(c, Some(clock))
+}
+
+/// Moves the virtual time forward, starting from the real time if there is no virtual clock
+pub fn advance_virtual_time(c: Arc<Chrome>, by: Duration) -> Result<(), JSError> {
+    let current = *c.clock.lock().expect("Unable to lock");
+    let now = real_now();
+    let clock = VirtualClock {
+        base: current.map_or(now, |clock| clock.now()) + by.as_secs_f64() * 1000.0,
+        since: now,
+        ticking: current.is_none_or(|clock| clock.ticking),
+    };
+    install_clock(c, Some(clock))
+}
+
+pub fn clear_virtual_time(c: Arc<Chrome>) -> Result<(), JSError> {
+    install_clock(c, None)
+}
+
 pub fn load_js(c: Arc<Chrome>, script: &str) -> Result<(), JSError> {
-    if let Err(e) = send(
+    send(
         Arc::clone(&c),
         "Page.addScriptToEvaluateOnNewDocument",
         &json!({ "source": script }),
-    ) {
-        return Err(e.into());
-    }
-    eval(Arc::clone(&c), &script).to_result_of_jserror()
+    )?;
+    eval(c, script, None).to_result_of_jserror()
 }
 
 pub fn load_css(c: Arc<Chrome>, css: &str) -> Result<(), JSError> {
-    let frame_tree = match send(
+    let frame_tree = send(
         Arc::clone(&c),
         "Page.getFrameTree",
         &json!({ "targetId": c.target }),
-    ) {
-        Ok(ft) => ft,
-        Err(e) => return Err(e.into()),
-    };
+    )?;
     let frame_id = frame_tree["frameTree"]["frame"]["id"].as_str().unwrap();
-    let style_sheet = match send(
+    let style_sheet = send(
         Arc::clone(&c),
         "CSS.createStyleSheet",
         &json!({ "frameId": frame_id }),
-    ) {
-        Ok(ss) => ss,
-        Err(e) => return Err(e.into()),
-    };
+    )?;
     let style_sheet_id = style_sheet["styleSheetId"].as_str().unwrap();
     send(
         Arc::clone(&c),
@@ -434,13 +1203,11 @@ pub fn load_css(c: Arc<Chrome>, css: &str) -> Result<(), JSError> {
 pub fn bind(c: Arc<Chrome>, name: &str, f: BindingFunc) -> Result<(), JSError> {
     c.bindings.insert(name.to_string(), f);
 
-    if let Err(e) = send(
+    send(
         Arc::clone(&c),
         "Runtime.addBinding",
         &json!({ "name": name }),
-    ) {
-        return Err(e.into());
-    }
+    )?;
 
     let script = format!(
         r"(()=>{{
@@ -471,14 +1238,180 @@ pub fn bind(c: Arc<Chrome>, name: &str, f: BindingFunc) -> Result<(), JSError> {
         name = name
     );
 
-    if let Err(e) = send(
+    send(
         Arc::clone(&c),
         "Page.addScriptToEvaluateOnNewDocument",
         &json!({ "source": script }),
-    ) {
-        return Err(e.into());
+    )?;
+    eval(Arc::clone(&c), &script, None).to_result_of_jserror()
+}
+
+/// Pauses the requests of the page whose URL matches one of `patterns` at the given stage and
+/// lets `f` decide what to do with them
+pub fn intercept(
+    c: Arc<Chrome>,
+    patterns: &[(&str, InterceptStage)],
+    f: InterceptFunc,
+) -> Result<(), JSError> {
+    *c.interceptor.lock().expect("Unable to lock") = Some(f);
+    let patterns: Vec<JSObject> = patterns
+        .iter()
+        .map(|(pattern, stage)| json!({ "urlPattern": pattern, "requestStage": stage }))
+        .collect();
+    send(c, "Fetch.enable", &json!({ "patterns": patterns })).to_result_of_jserror()
+}
+
+pub fn stop_intercepting(c: Arc<Chrome>) -> Result<(), JSError> {
+    send(Arc::clone(&c), "Fetch.disable", &json!({}))?;
+    *c.interceptor.lock().expect("Unable to lock") = None;
+    Ok(())
+}
+
+fn response_body(c: Arc<Chrome>, id: &str) -> Option<Vec<u8>> {
+    let result = match send(c, "Fetch.getResponseBody", &json!({ "requestId": id })) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("[alcro] 无法获取被拦截请求的响应内容: {}", e);
+            return None;
+        }
+    };
+    let body = result["body"].as_str()?;
+    if result["base64Encoded"].as_bool().unwrap_or(false) {
+        base64::engine::general_purpose::STANDARD.decode(body).ok()
+    } else {
+        Some(body.as_bytes().to_vec())
+    }
+}
+
+fn resolve_request(c: Arc<Chrome>, id: &str, action: InterceptAction) -> Result<(), JSError> {
+    fn header_entries(headers: &[(String, String)]) -> Vec<JSObject> {
+        headers
+            .iter()
+            .map(|(name, value)| json!({ "name": name, "value": value }))
+            .collect()
     }
-    eval(Arc::clone(&c), &script).to_result_of_jserror()
+    let (method, params) = match action {
+        InterceptAction::Continue => ("Fetch.continueRequest", json!({ "requestId": id })),
+        InterceptAction::Modify {
+            url,
+            method,
+            headers,
+        } => {
+            let mut params = json!({ "requestId": id });
+            if let Some(url) = url {
+                params["url"] = json!(url);
+            }
+            if let Some(method) = method {
+                params["method"] = json!(method);
+            }
+            if let Some(headers) = headers {
+                params["headers"] = json!(header_entries(&headers));
+            }
+            ("Fetch.continueRequest", params)
+        }
+        InterceptAction::Fulfill {
+            status,
+            headers,
+            body,
+        } => (
+            "Fetch.fulfillRequest",
+            json!({
+                "requestId": id,
+                "responseCode": status,
+                "responseHeaders": header_entries(&headers),
+                "body": base64::engine::general_purpose::STANDARD.encode(body),
+            }),
+        ),
+        InterceptAction::Fail(reason) => (
+            "Fetch.failRequest",
+            json!({ "requestId": id, "errorReason": reason }),
+        ),
+    };
+    send(c, method, &params).to_result_of_jserror()
+}
+
+/// Called by the read loop for `Fetch.requestPaused`. The handler may read files or wait for the
+/// network, so it runs in its own thread; a request it does not resolve would hang the page.
+pub(crate) fn request_paused(c: Arc<Chrome>, params: &JSObject) {
+    let mut request = InterceptedRequest::from_params(params);
+    let interceptor = c.interceptor.lock().expect("Unable to lock").clone();
+    std::thread::spawn(move || {
+        if let Some(ref mut response) = request.response {
+            if response.status.is_some_and(|s| (200..300).contains(&s)) {
+                response.body = response_body(Arc::clone(&c), &request.id);
+            }
+        }
+        let action = match interceptor {
+            Some(f) => std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(&request)))
+                .unwrap_or_else(|_| {
+                    eprintln!("[alcro] 请求拦截函数发生 panic，继续原请求: {}", request.url);
+                    InterceptAction::Continue
+                }),
+            None => InterceptAction::Continue,
+        };
+        if let Err(e) = resolve_request(c, &request.id, action) {
+            eprintln!("[alcro] 无法处理被拦截的请求 {}: {}", request.url, e);
+        }
+    });
+}
+
+/// Starts sending the frames of the page to `f`, scaled down to fit in `max_size` if given
+pub fn start_screencast(
+    c: Arc<Chrome>,
+    format: ScreencastFormat,
+    quality: u8,
+    max_size: Option<(u32, u32)>,
+    f: ScreencastFunc,
+) -> Result<(), JSError> {
+    *c.screencast.lock().expect("Unable to lock") = Some(f);
+    let mut params = json!({ "format": format, "quality": quality });
+    if let Some((width, height)) = max_size {
+        params["maxWidth"] = json!(width);
+        params["maxHeight"] = json!(height);
+    }
+    send(c, "Page.startScreencast", &params).to_result_of_jserror()
+}
+
+pub fn stop_screencast(c: Arc<Chrome>) -> Result<(), JSError> {
+    send(Arc::clone(&c), "Page.stopScreencast", &json!({}))?;
+    *c.screencast.lock().expect("Unable to lock") = None;
+    Ok(())
+}
+
+/// Called by the read loop for `Page.screencastFrame`. The browser sends the next frame only
+/// after this one is acknowledged, so a slow handler lowers the frame rate instead of queueing
+/// frames.
+pub(crate) fn screencast_frame(c: Arc<Chrome>, params: &JSObject) {
+    let handler = c.screencast.lock().expect("Unable to lock").clone();
+    let session_id = params["sessionId"].clone();
+    let data = params["data"].as_str().unwrap_or_default().to_string();
+    let metadata = &params["metadata"];
+    let (device_width, device_height, timestamp) = (
+        metadata["deviceWidth"].as_f64().unwrap_or_default(),
+        metadata["deviceHeight"].as_f64().unwrap_or_default(),
+        metadata["timestamp"].as_f64(),
+    );
+    std::thread::spawn(move || {
+        if let Some(f) = handler {
+            match base64::engine::general_purpose::STANDARD.decode(data) {
+                Ok(data) => {
+                    let frame = ScreencastFrame {
+                        data,
+                        device_width,
+                        device_height,
+                        timestamp,
+                    };
+                    if std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(frame))).is_err() {
+                        eprintln!("[alcro] 画面帧处理函数发生 panic");
+                    }
+                }
+                Err(e) => eprintln!("[alcro] 无法解码画面帧: {}", e),
+            }
+        }
+        if let Err(e) = send(c, "Page.screencastFrameAck", &json!({ "sessionId": session_id })) {
+            eprintln!("[alcro] 无法确认画面帧: {}", e);
+        }
+    });
 }
 
 fn complete_binding(context: ActiveBindingContext, result: JSResult) {
@@ -523,7 +1456,38 @@ pub fn close(c: Arc<Chrome>) {
     });
 }
 
+/// Closes only the window of `c`, the browser and its other windows keep running
+pub fn close_target(c: Arc<Chrome>) {
+    std::thread::spawn(move || {
+        if let Err(e) = send_browser(
+            &c.browser,
+            "Target.closeTarget",
+            &json!({ "targetId": c.target }),
+            c.timeout,
+        ) {
+            eprintln!("{}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::url_matches;
+
+    #[test]
+    fn test_url_matches() {
+        assert!(url_matches("*", "https://example.com/"));
+        assert!(url_matches("*/fonts/*.woff2", "https://cdn.example.com/fonts/a.woff2"));
+        assert!(!url_matches("*/fonts/*.woff2", "https://cdn.example.com/fonts/a.woff"));
+        assert!(url_matches("https://example.com/style.css", "https://example.com/style.css"));
+        assert!(!url_matches("https://example.com/style.css", "https://example.com/style.css?v=2"));
+        assert!(url_matches("https://example.com/?.css", "https://example.com/a.css"));
+        assert!(url_matches("*\\?v=1", "https://example.com/a.css?v=1"));
+        assert!(!url_matches("*\\?v=1", "https://example.com/a.cssxv=1"));
+    }
+}
+
 #[cfg(target_family = "windows")]
 pub fn close_handle(c: Arc<Chrome>) {
-    close_process_handle(c.pid as Process).expect("Unable to close handle")
+    close_process_handle(c.browser.pid as Process).expect("Unable to close handle")
 }
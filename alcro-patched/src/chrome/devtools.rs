@@ -1,40 +1,45 @@
-use super::{ActiveBindingContext, BindingContext, Chrome, JSObject, JSResult};
+use super::{
+    request_paused, screencast_frame, ActiveBindingContext, BindingContext, Browser, Chrome, JSError, JSObject,
+    Pending,
+};
 use super::{PipeReader, PipeWriter};
-use crossbeam_channel::{bounded, Sender};
+use crossbeam_channel::{bounded, RecvTimeoutError, Sender};
 use serde_json::json;
 use std::sync::{atomic::Ordering, Arc, Mutex};
 use std::collections::HashSet;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::Duration;
 use once_cell::sync::Lazy;
 use std::io::ErrorKind;
 
 static DESTROYED_TARGETS: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
 
-pub fn send_msg(p: &Mutex<PipeWriter>, message: String) {
+pub fn send_msg(p: &Mutex<PipeWriter>, message: String) -> Result<(), JSError> {
     let result = p.lock()
         .expect("Unable to lock")
         .write(message);
-    if let Err(e) = result {
+    match result {
+        Ok(_) => Ok(()),
         // 特殊处理 BrokenPipe 错误，防止 panic
-        match e {
-            super::os::PipeWriteError::IOError(ref ioerr) if ioerr.kind() == ErrorKind::BrokenPipe => {
-                eprintln!("[alcro-devtools] 管道已关闭 (BrokenPipe)，忽略写入: {}", ioerr);
-                return;
-            }
-            _ => panic!("Unable to write to pipe: {}", e),
+        Err(super::os::PipeWriteError::IOError(ref ioerr)) if ioerr.kind() == ErrorKind::BrokenPipe => {
+            eprintln!("[alcro-devtools] 管道已关闭 (BrokenPipe)，忽略写入: {}", ioerr);
+            Err(JSError::BrowserExited)
         }
+        Err(e) => Err(JSError::Transport(e.to_string())),
     }
 }
 
-pub fn recv_msg(p: &Mutex<PipeReader>) -> String {
-    let msg = p.lock()
-        .expect("Unable to lock")
-        .read()
-        .unwrap_or_else(|e| {
+/// Reads the next message from chrome. An empty message means the pipe was closed.
+pub fn recv_msg(p: &Mutex<PipeReader>) -> Result<String, JSError> {
+    match p.lock().expect("Unable to lock").read() {
+        Ok(msg) if msg.is_empty() => Err(JSError::BrowserExited),
+        Ok(msg) => Ok(msg),
+        Err(e) => {
+            // 只在出错时显示
             eprintln!("[alcro-debug] 管道读取错误: {:?}", e);
-            String::new()
-        });
-    // 只在出错时显示
-    msg
+            Err(JSError::Transport(e.to_string()))
+        }
+    }
 }
 
 fn beautify_nested_json(json_value: &serde_json::Value) -> String {
@@ -60,13 +65,60 @@ fn beautify_nested_json(json_value: &serde_json::Value) -> String {
     pretty_json
 }
 
-pub fn readloop(c: Arc<Chrome>) {
-    loop {
-        let pmsg = recv_msg(&c.precv);
-        if pmsg.is_empty() {
-            eprintln!("[alcro-debug] 收到空消息，浏览器可能已退出或管道已关闭");
-            break;
+pub fn readloop(b: Arc<Browser>) {
+    let reason = process_messages(&b);
+    close_pending(&b, &b.closed, None, reason);
+}
+
+/// Marks the connection as closed: wakes up every caller still waiting for an answer with
+/// `reason`, and makes later calls fail immediately. With a `session` only the commands sent to
+/// that page are affected.
+fn close_pending(b: &Browser, closed: &Mutex<Option<JSError>>, session: Option<&str>, reason: JSError) {
+    let pending = &b.pending;
+    let mut closed = closed.lock().expect("Unable to lock");
+    if closed.is_none() {
+        *closed = Some(reason.clone());
+    }
+    drop(closed);
+    // Waiters check the flags while holding the lock of the browser, so taking it here makes sure
+    // none of them misses the notification
+    let guard = b.closed.lock().expect("Unable to lock");
+    b.closed_signal.notify_all();
+    drop(guard);
+    let ids: Vec<i32> = pending
+        .iter()
+        .filter(|entry| session.is_none() || entry.session.as_deref() == session)
+        .map(|entry| *entry.key())
+        .collect();
+    for id in ids {
+        if let Some((_, p)) = pending.remove(&id) {
+            let _ = p.reschan.send(Err(reason.clone()));
         }
+    }
+}
+
+/// Closes one page: its pending commands fail with `reason` and it stops receiving messages.
+fn close_session(b: &Browser, c: &Chrome, reason: JSError) {
+    b.targets.remove(&c.session);
+    close_pending(b, &c.closed, Some(&c.session), reason);
+}
+
+fn find_by_target(b: &Browser, target: &JSObject) -> Option<Arc<Chrome>> {
+    b.targets
+        .iter()
+        .filter_map(|entry| entry.value().upgrade())
+        .find(|c| *target == c.target)
+}
+
+fn process_messages(b: &Arc<Browser>) -> JSError {
+    loop {
+        let pmsg = match recv_msg(&b.precv) {
+            Ok(pmsg) => pmsg,
+            Err(e) => {
+                eprintln!("[alcro-debug] 收到空消息，浏览器可能已退出或管道已关闭");
+                return e;
+            }
+        };
         // 只打印关键事件，Target.targetDestroyed 去重
         if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(&pmsg) {
             let method = json_value.get("method").and_then(|m| m.as_str()).unwrap_or("");
@@ -93,27 +145,66 @@ pub fn readloop(c: Arc<Chrome>) {
             Ok(obj) => obj,
             Err(e) => {
                 eprintln!("[alcro-debug] JSON 解析失败: {:?}", e);
-                break;
+                return JSError::Transport(format!("Invalid JSON: {}", e));
             }
         };
 
         if pmsg["method"] == "Target.targetDestroyed" {
-            #[cfg(target_family = "unix")]
-            if pmsg["params"]["targetId"] == c.target {
-                let _ = c.kill_send.send(());
-                return;
+            // 已被丢弃的页面不会再收到消息，顺便清理
+            b.targets.retain(|_, c| c.strong_count() > 0);
+            if let Some(c) = find_by_target(b, &pmsg["params"]["targetId"]) {
+                close_session(b, &c, JSError::TargetDestroyed);
+                // 最后一个窗口关闭时结束浏览器进程（Windows 下浏览器会自行退出）
+                #[cfg(target_family = "unix")]
+                if b.targets.is_empty() {
+                    let _ = b._kill_send.send(());
+                    return JSError::TargetDestroyed;
+                }
+            }
+        } else if pmsg["id"].is_i64() && pmsg["error"] != JSObject::Null {
+            // Target.sendMessageToTarget 本身失败（例如会话已不存在）时，错误直接在浏览器层返回
+            let res_id = pmsg["id"].as_i64().expect("Expected i64") as i32;
+            if let Some((_, p)) = b.pending.remove(&res_id) {
+                let _ = p.reschan.send(Err(JSError::from_protocol_error(&pmsg["error"])));
+            }
+        } else if pmsg["id"].is_i64() {
+            // 发给浏览器本身的命令的结果；发给页面的命令在这里只会收到转发成功的确认，忽略
+            let res_id = pmsg["id"].as_i64().expect("Expected i64") as i32;
+            let for_browser = matches!(b.pending.get(&res_id), Some(p) if p.session.is_none());
+            if for_browser {
+                if let Some((_, p)) = b.pending.remove(&res_id) {
+                    let _ = p.reschan.send(Ok(pmsg["result"].clone()));
+                }
             }
         } else if pmsg["method"] == "Target.receivedMessageFromTarget" {
             let params = &pmsg["params"];
-            if params["sessionId"] != c.session {
-                continue;
-            }
+            let c = match params["sessionId"]
+                .as_str()
+                .and_then(|session| b.targets.get(session).and_then(|c| c.upgrade()))
+            {
+                Some(c) => c,
+                None => continue,
+            };
 
             let message = params["message"]
                 .as_str()
                 .expect("message should be a string");
             let res: JSObject = serde_json::from_str(message).expect("Invalid JSON");
 
+            if res["method"] == "Inspector.targetCrashed" {
+                eprintln!("[alcro-debug] 页面渲染进程已崩溃");
+                c.crashed.store(true, Ordering::Relaxed);
+                close_pending(b, &c.closed, Some(&c.session), JSError::TargetDestroyed);
+                continue;
+            }
+            if res["method"] == "Fetch.requestPaused" {
+                request_paused(c, &res["params"]);
+                continue;
+            }
+            if res["method"] == "Page.screencastFrame" {
+                screencast_frame(c, &res["params"]);
+                continue;
+            }
             if res["id"] == JSObject::Null && res["method"] == "Runtime.consoleAPICalled"
                 || res["method"] == "Runtime.exceptionThrown"
             {
@@ -133,7 +224,7 @@ pub fn readloop(c: Arc<Chrome>) {
                 )
                 .expect("Invalid JSON");
                 binding_called(
-                    c.clone(),
+                    c,
                     res["params"]["name"].as_str().expect("Expected string"),
                     payload,
                     res["params"]["executionContextId"]
@@ -144,65 +235,133 @@ pub fn readloop(c: Arc<Chrome>) {
             } else if res["id"].is_i64() {
                 let res_id = res["id"].as_i64().expect("Expected i64") as i32;
 
-                match c.pending.get(&res_id) {
-                    None => continue,
-                    Some(reschan) => {
-                        send_result(&*reschan, &res);
-                    }
+                if let Some((_, p)) = b.pending.remove(&res_id) {
+                    send_result(&p.reschan, &res);
                 }
-                c.pending.remove(&res_id);
             }
         }
     }
 }
 
-pub fn send(c: Arc<Chrome>, method: &str, params: &JSObject) -> JSResult {
-    let id = c.id.fetch_add(1, Ordering::Relaxed) + 1;
+/// Sends a command to the page and waits for the result, at most for the default timeout of the
+/// browser.
+pub fn send(c: Arc<Chrome>, method: &str, params: &JSObject) -> Result<JSObject, JSError> {
+    let timeout = c.timeout;
+    send_with_timeout(c, method, params, timeout)
+}
+
+/// Sends a command to the page and waits for the result. `None` waits forever.
+pub fn send_with_timeout(
+    c: Arc<Chrome>,
+    method: &str,
+    params: &JSObject,
+    timeout: Option<Duration>,
+) -> Result<JSObject, JSError> {
+    request(&c.browser, Some(&c), method, params, timeout)
+}
+
+/// Sends a command to the browser itself instead of one of its pages, for example to open or
+/// close a window. `None` waits forever.
+pub fn send_browser(
+    b: &Browser,
+    method: &str,
+    params: &JSObject,
+    timeout: Option<Duration>,
+) -> Result<JSObject, JSError> {
+    request(b, None, method, params, timeout)
+}
+
+fn request(
+    b: &Browser,
+    page: Option<&Chrome>,
+    method: &str,
+    params: &JSObject,
+    timeout: Option<Duration>,
+) -> Result<JSObject, JSError> {
+    if let Some(reason) = closed_reason(b, page) {
+        return Err(reason);
+    }
+    let id = b.id.fetch_add(1, Ordering::Relaxed) + 1;
     let json_msg = json!({
         "id":id,
         "method":method,
         "params":params
     });
     let (s, r) = bounded(1);
-    c.pending.insert(id, s);
+    b.pending.insert(
+        id,
+        Pending {
+            session: page.map(|c| c.session.clone()),
+            reschan: s,
+        },
+    );
 
-    send_msg(
-        &c.psend,
-        json!({
+    let message = match page {
+        Some(c) => json!({
             "id":id,
             "method":"Target.sendMessageToTarget",
             "params":json!({
                 "message":json_msg.to_string(),
                 "sessionId":c.session
             })
-        })
-        .to_string(),
-    );
-    r.recv().unwrap()
+        }),
+        None => json_msg,
+    };
+    if let Err(e) = send_msg(&b.psend, message.to_string()) {
+        b.pending.remove(&id);
+        return Err(e);
+    }
+    // readloop 可能在插入 pending 之前就已退出，此时不会再有人唤醒我们
+    if let Some(reason) = closed_reason(b, page) {
+        b.pending.remove(&id);
+        return Err(reason);
+    }
+
+    let result = match timeout {
+        Some(t) => r.recv_timeout(t).map_err(|e| match e {
+            RecvTimeoutError::Timeout => JSError::Timeout(t),
+            RecvTimeoutError::Disconnected => JSError::BrowserExited,
+        }),
+        None => r.recv().map_err(|_| JSError::BrowserExited),
+    };
+    if result.is_err() {
+        b.pending.remove(&id);
+    }
+    result?
+}
+
+fn closed_reason(b: &Browser, page: Option<&Chrome>) -> Option<JSError> {
+    if let Some(reason) = b.closed.lock().expect("Unable to lock").clone() {
+        return Some(reason);
+    }
+    page.and_then(|c| c.closed.lock().expect("Unable to lock").clone())
 }
 
-fn send_result(reschan: &Sender<JSResult>, res: &JSObject) {
-    if res["error"]["message"] != JSObject::Null {
-        reschan.send(Err(res["error"]["message"].clone())).unwrap();
-    } else if res["result"]["exceptionDetails"]["exception"]["value"] != JSObject::Null {
-        reschan
-            .send(Err(
-                res["result"]["exceptionDetails"]["exception"]["value"].clone()
-            ))
-            .unwrap();
+fn send_result(reschan: &Sender<Result<JSObject, JSError>>, res: &JSObject) {
+    // 调用者可能已超时放弃等待，此时接收端已被丢弃，忽略发送失败
+    let result = if res["error"]["message"] != JSObject::Null {
+        Err(JSError::from_protocol_error(&res["error"]))
+    } else if res["result"]["exceptionDetails"] != JSObject::Null {
+        Err(JSError::from_exception_details(
+            &res["result"]["exceptionDetails"],
+        ))
     } else if res["result"]["result"]["type"] == "object"
         && res["result"]["result"]["subtype"] == "error"
     {
-        reschan
-            .send(Err(res["result"]["result"]["description"].clone()))
-            .unwrap();
+        Err(res["result"]["result"]["description"].clone().into())
+    } else if res["result"]["result"]["type"] == "object"
+        && res["result"]["result"].get("value").is_none()
+        && res["result"]["result"]["objectId"].is_string()
+    {
+        // An object returned by reference (`returnByValue` not set): hand the whole remote
+        // object to the caller so that it can use the `objectId`
+        Ok(res["result"]["result"].clone())
     } else if res["result"]["result"]["type"] != JSObject::Null {
-        reschan
-            .send(Ok(res["result"]["result"]["value"].clone()))
-            .unwrap();
+        Ok(res["result"]["result"]["value"].clone())
     } else {
-        reschan.send(Ok(res["result"].clone())).unwrap();
-    }
+        Ok(res["result"].clone())
+    };
+    let _ = reschan.send(result);
 }
 
 fn binding_called(c: Arc<Chrome>, name: &str, payload: JSObject, context_id: i64) {
@@ -211,10 +370,17 @@ fn binding_called(c: Arc<Chrome>, name: &str, payload: JSObject, context_id: i64
         None => None,
     };
     if let Some(binding) = binding {
-        binding(BindingContext::new(ActiveBindingContext {
-            chrome: c,
-            payload,
-            context_id,
-        }))
+        // 绑定函数在消息循环中执行，panic 不能让 readloop 线程退出。
+        // BindingContext 在展开时被丢弃，会以错误结束 JS 端的 promise。
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            binding(BindingContext::new(ActiveBindingContext {
+                chrome: c,
+                payload,
+                context_id,
+            }))
+        }));
+        if result.is_err() {
+            eprintln!("[alcro-devtools] 绑定函数 {} 发生 panic", name);
+        }
     }
 }
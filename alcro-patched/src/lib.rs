@@ -32,20 +32,37 @@
 //!
 //! To change the path of the browser launched set the ALCRO_BROWSER_PATH environment variable. Only Chromium based browsers work.
 //!
+//! If no browser is found alcro asks the user whether to download Chrome. Set the
+//! ALCRO_NONINTERACTIVE environment variable or use [`UIBuilder::interactive`] to fail without
+//! asking.
+//!
 
 mod chrome;
 #[cfg(target_family = "windows")]
 use chrome::close_handle;
-use chrome::{bind, bounds, close, eval, load, load_css, load_js, set_bounds, Chrome};
-pub use chrome::{BindingContext, Bounds, JSError, JSObject, JSResult, WindowState};
+use chrome::{
+    advance_virtual_time, bind, bounds, clear_device_metrics, clear_virtual_time, close,
+    close_target, dispatch_mouse_event, emit, eval, intercept, load, load_css, load_js, new_window, reload, set_bounds,
+    set_device_metrics, set_timezone, set_virtual_time, set_zoom, start_screencast, stop_intercepting,
+    stop_screencast, Chrome,
+};
+pub use chrome::{
+    BindingContext, Bounds, BrowserVersion, DeviceMetrics, InterceptAction, InterceptStage, InterceptedRequest,
+    InterceptedResponse, JSError, JSObject, JSResult, MouseButton, MouseEvent, MouseEventKind,
+    ScreencastFormat, ScreencastFrame, WindowState,
+};
 mod locate;
 pub use locate::tinyfiledialogs as dialog;
-use locate::{locate_chrome, LocateChromeError};
+pub use locate::{
+    locate_browsers, BrowserCandidate, BrowserSource, LocateChromeError, NONINTERACTIVE_ENV,
+};
+use locate::{detect_version, locate_chrome};
+use base64::Engine;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
-use tempfile;
+use std::time::Duration;
 
 const DEFAULT_CHROME_ARGS: &[&str] = &[
     "--disable-background-networking",
@@ -79,6 +96,22 @@ pub struct UI {
     chrome: Arc<Chrome>,
     _tmpdir: Option<tempfile::TempDir>,
     waited: AtomicBool,
+    /// False for windows opened with [`UI::new_window`], which share the browser of another UI
+    owns_browser: bool,
+}
+
+/// Sends mouse events to the page of a [`UI`], see [`UI::mouse_input`]. Sending fails once the
+/// browser is closed.
+#[derive(Clone)]
+pub struct MouseInput {
+    chrome: Arc<Chrome>,
+}
+
+impl MouseInput {
+    /// Send a mouse event to the page, see [`UI::dispatch_mouse_event`]
+    pub fn dispatch(&self, event: MouseEvent) -> Result<(), JSError> {
+        dispatch_mouse_event(self.chrome.clone(), event)
+    }
 }
 
 /// Error in launching a UI window
@@ -96,23 +129,89 @@ pub enum UILaunchError {
     /// Error when initializing chrome
     #[error("Error when initializing chrome: {0}")]
     ChromeInitError(#[from] JSError),
+    /// The browser is older than the version set with [`UIBuilder::min_version`]
+    #[error("The browser version {version} is older than the minimum supported version {minimum}")]
+    BrowserTooOld {
+        /// The version of the browser
+        version: String,
+        /// The minimum major version required
+        minimum: u32,
+    },
 }
 
 impl UI {
-    /// Returns true if the browser is closed
+    /// Returns true if the browser is closed. For a window opened with [`UI::new_window`] it
+    /// also returns true once that window is closed.
     pub fn done(&self) -> bool {
-        self.chrome.done()
+        if self.owns_browser {
+            self.chrome.done()
+        } else {
+            self.chrome.done() || self.chrome.closed()
+        }
+    }
+
+    /// The version of the browser, as reported by it at startup. It is `None` if the browser
+    /// could not be queried.
+    pub fn browser_version(&self) -> Option<&BrowserVersion> {
+        self.chrome.version()
+    }
+
+    /// Returns true if the page crashed, for example because the renderer process was killed or
+    /// ran out of memory. The window stays open but the page will not respond anymore, so it has
+    /// to be closed and launched again.
+    pub fn crashed(&self) -> bool {
+        self.chrome.crashed()
     }
 
     /// Wait for the browser to be closed
     pub fn wait_finish(&self) {
-        self.chrome.wait_finish();
+        if self.owns_browser {
+            self.chrome.wait_finish();
+        } else {
+            self.chrome.wait_closed();
+        }
         self.waited.store(true, Ordering::Relaxed);
     }
 
-    /// Close the browser window
+    /// Close the browser window. For a window opened with [`UI::new_window`] only that window is
+    /// closed.
     pub fn close(&self) {
-        close(self.chrome.clone())
+        if self.owns_browser {
+            close(self.chrome.clone())
+        } else {
+            close_target(self.chrome.clone())
+        }
+    }
+
+    /// Open another window in the same browser process. This is much cheaper than launching a
+    /// second browser with [`UIBuilder::run`]. The new window has its own bindings and is closed
+    /// when the returned UI is dropped, but it also goes away when this browser is closed.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use alcro::{UIBuilder, Content};
+    /// let ui = UIBuilder::new().run().expect("Unable to launch");
+    /// let settings = ui
+    ///     .new_window(Content::Html("<html><body>Settings</body></html>"))
+    ///     .expect("Unable to open window");
+    /// settings.wait_finish();
+    /// ```
+    pub fn new_window(&self, content: Content) -> Result<UI, JSError> {
+        let html: String;
+        let url = match content {
+            Content::Url(u) => u,
+            Content::Html(h) => {
+                html = html_data_url(h);
+                &html
+            }
+        };
+        Ok(UI {
+            chrome: new_window(self.chrome.clone(), url)?,
+            _tmpdir: None,
+            waited: AtomicBool::new(false),
+            owns_browser: false,
+        })
     }
 
     /// 关闭窗口并阻塞直到窗口真正关闭（或超时）
@@ -142,15 +241,48 @@ impl UI {
         let url = match content {
             Content::Url(u) => u,
             Content::Html(h) => {
-                html = format!("data:text/html,{}", h);
+                html = html_data_url(h);
                 &html
             }
         };
         load(self.chrome.clone(), url)
     }
 
+    /// Reload the page, bypassing the cache. Unlike evaluating `location.reload()`, this also
+    /// works when the page is stuck in a busy loop. It waits at most `timeout` for the browser to
+    /// accept the command.
+    pub fn reload(&self, timeout: Duration) -> Result<(), JSError> {
+        reload(self.chrome.clone(), Some(timeout))
+    }
+
+    /// Dispatch a `CustomEvent` named `event` on `window` with `payload` serialized to JSON as
+    /// its `detail`. Unlike building a script for [`UI::eval`] by hand, any string in the event
+    /// name or payload is passed through unchanged. The event is cancelable and the method
+    /// returns false if a listener called `preventDefault`, so the page can tell the caller that
+    /// it handled the event.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![windows_subsystem = "windows"]
+    /// use alcro::UIBuilder;
+    /// use serde_json::json;
+    ///
+    /// let ui = UIBuilder::new().custom_args(&["--headless"]).run().expect("Unable to launch");
+    /// ui.eval("addEventListener('greet', e => window.greeting = e.detail.text)").unwrap();
+    /// ui.emit("greet", &json!({ "text": "it's \"quoted\"" })).unwrap();
+    /// assert_eq!(ui.eval("window.greeting").unwrap(), "it's \"quoted\"");
+    /// ```
+    pub fn emit<T: serde::Serialize + ?Sized>(&self, event: &str, payload: &T) -> Result<bool, JSError> {
+        let detail = serde_json::to_value(payload).map_err(|e| {
+            JSError::from(JSObject::String(format!("Cannot serialize the payload of {}: {}", event, e)))
+        })?;
+        emit(self.chrome.clone(), event, &detail)
+    }
+
     /// Bind a rust function so that JS code can use it. It returns Err if it fails.
-    /// The rust function will be executed in a new thread and can be called asynchronously from Javascript
+    /// The rust function will be executed in a new thread and can be called asynchronously from Javascript.
+    /// If the function panics the JS promise is rejected with an error.
     ///
     /// # Arguments
     ///
@@ -189,13 +321,58 @@ impl UI {
             Arc::new(move |context| {
                 let f = f.clone();
                 std::thread::spawn(move || {
-                    let result = f(context.args());
+                    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        f(context.args())
+                    }))
+                    .unwrap_or_else(|panic| Err(panic_message(panic.as_ref())));
                     context.complete(result);
                 });
             }),
         )
     }
 
+    /// Bind a rust function whose arguments and return value are converted with serde. It
+    /// returns Err if it fails.
+    ///
+    /// The JS arguments are deserialized as a list into `A`, so `A` is usually a tuple such as
+    /// `(String, u32)` or a struct whose fields are taken in order. Functions without arguments
+    /// can use `()`. When the arguments do not match, the JS promise is rejected with an error
+    /// naming the function and the mismatch, and `f` is not called. Like [`UI::bind`], `f` runs
+    /// in a new thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the function
+    /// * `f` - The function. It should take the decoded arguments and return a value that can be
+    ///   serialized, or a `JSObject` to reject the promise with
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![windows_subsystem = "windows"]
+    /// use alcro::UIBuilder;
+    ///
+    /// let ui = UIBuilder::new().custom_args(&["--headless"]).run().expect("Unable to launch");
+    /// ui.bind_typed("repeat", |(text, count): (String, usize)| Ok(text.repeat(count)))
+    ///     .expect("Unable to bind function");
+    /// assert_eq!(ui.eval("repeat('ab', 2)").unwrap(), "abab");
+    /// assert!(ui.eval("repeat('ab')").is_err());
+    /// ```
+    pub fn bind_typed<A, R, F>(&self, name: &str, f: F) -> Result<(), JSError>
+    where
+        A: serde::de::DeserializeOwned,
+        R: serde::Serialize,
+        F: Fn(A) -> Result<R, JSObject> + Sync + Send + 'static,
+    {
+        let name_owned = name.to_string();
+        self.bind(name, move |args| {
+            let result = f(decode_args(&name_owned, args)?)?;
+            serde_json::to_value(result).map_err(|e| {
+                JSObject::String(format!("Cannot serialize the result of {}: {}", name_owned, e))
+            })
+        })
+    }
+
     /// Bind a rust function callable from JS that can complete asynchronously. If you are using
     /// [`tokio`], you will probably want to be using [`Self::bind_tokio()`] instead.
     ///
@@ -211,7 +388,7 @@ impl UI {
     ///
     /// * `name` - Name of the function
     /// * `f` - The function. It should take a [`BindingContext`] that gives access to the
-    ///         arguments and allows returning results.
+    ///   arguments and allows returning results.
     ///
     /// # Examples
     ///
@@ -315,7 +492,8 @@ impl UI {
         })
     }
 
-    /// Evaluates js code and returns the result.
+    /// Evaluates js code and returns the result. It waits for at most the timeout set by
+    /// [`UIBuilder::timeout()`], forever if none was set.
     ///
     /// # Examples
     ///
@@ -327,9 +505,31 @@ impl UI {
     /// assert_eq!(ui.eval("'Hello'+' World'").unwrap(), "Hello World");
     /// assert!(ui.eval("xfgch").is_err());
     /// ```
-
     pub fn eval(&self, js: &str) -> JSResult {
-        eval(self.chrome.clone(), js)
+        eval(self.chrome.clone(), js, None).map_err(JSError::source)
+    }
+
+    /// Evaluates js code and returns the result, giving up after `timeout`.
+    ///
+    /// Unlike `eval()` the error distinguishes a JS error from a browser that does not answer in
+    /// time ([`JSError::Timeout`]), has exited ([`JSError::BrowserExited`]), cannot be reached
+    /// ([`JSError::Transport`]) or whose page is gone ([`JSError::TargetDestroyed`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![windows_subsystem = "windows"]
+    /// use alcro::{JSError, UIBuilder};
+    /// use std::time::Duration;
+    /// let ui = UIBuilder::new().custom_args(&["--headless"]).run().expect("Unable to launch");
+    /// assert_eq!(ui.eval_with_timeout("1+1", Duration::from_secs(5)).unwrap(), 2);
+    /// assert!(matches!(
+    ///     ui.eval_with_timeout("new Promise(() => {})", Duration::from_millis(100)),
+    ///     Err(JSError::Timeout(_))
+    /// ));
+    /// ```
+    pub fn eval_with_timeout(&self, js: &str, timeout: Duration) -> Result<JSObject, JSError> {
+        eval(self.chrome.clone(), js, Some(timeout))
     }
 
     /// Evaluates js code and adds functions before document loads. Loaded js is unloaded on reload.
@@ -347,7 +547,6 @@ impl UI {
     /// ui.load_js("function loadedFunction() { return 'This function was loaded from rust'; }").expect("Unable to load js");
     /// assert_eq!(ui.eval("loadedFunction()").unwrap(), "This function was loaded from rust");
     /// ```
-
     pub fn load_js(&self, script: &str) -> Result<(), JSError> {
         load_js(self.chrome.clone(), script)
     }
@@ -366,11 +565,223 @@ impl UI {
     /// let ui = UIBuilder::new().custom_args(&["--headless"]).run().expect("Unable to launch");
     /// ui.load_css("body {display: none;}").expect("Unable to load css");
     /// ```
-
     pub fn load_css(&self, css: &str) -> Result<(), JSError> {
         load_css(self.chrome.clone(), css)
     }
 
+    /// Intercept the requests of the page whose URL matches one of `patterns` before they are
+    /// sent. `f` decides for each one whether to send it, change it, answer it itself or fail it.
+    /// In the patterns `*` matches any number of characters and `?` exactly one. Calling it
+    /// again replaces the patterns and the function.
+    ///
+    /// `f` is executed in a new thread for every request, and the page waits for it before it
+    /// can use the resource. Windows opened with [`UI::new_window`] are not intercepted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![windows_subsystem = "windows"]
+    /// use alcro::{InterceptAction, UIBuilder};
+    /// let ui = UIBuilder::new().custom_args(&["--headless"]).run().expect("Unable to launch");
+    /// ui.intercept(&["*/style.css"], |_| InterceptAction::Fulfill {
+    ///     status: 200,
+    ///     headers: vec![("Content-Type".to_string(), "text/css".to_string())],
+    ///     body: b"body { color: red; }".to_vec(),
+    /// }).expect("Unable to intercept");
+    /// ```
+    pub fn intercept<F>(&self, patterns: &[&str], f: F) -> Result<(), JSError>
+    where
+        F: Fn(&InterceptedRequest) -> InterceptAction + Sync + Send + 'static,
+    {
+        let patterns: Vec<(&str, InterceptStage)> = patterns
+            .iter()
+            .map(|pattern| (*pattern, InterceptStage::Request))
+            .collect();
+        intercept(self.chrome.clone(), &patterns, Arc::new(f))
+    }
+
+    /// Like [`UI::intercept`], but each pattern also says when to pause the request. At
+    /// [`InterceptStage::Response`] `f` gets the status, headers and body of the response in
+    /// [`InterceptedRequest::response`], or the network error if the request failed, and can
+    /// pass the response on or replace it. A request matching patterns of both stages is paused
+    /// twice, unless it is answered at the request stage.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![windows_subsystem = "windows"]
+    /// use alcro::{InterceptAction, InterceptStage, UIBuilder};
+    /// let ui = UIBuilder::new().custom_args(&["--headless"]).run().expect("Unable to launch");
+    /// ui.intercept_at(&[("https://*", InterceptStage::Response)], |request| {
+    ///     match request.response {
+    ///         // The network is down: answer from somewhere else
+    ///         Some(ref response) if response.error.is_some() => InterceptAction::Fulfill {
+    ///             status: 200,
+    ///             headers: vec![],
+    ///             body: b"offline".to_vec(),
+    ///         },
+    ///         _ => InterceptAction::Continue,
+    ///     }
+    /// }).expect("Unable to intercept");
+    /// ```
+    pub fn intercept_at<F>(&self, patterns: &[(&str, InterceptStage)], f: F) -> Result<(), JSError>
+    where
+        F: Fn(&InterceptedRequest) -> InterceptAction + Sync + Send + 'static,
+    {
+        intercept(self.chrome.clone(), patterns, Arc::new(f))
+    }
+
+    /// Stop intercepting requests started by [`UI::intercept`]
+    pub fn stop_intercepting(&self) -> Result<(), JSError> {
+        stop_intercepting(self.chrome.clone())
+    }
+
+    /// Capture the page as it is painted and pass every new frame to `f`, encoded as
+    /// `format` (`quality` from 0 to 100 only applies to JPEG). Frames larger than `max_size`
+    /// are scaled down. Calling it again replaces the function.
+    ///
+    /// `f` is executed in a new thread for every frame, and the browser waits for it before
+    /// sending the next one. A page that does not change sends no frames. This also works for
+    /// headless browsers, which makes it possible to show the page somewhere the browser window
+    /// cannot go.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![windows_subsystem = "windows"]
+    /// use alcro::{ScreencastFormat, UIBuilder};
+    /// let ui = UIBuilder::new().custom_args(&["--headless"]).run().expect("Unable to launch");
+    /// ui.start_screencast(ScreencastFormat::Png, 100, Some((800, 600)), |frame| {
+    ///     println!("{} bytes, {}x{}", frame.data.len(), frame.device_width, frame.device_height);
+    /// }).expect("Unable to start screencast");
+    /// ui.stop_screencast().expect("Unable to stop screencast");
+    /// ```
+    pub fn start_screencast<F>(
+        &self,
+        format: ScreencastFormat,
+        quality: u8,
+        max_size: Option<(u32, u32)>,
+        f: F,
+    ) -> Result<(), JSError>
+    where
+        F: Fn(ScreencastFrame) + Sync + Send + 'static,
+    {
+        start_screencast(self.chrome.clone(), format, quality, max_size, Arc::new(f))
+    }
+
+    /// Stop sending frames started by [`UI::start_screencast`]
+    pub fn stop_screencast(&self) -> Result<(), JSError> {
+        stop_screencast(self.chrome.clone())
+    }
+
+    /// Emulate a screen with different metrics, for example a higher `devicePixelRatio` for
+    /// a projector with an unusual resolution. It stays in effect when the page is reloaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![windows_subsystem = "windows"]
+    /// use alcro::{DeviceMetrics, UIBuilder};
+    /// let ui = UIBuilder::new().custom_args(&["--headless"]).run().expect("Unable to launch");
+    /// ui.set_device_metrics(DeviceMetrics {
+    ///     width: 0,
+    ///     height: 0,
+    ///     device_scale_factor: 2.0,
+    ///     mobile: false,
+    /// }).expect("Unable to set device metrics");
+    /// assert_eq!(ui.eval("window.devicePixelRatio").unwrap(), 2);
+    /// ```
+    pub fn set_device_metrics(&self, metrics: DeviceMetrics) -> Result<(), JSError> {
+        set_device_metrics(self.chrome.clone(), metrics)
+    }
+
+    /// Stop emulating the metrics set with [`UI::set_device_metrics`]
+    pub fn clear_device_metrics(&self) -> Result<(), JSError> {
+        clear_device_metrics(self.chrome.clone())
+    }
+
+    /// Make the page use another time zone, given by its IANA name such as "Asia/Shanghai". An
+    /// empty string restores the time zone of the system.
+    pub fn set_timezone(&self, timezone_id: &str) -> Result<(), JSError> {
+        set_timezone(self.chrome.clone(), timezone_id)
+    }
+
+    /// Zoom the page by `factor`, 1.0 being the normal size. Like the zoom of the browser, the
+    /// layout adapts to the smaller or larger space. It also applies to pages loaded later.
+    pub fn set_zoom(&self, factor: f64) -> Result<(), JSError> {
+        set_zoom(self.chrome.clone(), factor)
+    }
+
+    /// Make the page see `time` as the current time, for example "2025-09-01T07:54:00". The
+    /// string is parsed by `Date.parse` in the page, so a date and time without an offset is in
+    /// the time zone of the page, see [`UI::set_timezone`]. If `ticking` the clock goes on from
+    /// there, otherwise it stands still until moved with [`UI::advance_virtual_time`].
+    ///
+    /// Only `Date` is affected: timers like `setInterval` still run in real time. The clock
+    /// stays in effect for pages loaded later, so it can be set on a blank page before loading
+    /// the page to test.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![windows_subsystem = "windows"]
+    /// use alcro::UIBuilder;
+    /// use std::time::Duration;
+    /// let ui = UIBuilder::new().custom_args(&["--headless"]).run().expect("Unable to launch");
+    /// ui.set_timezone("Asia/Shanghai").unwrap();
+    /// ui.set_virtual_time("2025-09-01T07:54:00", false).unwrap();
+    /// assert_eq!(ui.eval("new Date().getHours()").unwrap(), 7);
+    /// ui.advance_virtual_time(Duration::from_secs(6 * 60)).unwrap();
+    /// assert_eq!(ui.eval("new Date().getHours()").unwrap(), 8);
+    /// ```
+    pub fn set_virtual_time(&self, time: &str, ticking: bool) -> Result<(), JSError> {
+        set_virtual_time(self.chrome.clone(), time, ticking)
+    }
+
+    /// Move the virtual time set with [`UI::set_virtual_time`] forward by `by`. Without a
+    /// virtual clock it starts a ticking one at the real time.
+    pub fn advance_virtual_time(&self, by: Duration) -> Result<(), JSError> {
+        advance_virtual_time(self.chrome.clone(), by)
+    }
+
+    /// Give the page the real time again
+    pub fn clear_virtual_time(&self) -> Result<(), JSError> {
+        clear_virtual_time(self.chrome.clone())
+    }
+
+    /// Send a mouse event to the page, as if the user clicked, moved the pointer or turned the
+    /// wheel over the viewport. Useful for a page that runs with `--headless` and is shown some
+    /// other way, for example with [`UI::start_screencast`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// #![windows_subsystem = "windows"]
+    /// use alcro::{MouseButton, MouseEvent, MouseEventKind, UIBuilder};
+    /// let ui = UIBuilder::new().custom_args(&["--headless"]).run().expect("Unable to launch");
+    /// ui.eval("window.clicks = 0; addEventListener('click', () => clicks++)").unwrap();
+    /// for kind in [MouseEventKind::MousePressed, MouseEventKind::MouseReleased] {
+    ///     ui.dispatch_mouse_event(MouseEvent {
+    ///         kind,
+    ///         button: MouseButton::Left,
+    ///         click_count: 1,
+    ///         ..MouseEvent::moved(10.0, 10.0, 0)
+    ///     }).unwrap();
+    /// }
+    /// assert_eq!(ui.eval("clicks").unwrap(), 1);
+    /// ```
+    pub fn dispatch_mouse_event(&self, event: MouseEvent) -> Result<(), JSError> {
+        dispatch_mouse_event(self.chrome.clone(), event)
+    }
+
+    /// A handle that sends mouse events to the page like [`UI::dispatch_mouse_event`], for
+    /// threads that only need to forward input and do not own the `UI`
+    pub fn mouse_input(&self) -> MouseInput {
+        MouseInput {
+            chrome: self.chrome.clone(),
+        }
+    }
+
     /// It changes the size, position or state of the browser window specified by the `Bounds` struct. It returns Err if it fails.
     ///
     /// To change the window state alone use `WindowState::to_bounds()`
@@ -384,9 +795,47 @@ impl UI {
     }
 }
 
+/// A `data:` URL for `html`. The page is base64 encoded so that `#`, `%` and non-ASCII text
+/// are not taken as URL syntax.
+fn html_data_url(html: &str) -> String {
+    format!(
+        "data:text/html;charset=utf-8;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(html)
+    )
+}
+
+// 把 JS 参数列表反序列化为 A；没有参数时也接受 `()` 这样从 null 反序列化的类型
+fn decode_args<A: serde::de::DeserializeOwned>(name: &str, args: &[JSObject]) -> Result<A, JSObject> {
+    serde_json::from_value(JSObject::Array(args.to_vec())).or_else(|e| {
+        if args.is_empty() {
+            if let Ok(value) = serde_json::from_value(JSObject::Null) {
+                return Ok(value);
+            }
+        }
+        Err(JSObject::String(format!("Invalid arguments for {}: {}", name, e)))
+    })
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> JSObject {
+    let message = if let Some(s) = panic.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    };
+    JSObject::String(format!("Rust binding panicked: {}", message))
+}
+
 /// Closes the browser window
 impl Drop for UI {
     fn drop(&mut self) {
+        if !self.owns_browser {
+            if !self.done() {
+                self.close();
+            }
+            return;
+        }
         if !self.waited.load(Ordering::Relaxed) && !self.done() {
             self.close();
             self.wait_finish();
@@ -416,6 +865,9 @@ pub struct UIBuilder<'a> {
     height: i32,
     custom_args: &'a [&'a str],
     browser_path: Option<&'a str>,
+    timeout: Option<Duration>,
+    min_version: Option<u32>,
+    interactive: bool,
 }
 
 impl<'a> Default for UIBuilder<'a> {
@@ -434,6 +886,9 @@ impl<'a> UIBuilder<'a> {
             height: 0,
             custom_args: &[],
             browser_path: None,
+            timeout: None,
+            min_version: None,
+            interactive: true,
         }
     }
 
@@ -448,7 +903,7 @@ impl<'a> UIBuilder<'a> {
         let url = match self.content {
             Content::Url(u) => u,
             Content::Html(h) => {
-                html = format!("data:text/html,{}", h);
+                html = html_data_url(h);
                 &html
             }
         };
@@ -468,9 +923,18 @@ impl<'a> UIBuilder<'a> {
                         return Err(UILaunchError::BrowserPathInvalid(path));
                     }
                 }
-                Err(_) => locate_chrome()?,
+                Err(_) => locate_chrome(self.interactive)?,
             }
         };
+        // 过旧的浏览器可能根本无法通过管道通信，先在启动前根据安装目录或 --version 检查一次
+        if let Some(minimum) = self.min_version {
+            if let Some(version) = detect_version(std::path::Path::new(&chrome_path)) {
+                let major = version.split('.').next().and_then(|m| m.parse::<u32>().ok());
+                if major.is_some_and(|major| major < minimum) {
+                    return Err(UILaunchError::BrowserTooOld { version, minimum });
+                }
+            }
+        }
         let mut args: Vec<String> = DEFAULT_CHROME_ARGS.iter().map(|s| s.to_string()).collect();
         // 自动生成 user-data-dir
         let tmpdir = tempfile::TempDir::new()?;
@@ -486,9 +950,7 @@ impl<'a> UIBuilder<'a> {
         }
         args.push("--remote-debugging-pipe".to_string());
         // kiosk模式：不加--app，直接加url
-        if self.custom_args.iter().any(|&a| a == "--kiosk") {
-            args.push(url.to_string());
-        } else if self.custom_args.contains(&"--headless") {
+        if self.custom_args.contains(&"--kiosk") || self.custom_args.contains(&"--headless") {
             args.push(url.to_string());
         } else {
             let app_arg = format!("--app={}", url);
@@ -502,12 +964,23 @@ impl<'a> UIBuilder<'a> {
         //     println!("    {}", arg);
         // }
         // println!("========================================\n");
-        let chrome = Chrome::new_with_args(&chrome_path, &args_ref)?;
-        Ok(UI {
+        let chrome = Chrome::new_with_args(&chrome_path, &args_ref, self.timeout)?;
+        let ui = UI {
             chrome,
             _tmpdir: Some(tmpdir),
             waited: AtomicBool::new(false),
-        })
+            owns_browser: true,
+        };
+        if let (Some(minimum), Some(version)) = (self.min_version, ui.browser_version()) {
+            if version.major().is_some_and(|major| major < minimum) {
+                // 丢弃 ui 时会关闭浏览器
+                return Err(UILaunchError::BrowserTooOld {
+                    version: version.product.clone(),
+                    minimum,
+                });
+            }
+        }
+        Ok(ui)
     }
 
     /// Set the content (url or html text)
@@ -529,6 +1002,30 @@ impl<'a> UIBuilder<'a> {
         self
     }
 
+    /// Set how long to wait for the browser to answer a command, for example `eval()`. By default
+    /// it waits forever.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Refuse to use a browser whose major version is lower than `major`. [`UIBuilder::run`]
+    /// then fails with [`UILaunchError::BrowserTooOld`] instead of an error while talking to the
+    /// browser.
+    pub fn min_version(&mut self, major: u32) -> &mut Self {
+        self.min_version = Some(major);
+        self
+    }
+
+    /// Whether alcro may show dialogs, such as the prompt to download Chrome when no browser is
+    /// found. With `false` [`UIBuilder::run`] fails with
+    /// [`LocateChromeError::ChromeNotInstalledError`] instead. Setting [`NONINTERACTIVE_ENV`] has
+    /// the same effect. It is `true` by default.
+    pub fn interactive(&mut self, interactive: bool) -> &mut Self {
+        self.interactive = interactive;
+        self
+    }
+
     /// Add custom arguments to spawn chrome with
     pub fn custom_args(&mut self, custom_args: &'a [&'a str]) -> &mut Self {
         self.custom_args = custom_args;
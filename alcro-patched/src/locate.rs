@@ -1,65 +1,344 @@
 #[cfg(target_os = "windows")]
 use std::env::var;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 pub use tinyfiledialogs;
 use tinyfiledialogs::{message_box_yes_no, MessageBoxIcon, YesNo};
 
 #[cfg(target_os = "macos")]
-const PATHS: &[&str] = &[
-    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
-    "/Applications/Google Chrome Canary.app/Contents/MacOS/Google Chrome Canary",
-    "/Applications/Chromium.app/Contents/MacOS/Chromium",
-    "/usr/bin/google-chrome-stable",
-    "/usr/bin/google-chrome",
-    "/usr/bin/chromium",
-    "/usr/bin/chromium-browser",
+const PATHS: &[(&str, &str)] = &[
+    ("Google Chrome", "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome"),
+    ("Google Chrome Canary", "/Applications/Google Chrome Canary.app/Contents/MacOS/Google Chrome Canary"),
+    ("Chromium", "/Applications/Chromium.app/Contents/MacOS/Chromium"),
+    ("Microsoft Edge", "/Applications/Microsoft Edge.app/Contents/MacOS/Microsoft Edge"),
+    ("Brave", "/Applications/Brave Browser.app/Contents/MacOS/Brave Browser"),
+    ("Vivaldi", "/Applications/Vivaldi.app/Contents/MacOS/Vivaldi"),
+    ("Google Chrome", "/usr/bin/google-chrome-stable"),
+    ("Google Chrome", "/usr/bin/google-chrome"),
+    ("Chromium", "/usr/bin/chromium"),
+    ("Chromium", "/usr/bin/chromium-browser"),
 ];
 
 #[cfg(target_os = "linux")]
-const PATHS: &[&str] = &[
-    "/usr/bin/google-chrome-stable",
-    "/usr/bin/google-chrome",
-    "/usr/bin/chromium",
-    "/usr/bin/chromium-browser",
-    "/snap/bin/chromium",
+const PATHS: &[(&str, &str)] = &[
+    ("Google Chrome", "/usr/bin/google-chrome-stable"),
+    ("Google Chrome", "/usr/bin/google-chrome"),
+    ("Google Chrome", "/opt/google/chrome/chrome"),
+    ("Chromium", "/usr/bin/chromium"),
+    ("Chromium", "/usr/bin/chromium-browser"),
+    ("Microsoft Edge", "/usr/bin/microsoft-edge-stable"),
+    ("Microsoft Edge", "/usr/bin/microsoft-edge"),
+    ("Microsoft Edge", "/opt/microsoft/msedge/msedge"),
+    ("Brave", "/usr/bin/brave-browser"),
+    ("Brave", "/opt/brave.com/brave/brave"),
+    ("Vivaldi", "/usr/bin/vivaldi-stable"),
+    ("Vivaldi", "/usr/bin/vivaldi"),
+    ("Vivaldi", "/opt/vivaldi/vivaldi"),
 ];
 
 #[cfg(target_family = "unix")]
-fn paths() -> &'static [&'static str] {
+fn paths() -> Vec<(&'static str, String)> {
     PATHS
+        .iter()
+        .map(|(name, path)| (*name, path.to_string()))
+        .collect()
 }
 
 #[cfg(target_os = "windows")]
-fn paths() -> [String; 7] {
-    [
-        var("LocalAppData").unwrap() + "/Google/Chrome/Application/chrome.exe",
-        var("ProgramFiles").unwrap() + "/Google/Chrome/Application/chrome.exe",
-        var("ProgramFiles(x86)").unwrap() + "/Google/Chrome/Application/chrome.exe",
-        var("LocalAppData").unwrap() + "/Chromium/Application/chrome.exe",
-        var("ProgramFiles").unwrap() + "/Chromium/Application/chrome.exe",
-        var("ProgramFiles(x86)").unwrap() + "/Chromium/Application/chrome.exe",
-        var("ProgramFiles(x86)").unwrap() + "/Microsoft/Edge/Application/msedge.exe",
-    ]
+fn paths() -> Vec<(&'static str, String)> {
+    const INSTALLS: &[(&str, &str)] = &[
+        ("Google Chrome", "/Google/Chrome/Application/chrome.exe"),
+        ("Chromium", "/Chromium/Application/chrome.exe"),
+        ("Microsoft Edge", "/Microsoft/Edge/Application/msedge.exe"),
+        ("Brave", "/BraveSoftware/Brave-Browser/Application/brave.exe"),
+        ("Vivaldi", "/Vivaldi/Application/vivaldi.exe"),
+    ];
+    let mut paths = Vec::new();
+    for (name, suffix) in INSTALLS {
+        for root in ["LocalAppData", "ProgramFiles", "ProgramFiles(x86)"] {
+            // 精简版系统中可能缺少其中某些环境变量
+            if let Ok(root) = var(root) {
+                paths.push((*name, root + suffix));
+            }
+        }
+    }
+    paths
+}
+
+/// Executable names searched in `PATH`, with the name of the browser they belong to
+#[cfg(target_family = "unix")]
+const EXECUTABLES: &[(&str, &str)] = &[
+    ("Google Chrome", "google-chrome-stable"),
+    ("Google Chrome", "google-chrome"),
+    ("Chromium", "chromium"),
+    ("Chromium", "chromium-browser"),
+    ("Microsoft Edge", "microsoft-edge-stable"),
+    ("Microsoft Edge", "microsoft-edge"),
+    ("Brave", "brave-browser"),
+    ("Brave", "brave"),
+    ("Vivaldi", "vivaldi-stable"),
+    ("Vivaldi", "vivaldi"),
+];
+
+#[cfg(target_os = "windows")]
+const EXECUTABLES: &[(&str, &str)] = &[
+    ("Google Chrome", "chrome.exe"),
+    ("Microsoft Edge", "msedge.exe"),
+    ("Brave", "brave.exe"),
+    ("Vivaldi", "vivaldi.exe"),
+];
+
+/// Flatpak application ids, looked up in the system and user export directories
+#[cfg(target_os = "linux")]
+const FLATPAKS: &[(&str, &str)] = &[
+    ("Google Chrome", "com.google.Chrome"),
+    ("Chromium", "org.chromium.Chromium"),
+    ("Microsoft Edge", "com.microsoft.Edge"),
+    ("Brave", "com.brave.Browser"),
+    ("Vivaldi", "com.vivaldi.Vivaldi"),
+];
+
+#[cfg(target_os = "linux")]
+const SNAPS: &[(&str, &str)] = &[("Chromium", "chromium"), ("Brave", "brave")];
+
+/// Directories next to the application that may contain a portable browser, for example
+/// `chrome/chrome.exe` or `browser/chrome-120.0.6099.109/chrome.exe`
+const PORTABLE_DIRS: &[&str] = &["chrome", "chromium", "browser", "browsers"];
+
+/// Where a browser was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserSource {
+    /// A portable browser in the directory of the application
+    Portable,
+    /// A well known installation path
+    Installed,
+    /// An executable in `PATH`
+    Path,
+    /// A Flatpak export. These are `flatpak run` wrapper scripts that do not pass on the file
+    /// descriptors `--remote-debugging-pipe` needs, so they are listed but not supported.
+    Flatpak,
+    /// A Snap package
+    Snap,
 }
 
+/// A Chromium based browser found on this computer
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct BrowserCandidate {
+    /// The name of the browser, for example "Microsoft Edge"
+    pub name: String,
+    /// Path of the executable
+    pub path: String,
+    /// The detected version, for example "120.0.6099.109"
+    pub version: Option<String>,
+    /// Where the browser was found
+    pub source: BrowserSource,
+    /// Whether the browser can be used. Unsupported browsers are never chosen automatically.
+    pub supported: bool,
+}
+
+impl BrowserSource {
+    fn supported(self) -> bool {
+        self != BrowserSource::Flatpak
+    }
+}
+
+/// Error in locating chrome
 #[derive(Debug, thiserror::Error)]
 pub enum LocateChromeError {
+    /// No supported browser was found
     #[error("An installation of chrome/chromium could not be found")]
     ChromeNotInstalledError,
+    /// The prompt to download chrome could not be shown
     #[error("Download chrome prompt could not be displayed: {0}")]
     PromptError(#[from] PromptError),
 }
 
-pub fn locate_chrome() -> Result<String, LocateChromeError> {
-    for path in paths().iter() {
-        if Path::new(path).exists() {
-            return Ok(path.to_string());
-        }
+/// Environment variable that turns off the download prompt shown when no browser is found, for
+/// unattended deployments, headless tests and services. Any value except "" and "0" counts.
+pub const NONINTERACTIVE_ENV: &str = "ALCRO_NONINTERACTIVE";
+
+/// Whether [`NONINTERACTIVE_ENV`] is set
+pub(crate) fn noninteractive_from_env() -> bool {
+    std::env::var_os(NONINTERACTIVE_ENV).is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+/// Returns the first supported browser. If there is none and `interactive` is true, it asks the
+/// user whether to download Chrome before failing; otherwise it fails right away.
+pub fn locate_chrome(interactive: bool) -> Result<String, LocateChromeError> {
+    if let Some((_, path, _)) = find_browsers()
+        .into_iter()
+        .find(|(_, _, source)| source.supported())
+    {
+        return Ok(path);
+    }
+    if interactive && !noninteractive_from_env() {
+        prompt_download()?;
     }
-    prompt_download()?;
     Err(LocateChromeError::ChromeNotInstalledError)
 }
 
+/// Returns all the browsers that can be used, with their versions. Portable browsers next to the
+/// application come first, then installed ones, `PATH`, Flatpak and Snap. The first supported one
+/// is the browser [`UIBuilder::run`](crate::UIBuilder::run) uses by default.
+///
+/// Detecting the version may start each browser with `--version`, so this can take a moment.
+pub fn locate_browsers() -> Vec<BrowserCandidate> {
+    find_browsers()
+        .into_iter()
+        .map(|(name, path, source)| BrowserCandidate {
+            version: detect_version(Path::new(&path)),
+            name: name.to_string(),
+            path,
+            supported: source.supported(),
+            source,
+        })
+        .collect()
+}
+
+fn find_browsers() -> Vec<(&'static str, String, BrowserSource)> {
+    let mut found = portable_browsers();
+    for (name, path) in paths() {
+        found.push((name, path, BrowserSource::Installed));
+    }
+    found.extend(path_browsers());
+    #[cfg(target_os = "linux")]
+    {
+        let mut flatpak_dirs = vec![PathBuf::from("/var/lib/flatpak/exports/bin")];
+        if let Some(home) = std::env::var_os("HOME") {
+            flatpak_dirs.push(PathBuf::from(home).join(".local/share/flatpak/exports/bin"));
+        }
+        for dir in flatpak_dirs {
+            for (name, id) in FLATPAKS {
+                found.push((name, dir.join(id).to_string_lossy().to_string(), BrowserSource::Flatpak));
+            }
+        }
+        for (name, snap) in SNAPS {
+            found.push((name, format!("/snap/bin/{}", snap), BrowserSource::Snap));
+        }
+    }
+
+    // 同一个浏览器可能通过多种方式找到（例如 PATH 中的符号链接），按真实路径去重
+    let mut seen = std::collections::HashSet::new();
+    found
+        .into_iter()
+        .filter(|(_, path, _)| Path::new(path).is_file())
+        .filter(|(_, path, _)| {
+            let real = std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+            seen.insert(real)
+        })
+        .collect()
+}
+
+fn path_browsers() -> Vec<(&'static str, String, BrowserSource)> {
+    let mut found = Vec::new();
+    if let Some(path_var) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&path_var) {
+            for (name, exe) in EXECUTABLES {
+                let path = dir.join(exe);
+                if path.is_file() {
+                    found.push((*name, path.to_string_lossy().to_string(), BrowserSource::Path));
+                }
+            }
+        }
+    }
+    found
+}
+
+fn portable_browsers() -> Vec<(&'static str, String, BrowserSource)> {
+    let mut roots = Vec::new();
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        roots.push(dir);
+    }
+    if let Ok(dir) = std::env::current_dir() {
+        if !roots.contains(&dir) {
+            roots.push(dir);
+        }
+    }
+    let mut found = Vec::new();
+    for root in roots {
+        for portable in PORTABLE_DIRS {
+            let dir = root.join(portable);
+            if !dir.is_dir() {
+                continue;
+            }
+            // 浏览器可以直接放在目录中，也可以放在以版本号命名的子目录中
+            let mut dirs = vec![dir.clone()];
+            if let Ok(entries) = std::fs::read_dir(&dir) {
+                let mut subdirs: Vec<PathBuf> = entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.is_dir())
+                    .collect();
+                // 版本较新的排在前面
+                subdirs.sort_by_key(|p| {
+                    std::cmp::Reverse(version_key(&p.file_name().unwrap_or_default().to_string_lossy()))
+                });
+                dirs.extend(subdirs);
+            }
+            for dir in dirs {
+                for (name, exe) in EXECUTABLES {
+                    for path in [dir.join(exe), dir.join("Application").join(exe)] {
+                        if path.is_file() {
+                            found.push((*name, path.to_string_lossy().to_string(), BrowserSource::Portable));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Extracts the first dotted version number, e.g. "120.0.6099.109" from
+/// "Google Chrome 120.0.6099.109 unknown" or "chrome-120.0.6099.109".
+fn parse_version(text: &str) -> Option<String> {
+    text.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .map(|part| part.trim_matches('.'))
+        .find(|part| part.contains('.') && part.split('.').all(|n| !n.is_empty()))
+        .map(|part| part.to_string())
+}
+
+fn version_key(text: &str) -> Vec<u32> {
+    parse_version(text)
+        .map(|v| v.split('.').filter_map(|n| n.parse().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Whether `text` is a full Chrome version number with four parts, e.g. "120.0.6099.109"
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+fn is_full_version(text: &str) -> bool {
+    let parts: Vec<&str> = text.split('.').collect();
+    parts.len() == 4
+        && parts
+            .iter()
+            .all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+pub(crate) fn detect_version(path: &Path) -> Option<String> {
+    // Windows 的安装目录和便携版中，浏览器旁边有以完整版本号命名的子目录
+    // （chrome.exe --version 在 Windows 上不会输出版本）；其他名称的子目录不算
+    #[cfg(target_os = "windows")]
+    {
+        path.parent().and_then(|dir| {
+            std::fs::read_dir(dir)
+                .ok()?
+                .flatten()
+                .filter(|e| e.path().is_dir())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|name| is_full_version(name))
+                .max_by_key(|v| version_key(v))
+        })
+    }
+    #[cfg(target_family = "unix")]
+    {
+        let output = Command::new(path).arg("--version").output().ok()?;
+        parse_version(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
 use std::process::Command;
 
 #[derive(Debug, thiserror::Error)]
@@ -97,6 +376,21 @@ mod tests {
     use super::*;
     #[test]
     fn test_locate_chrome() {
-        assert!(locate_chrome().is_ok())
+        assert!(locate_chrome(false).is_ok())
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(
+            parse_version("Google Chrome 120.0.6099.109 unknown").as_deref(),
+            Some("120.0.6099.109")
+        );
+        assert_eq!(parse_version("chrome-win64-119.0.1").as_deref(), Some("119.0.1"));
+        assert_eq!(parse_version("Chromium 64 beta"), None);
+        assert!(version_key("120.0.1") > version_key("99.1.0"));
+        assert!(is_full_version("120.0.6099.109"));
+        assert!(!is_full_version("python3.11"));
+        assert!(!is_full_version("3.11"));
+        assert!(!is_full_version("1.2.3.4.5"));
     }
 }
@@ -1,4 +1,8 @@
-use alcro::{Content, UIBuilder};
+use alcro::{
+    Content, DeviceMetrics, InterceptAction, InterceptStage, JSError, MouseButton, MouseEvent, MouseEventKind,
+    ScreencastFormat, UIBuilder, UILaunchError,
+};
+use std::time::Duration;
 
 #[test]
 fn test_content() {
@@ -33,6 +37,248 @@ fn test_eval() {
     assert!(ui.eval("dtyfhgxnt*").is_err());
 }
 
+#[test]
+fn test_eval_timeout() {
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .timeout(Duration::from_secs(5))
+        .run()
+        .expect("Unable to launch");
+    assert!(matches!(
+        ui.eval_with_timeout("new Promise(() => {})", Duration::from_millis(200)),
+        Err(JSError::Timeout(_))
+    ));
+    // The browser is still usable after a timed out call
+    assert_eq!(ui.eval("2+2").unwrap(), 4);
+}
+
+#[test]
+fn test_eval_errors() {
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .run()
+        .expect("Unable to launch");
+    match ui.eval_with_timeout("throw new Error('x')", Duration::from_secs(5)) {
+        Err(JSError::Exception { text, value, .. }) => {
+            assert!(!text.is_empty());
+            assert!(value.as_str().unwrap().contains('x'));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(ui.eval_with_timeout("1", Duration::from_secs(5)).is_ok());
+}
+
+#[test]
+fn test_reload_hung_page() {
+    let ui = UIBuilder::new()
+        .content(Content::Html("<html><body>hello</body></html>"))
+        .custom_args(&["--headless"])
+        .timeout(Duration::from_secs(5))
+        .run()
+        .expect("Unable to launch");
+    assert!(matches!(
+        ui.eval_with_timeout("while (true) {}", Duration::from_millis(500)),
+        Err(JSError::Timeout(_))
+    ));
+    ui.reload(Duration::from_secs(5)).unwrap();
+    std::thread::sleep(Duration::from_secs(1));
+    assert_eq!(ui.eval("document.body.innerText").unwrap(), "hello");
+}
+
+#[test]
+fn test_crashed() {
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .timeout(Duration::from_secs(5))
+        .run()
+        .expect("Unable to launch");
+    assert!(!ui.crashed());
+    let _ = ui.load(Content::Url("chrome://crash"));
+    std::thread::sleep(Duration::from_secs(1));
+    assert!(ui.crashed());
+    assert!(ui.eval_with_timeout("1", Duration::from_secs(1)).unwrap_err().needs_restart());
+}
+
+#[test]
+fn test_new_window() {
+    let ui = UIBuilder::new()
+        .content(Content::Html("<html><body>first</body></html>"))
+        .custom_args(&["--headless"])
+        .timeout(Duration::from_secs(5))
+        .run()
+        .expect("Unable to launch");
+    let second = ui
+        .new_window(Content::Html("<html><body>second #2 100%</body></html>"))
+        .expect("Unable to open window");
+    assert_eq!(ui.eval("document.body.innerText").unwrap(), "first");
+    assert_eq!(
+        second.eval("document.body.innerText").unwrap(),
+        "second #2 100%"
+    );
+
+    // Bindings with the same name are routed to the window that registered them
+    ui.bind("which", |_| Ok(1.into())).unwrap();
+    second.bind("which", |_| Ok(2.into())).unwrap();
+    assert_eq!(ui.eval("which()").unwrap(), 1);
+    assert_eq!(second.eval("which()").unwrap(), 2);
+
+    second.close();
+    second.wait_finish();
+    assert!(!ui.done());
+    assert_eq!(ui.eval("1+1").unwrap(), 2);
+}
+
+#[test]
+fn test_browser_version() {
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .run()
+        .expect("Unable to launch");
+    assert!(ui.browser_version().unwrap().major().unwrap() > 0);
+
+    assert!(matches!(
+        UIBuilder::new()
+            .custom_args(&["--headless"])
+            .min_version(9999)
+            .run(),
+        Err(UILaunchError::BrowserTooOld { minimum: 9999, .. })
+    ));
+}
+
+#[test]
+fn test_intercept() {
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .timeout(Duration::from_secs(5))
+        .run()
+        .expect("Unable to launch");
+    ui.intercept(&["https://alcro.test/*"], |request| {
+        if request.matches("*/blocked") {
+            InterceptAction::Fail("BlockedByClient".to_string())
+        } else {
+            InterceptAction::Fulfill {
+                status: 200,
+                headers: vec![
+                    ("Content-Type".to_string(), "text/plain".to_string()),
+                    ("Access-Control-Allow-Origin".to_string(), "*".to_string()),
+                ],
+                body: format!("mapped {}", request.method).into_bytes(),
+            }
+        }
+    })
+    .unwrap();
+    assert_eq!(
+        ui.eval("fetch('https://alcro.test/hello.txt').then(r => r.text())").unwrap(),
+        "mapped GET"
+    );
+    assert!(ui.eval("fetch('https://alcro.test/blocked')").is_err());
+
+    ui.stop_intercepting().unwrap();
+    assert!(ui.eval("fetch('https://alcro.test/hello.txt')").is_err());
+}
+
+#[test]
+fn test_intercept_response() {
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .timeout(Duration::from_secs(5))
+        .run()
+        .expect("Unable to launch");
+    ui.intercept_at(&[("http://alcro.invalid/*", InterceptStage::Response)], |request| {
+        match request.response {
+            Some(ref response) if response.error.is_some() => InterceptAction::Fulfill {
+                status: 200,
+                headers: vec![("Access-Control-Allow-Origin".to_string(), "*".to_string())],
+                body: b"from cache".to_vec(),
+            },
+            _ => InterceptAction::Continue,
+        }
+    })
+    .unwrap();
+    assert_eq!(
+        ui.eval("fetch('http://alcro.invalid/page').then(r => r.text())").unwrap(),
+        "from cache"
+    );
+}
+
+#[test]
+fn test_emulation() {
+    let ui = UIBuilder::new()
+        .content(Content::Html("<html><body>zoom</body></html>"))
+        .custom_args(&["--headless"])
+        .timeout(Duration::from_secs(5))
+        .run()
+        .expect("Unable to launch");
+    ui.set_device_metrics(DeviceMetrics {
+        width: 800,
+        height: 600,
+        device_scale_factor: 2.0,
+        mobile: false,
+    })
+    .unwrap();
+    assert_eq!(ui.eval("window.devicePixelRatio").unwrap(), 2);
+    assert_eq!(ui.eval("window.innerWidth").unwrap(), 800);
+    ui.clear_device_metrics().unwrap();
+
+    ui.set_timezone("Asia/Shanghai").unwrap();
+    assert_eq!(
+        ui.eval("Intl.DateTimeFormat().resolvedOptions().timeZone").unwrap(),
+        "Asia/Shanghai"
+    );
+
+    ui.set_zoom(1.5).unwrap();
+    assert_eq!(ui.eval("document.documentElement.style.zoom").unwrap(), "1.5");
+    // The zoom is applied again after a reload
+    ui.reload(Duration::from_secs(5)).unwrap();
+    std::thread::sleep(Duration::from_secs(1));
+    assert_eq!(ui.eval("document.documentElement.style.zoom").unwrap(), "1.5");
+    ui.set_zoom(1.0).unwrap();
+    assert_eq!(ui.eval("document.documentElement.style.zoom").unwrap(), "");
+}
+
+#[test]
+fn test_virtual_time() {
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .timeout(Duration::from_secs(5))
+        .run()
+        .expect("Unable to launch");
+    ui.set_timezone("Asia/Shanghai").unwrap();
+    ui.set_virtual_time("2025-09-01T07:54:00", false).unwrap();
+    assert_eq!(ui.eval("new Date().getDay()").unwrap(), 1);
+    assert_eq!(ui.eval("new Date().getMinutes()").unwrap(), 54);
+    assert_eq!(ui.eval("Date.now() === new Date().getTime()").unwrap(), true);
+    assert_eq!(ui.eval("new Date(2000, 0, 1).getFullYear()").unwrap(), 2000);
+    assert_eq!(ui.eval("new Date() instanceof Date").unwrap(), true);
+    assert_eq!(ui.eval("typeof Date()").unwrap(), "string");
+
+    ui.advance_virtual_time(Duration::from_secs(60)).unwrap();
+    assert_eq!(ui.eval("new Date().getMinutes()").unwrap(), 55);
+
+    // The clock is installed before the scripts of new pages run
+    ui.load(Content::Html(
+        "<html><body><script>document.body.innerText = new Date().getMinutes()</script></body></html>",
+    ))
+    .unwrap();
+    std::thread::sleep(Duration::from_secs(1));
+    assert_eq!(ui.eval("document.body.innerText").unwrap(), "55");
+
+    ui.clear_virtual_time().unwrap();
+    assert!(ui.eval("new Date().getFullYear()").unwrap().as_i64().unwrap() >= 2025);
+    assert!(ui.set_virtual_time("not a time", false).is_err());
+}
+
+#[test]
+fn test_bind_panic() {
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .run()
+        .expect("Unable to launch");
+    ui.bind("explode", |_| panic!("boom")).unwrap();
+    let err = ui.eval("explode()").unwrap_err();
+    assert!(err.as_str().unwrap().contains("boom"));
+}
+
 #[test]
 fn test_bind_async() {
     let ui = UIBuilder::new()
@@ -63,3 +309,125 @@ fn test_bind_async() {
 
     assert_eq!(ui.eval("foo('a')").unwrap(), "abcd");
 }
+
+#[test]
+fn test_bind_typed() {
+    #[derive(serde::Deserialize)]
+    struct Lesson {
+        name: String,
+        minutes: u32,
+    }
+
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .run()
+        .expect("Unable to launch");
+    ui.bind_typed("add", |(a, b): (i64, i64)| Ok(a + b)).unwrap();
+    ui.bind_typed("describe", |lesson: Lesson| {
+        Ok(format!("{} {}", lesson.name, lesson.minutes))
+    })
+    .unwrap();
+    ui.bind_typed("answer", |()| Ok(42)).unwrap();
+    ui.bind_typed("fail", |(message,): (String,)| Err::<(), _>(message.into()))
+        .unwrap();
+
+    assert_eq!(ui.eval("add(1, 2)").unwrap(), 3);
+    assert_eq!(ui.eval("describe('math', 40)").unwrap(), "math 40");
+    assert_eq!(ui.eval("answer()").unwrap(), 42);
+    assert_eq!(ui.eval("fail('oops')").unwrap_err(), "oops");
+
+    let err = ui.eval("add(1, 'two')").unwrap_err();
+    assert!(err.as_str().unwrap().starts_with("Invalid arguments for add:"), "{}", err);
+    let err = ui.eval("describe('math')").unwrap_err();
+    assert!(err.as_str().unwrap().contains("invalid length 1"), "{}", err);
+}
+
+#[test]
+fn test_emit() {
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .run()
+        .expect("Unable to launch");
+    ui.eval(
+        "window.received = [];
+         addEventListener('lesson', e => received.push(e.detail));
+         addEventListener('cancel-me', e => e.preventDefault());",
+    )
+    .unwrap();
+
+    let payload = serde_json::json!({ "name": "</script>'\"${x}\u{2028}", "period": 3 });
+    assert!(ui.emit("lesson", &payload).unwrap());
+    assert!(ui.emit("lesson", "plain").unwrap());
+    assert_eq!(
+        ui.eval("received").unwrap(),
+        serde_json::json!([payload, "plain"])
+    );
+
+    assert!(!ui.emit("cancel-me", &()).unwrap());
+    assert!(ui.emit("'); throw 1; ('", &1).unwrap());
+}
+
+#[test]
+fn test_screencast() {
+    let ui = UIBuilder::new()
+        .custom_args(&["--headless"])
+        .run()
+        .expect("Unable to launch");
+    let (tx, rx) = std::sync::mpsc::channel();
+    let tx = std::sync::Mutex::new(tx);
+    ui.start_screencast(ScreencastFormat::Png, 100, Some((320, 240)), move |frame| {
+        let _ = tx.lock().unwrap().send(frame);
+    })
+    .unwrap();
+    // A frame is only sent when the page changes
+    ui.eval("document.body.style.background = 'red'").unwrap();
+
+    let frame = rx.recv_timeout(Duration::from_secs(10)).expect("No frame received");
+    assert_eq!(&frame.data[..8], b"\x89PNG\r\n\x1a\n");
+    assert!(frame.device_width > 0.0 && frame.device_height > 0.0);
+    ui.stop_screencast().unwrap();
+}
+
+#[test]
+fn test_dispatch_mouse_event() {
+    let ui = UIBuilder::new()
+        .content(Content::Html(
+            "<html><body style='margin: 0'><button style='width: 100px; height: 50px'>Click</button></body></html>",
+        ))
+        .custom_args(&["--headless"])
+        .run()
+        .expect("Unable to launch");
+    ui.eval(
+        "window.received = [];
+         for (const type of ['mousemove', 'click', 'dblclick', 'wheel']) {
+             document.querySelector('button').addEventListener(type, e => received.push([type, e.detail]));
+         }",
+    )
+    .unwrap();
+
+    ui.dispatch_mouse_event(MouseEvent::moved(20.0, 20.0, 0)).unwrap();
+    let input = ui.mouse_input();
+    for click_count in [1, 2] {
+        for kind in [MouseEventKind::MousePressed, MouseEventKind::MouseReleased] {
+            input
+                .dispatch(MouseEvent {
+                    kind,
+                    button: MouseButton::Left,
+                    click_count,
+                    ..MouseEvent::moved(20.0, 20.0, 0)
+                })
+                .unwrap();
+        }
+    }
+    input
+        .dispatch(MouseEvent {
+            kind: MouseEventKind::MouseWheel,
+            delta_y: 100.0,
+            ..MouseEvent::moved(20.0, 20.0, 0)
+        })
+        .unwrap();
+    assert_eq!(
+        ui.eval("received").unwrap(),
+        serde_json::json!([["mousemove", 0], ["click", 1], ["click", 2], ["dblclick", 2], ["wheel", 0]])
+    );
+}
@@ -0,0 +1,324 @@
+// 页面可调用的 Rust 函数。主窗口和设置窗口共用这一份注册表，每个函数注明在哪些窗口中可用；
+// 参数和返回值通过 alcro 的 bind_typed 按类型转换，参数不对时页面收到指明函数名和原因的错误
+
+use crate::platform::{self, Monitor};
+use crate::{offline_cache, widget, AppState, BROWSER_TIMEOUT};
+use alcro::{JSError, JSObject, UI};
+use once_cell::sync::OnceCell;
+use serde::{de::DeserializeOwned, Serialize};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use toml::Value as TomlValue;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    Main,
+    Settings,
+}
+
+const ALL: &[Page] = &[Page::Main, Page::Settings];
+const MAIN: &[Page] = &[Page::Main];
+const SETTINGS: &[Page] = &[Page::Settings];
+
+// 注册时传入页面所在的显示器，设置窗口没有所在的显示器
+type Register = Box<dyn Fn(&UI, &str, Option<&Monitor>) -> Result<(), JSError> + Send + Sync>;
+
+struct Binding {
+    name: &'static str,
+    pages: &'static [Page],
+    register: Register,
+}
+
+pub struct Registry {
+    bindings: Vec<Binding>,
+}
+
+impl Registry {
+    fn add<A, R, F>(&mut self, name: &'static str, pages: &'static [Page], f: F) -> &mut Self
+    where
+        A: DeserializeOwned + 'static,
+        R: Serialize + 'static,
+        F: Fn(A) -> Result<R, JSObject> + Send + Sync + 'static,
+    {
+        let f = Arc::new(f);
+        self.bindings.push(Binding {
+            name,
+            pages,
+            register: Box::new(move |ui, name, _| {
+                let f = Arc::clone(&f);
+                ui.bind_typed(name, move |args| f(args))
+            }),
+        });
+        self
+    }
+
+    // 与 add 相同，但函数还会收到页面所在的显示器；每次调用时按 id 重新查找，分辨率改变后返回新的信息
+    fn add_with_monitor<A, R, F>(&mut self, name: &'static str, pages: &'static [Page], f: F) -> &mut Self
+    where
+        A: DeserializeOwned + 'static,
+        R: Serialize + 'static,
+        F: Fn(Option<&Monitor>, A) -> Result<R, JSObject> + Send + Sync + 'static,
+    {
+        let f = Arc::new(f);
+        self.bindings.push(Binding {
+            name,
+            pages,
+            register: Box::new(move |ui, name, monitor| {
+                let f = Arc::clone(&f);
+                let monitor = monitor.cloned();
+                ui.bind_typed(name, move |args| {
+                    let current = monitor.as_ref().map(|bound| {
+                        platform::monitors()
+                            .into_iter()
+                            .find(|m| m.id == bound.id)
+                            .unwrap_or_else(|| bound.clone())
+                    });
+                    f(current.as_ref(), args)
+                })
+            }),
+        });
+        self
+    }
+
+    // 把 page 可用的函数全部绑定到 ui，单个函数绑定失败只记录日志
+    pub fn bind(&self, ui: &UI, page: Page, monitor: Option<&Monitor>) {
+        for binding in self.bindings.iter().filter(|b| b.pages.contains(&page)) {
+            if let Err(e) = (binding.register)(ui, binding.name, monitor) {
+                log::warn!("[ClassPaper] 绑定 {} 失败: {}", binding.name, e);
+            }
+        }
+    }
+}
+
+static REGISTRY: OnceCell<Registry> = OnceCell::new();
+
+// 程序启动时建立注册表，之后创建的窗口都从这里绑定
+pub fn init(app_state: Arc<Mutex<AppState>>) {
+    let mut registry = Registry { bindings: Vec::new() };
+    let app_state_widget = Arc::clone(&app_state);
+    registry
+        // 页面所在显示器的尺寸
+        .add_with_monitor("getWidth", MAIN, |monitor, ()| {
+            Ok(monitor.map_or_else(|| platform::backend().screen_size().0, |m| m.width))
+        })
+        .add_with_monitor("getHeight", MAIN, |monitor, ()| {
+            Ok(monitor.map_or_else(|| platform::backend().screen_size().1, |m| m.height))
+        })
+        // 所有显示器，主显示器在前，current 标记页面所在的显示器
+        .add_with_monitor("getMonitors", ALL, |current, ()| {
+            Ok(platform::monitors()
+                .into_iter()
+                .map(|monitor| MonitorInfo {
+                    current: current.is_some_and(|c| c.id == monitor.id),
+                    monitor,
+                })
+                .collect::<Vec<_>>())
+        })
+        .add("getOfflineStatus", MAIN, |()| Ok(offline_cache::status()))
+        // 小窗口模式下由页面顶部的拖动条调用，save 为 true 时记住位置
+        .add("moveWidget", MAIN, move |(left, top, save): (i32, i32, bool)| {
+            let ui = {
+                let state = app_state_widget.lock().unwrap();
+                match state.windows.first() {
+                    Some(window) if state.widget => Arc::clone(&window.ui),
+                    _ => return Err("当前不是小窗口模式".into()),
+                }
+            };
+            widget::move_to(&ui, left, top, save).map_err(Into::into)
+        })
+        .add("readFile", ALL, |(path,): (String,)| {
+            std::fs::read_to_string(path).map_err(|e| format!("读取文件失败: {}", e).into())
+        })
+        .add("writeFile", ALL, |(path, content): (String, String)| {
+            match std::fs::write(&path, content) {
+                Ok(_) => Ok(true),
+                Err(e) => {
+                    log::warn!("[ClassPaper] 写入文件 {} 失败: {}", path, e);
+                    Err(format!("写入文件失败: {}", e).into())
+                }
+            }
+        })
+        .add("readDir", ALL, |(dir,): (String,)| match std::fs::read_dir(dir) {
+            Ok(entries) => Ok(entries
+                .filter_map(|e| e.ok().map(|e| e.file_name().to_string_lossy().to_string()))
+                .collect::<Vec<_>>()),
+            Err(e) => Err(format!("读取目录失败: {}", e).into()),
+        })
+        .add("readConfig", SETTINGS, |()| read_config())
+        .add("saveConfig", SETTINGS, |(config_json,): (String,)| save_config(&config_json))
+        .add("scanWallpaperDir", SETTINGS, |()| Ok(scan_wallpaper_dir()))
+        .add("reloadMainWindow", SETTINGS, move |()| {
+            // 页面卡住时通知和刷新都要等到超时，不能持锁进行
+            let windows: Vec<(String, Arc<UI>)> = app_state
+                .lock()
+                .unwrap()
+                .windows
+                .iter()
+                .map(|w| (w.name.clone(), Arc::clone(&w.ui)))
+                .collect();
+            let mut result = Ok(true);
+            for (name, ui) in &windows {
+                // 先通知页面配置已更改，页面调用 preventDefault 表示已自行更新，不再整页刷新
+                match ui.emit("classpaper:configchange", &serde_json::json!({ "source": "settings" })) {
+                    Ok(false) => {
+                        log::info!("[ClassPaper] 主窗口 {} 已自行处理配置更改", name);
+                        continue;
+                    }
+                    Ok(true) => {}
+                    Err(e) => log::debug!("[ClassPaper] 通知主窗口 {} 配置更改失败: {}", name, e),
+                }
+                if let Err(e) = ui.eval_with_timeout("location.reload(true)", BROWSER_TIMEOUT) {
+                    if e.needs_restart() {
+                        log::warn!("[ClassPaper] 主窗口 {} 的浏览器已不可用，需要重启网页显示程序: {}", name, e);
+                    } else {
+                        log::warn!("[ClassPaper] 刷新主窗口 {} 失败: {}", name, e);
+                    }
+                    result = Err(format!("刷新主窗口失败: {}", e).into());
+                }
+            }
+            result
+        })
+        .add("openURLInBrowser", SETTINGS, |(url,): (String,)| open_url(&url))
+        // 返回本机检测到的浏览器及版本，供设置页下拉选择
+        .add("listBrowsers", SETTINGS, |()| {
+            let browsers = alcro::locate_browsers();
+            log::info!("[ClassPaper] 检测到 {} 个可用浏览器", browsers.iter().filter(|b| b.supported).count());
+            Ok(browsers)
+        })
+        // 使用编译时环境变量获取构建日期
+        .add("getBuildDate", SETTINGS, |()| Ok(option_env!("BUILD_DATE").unwrap_or("2026-01-28")));
+    if REGISTRY.set(registry).is_err() {
+        log::warn!("[ClassPaper] 绑定注册表已初始化");
+    }
+}
+
+#[derive(Serialize)]
+struct MonitorInfo {
+    #[serde(flatten)]
+    monitor: Monitor,
+    current: bool,
+}
+
+pub fn bind(ui: &UI, page: Page, monitor: Option<&Monitor>) {
+    match REGISTRY.get() {
+        Some(registry) => registry.bind(ui, page, monitor),
+        None => log::error!("[ClassPaper] 绑定注册表未初始化，页面无法调用 Rust 函数"),
+    }
+}
+
+// 读取 config.toml，转换为 { Default: { URL, BrowserPath } } 格式，字段名大写
+fn read_config() -> Result<JSObject, JSObject> {
+    let content = std::fs::read_to_string("config.toml").map_err(|e| {
+        println!("[settings][readConfig] 读取配置失败: {}", e);
+        JSObject::from(format!("读取配置失败: {}", e))
+    })?;
+    println!("[settings][readConfig] 读取到的 config.toml 内容:\n{}", content);
+    // 先用 toml 解析为 serde_json::Value
+    let json = toml::from_str::<JSObject>(&content).map_err(|e| {
+        println!("[settings][readConfig] TOML 解析失败: {}", e);
+        JSObject::from(format!("TOML 解析失败: {}", e))
+    })?;
+    // 兼容 [default] 或 [Default] 段
+    let mut result = serde_json::Map::new();
+    if let Some(default) = json.get("Default").or_else(|| json.get("default")) {
+        let mut default_map = serde_json::Map::new();
+        if let Some(url) = default.get("url").or_else(|| default.get("URL")) {
+            default_map.insert("URL".to_string(), url.clone());
+        }
+        if let Some(browser_path) = default.get("browser_path").or_else(|| default.get("BrowserPath")) {
+            default_map.insert("BrowserPath".to_string(), browser_path.clone());
+        }
+        result.insert("Default".to_string(), JSObject::Object(default_map));
+    }
+    let json_result = JSObject::Object(result);
+    println!("[settings][readConfig] 兼容后的 JSON: {}", json_result);
+    Ok(json_result)
+}
+
+fn save_config(config_json: &str) -> Result<bool, JSObject> {
+    // 先解析为 serde_json::Value
+    let value: JSObject = match serde_json::from_str(config_json) {
+        Ok(v) => v,
+        Err(e) => {
+            platform::backend().show_error(&format!("配置解析失败\n\n无法解析配置数据\n\n错误信息: {}\n\n请检查输入的配置格式是否正确。", e));
+            return Err(format!("解析配置JSON失败: {}", e).into());
+        }
+    };
+    // 只序列化 Default 字段为 [Default] 段，字段名大写；设置页不编辑的其他段（如 ResourceMap）原样保留
+    let mut toml_map = std::fs::read_to_string("config.toml")
+        .ok()
+        .and_then(|content| content.parse::<TomlValue>().ok())
+        .and_then(|value| match value {
+            TomlValue::Table(table) => Some(table),
+            _ => None,
+        })
+        .unwrap_or_default();
+    toml_map.remove("default");
+    if let Some(default) = value.get("Default") {
+        // 合并到原有的 [Default] 段，设置页不编辑的字段（如 Zoom）保持不变
+        let mut default_table = match toml_map.remove("Default") {
+            Some(TomlValue::Table(table)) => table,
+            _ => toml::map::Map::new(),
+        };
+        if let Ok(TomlValue::Table(updates)) = toml::Value::try_from(default.clone()) {
+            default_table.extend(updates);
+        }
+        toml_map.insert("Default".to_string(), TomlValue::Table(default_table));
+    }
+    let toml_str = match toml::to_string(&TomlValue::Table(toml_map)) {
+        Ok(s) => s,
+        Err(e) => {
+            platform::backend().show_error(&format!("配置序列化失败\n\n无法将配置转换为TOML格式\n\n错误信息: {}\n\n请检查配置数据格式。", e));
+            return Err(format!("序列化TOML失败: {}", e).into());
+        }
+    };
+    match std::fs::write("config.toml", toml_str) {
+        Ok(_) => Ok(true),
+        Err(e) => {
+            platform::backend().show_error(&format!("配置保存失败\n\n无法保存配置文件 config.toml\n\n错误信息: {}\n\n可能原因：\n• 文件权限不足\n• 文件被其他程序占用\n• 磁盘空间不足\n\n请检查文件权限或重启程序后再试。", e));
+            Err(format!("写入配置失败: {}", e).into())
+        }
+    }
+}
+
+// 列出 res/wallpaper 下的图片，返回相对 res 的路径
+fn scan_wallpaper_dir() -> Vec<String> {
+    let mut wallpapers = Vec::new();
+    match std::fs::read_dir("res/wallpaper") {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if !path.is_file() {
+                    continue;
+                }
+                let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+                if matches!(ext.as_str(), "jpg" | "jpeg" | "png" | "gif") {
+                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                        wallpapers.push(format!("wallpaper/{}", name));
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            platform::backend().show_error(&format!("壁纸目录扫描失败\n\n无法扫描壁纸目录: res/wallpaper\n\n错误信息: {}\n\n可能原因：\n• 目录不存在\n• 权限不足\n• 路径错误\n\n请检查壁纸目录是否存在并有读取权限。", e));
+            log::warn!("[ClassPaper] 扫描壁纸目录失败: {}", e);
+        }
+    }
+    wallpapers
+}
+
+fn open_url(url: &str) -> Result<bool, JSObject> {
+    #[cfg(target_os = "windows")]
+    let result = Command::new("cmd").args(["/c", "start", url]).spawn();
+    #[cfg(target_os = "macos")]
+    let result = Command::new("open").arg(url).spawn();
+    #[cfg(target_os = "linux")]
+    let result = Command::new("xdg-open").arg(url).spawn();
+    match result {
+        Ok(_) => Ok(true),
+        Err(e) => {
+            platform::backend().show_error(&format!("浏览器打开失败\n\n无法打开链接: {}\n\n错误信息: {}\n\n可能原因：\n• 未安装默认浏览器\n• 浏览器路径配置错误\n• 系统关联设置问题\n\n请检查浏览器设置或手动访问该链接。", url, e));
+            Err(format!("打开浏览器失败: {}", e).into())
+        }
+    }
+}
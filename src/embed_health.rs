@@ -0,0 +1,190 @@
+// 桌面嵌入的健康检查：平台报告桌面外壳重启或嵌入失效、或者嵌入失败时，在后台按指数退避重新嵌入，
+// 直到成功为止，不会因为多次失败而放弃；状态变化写入日志并通过回调显示在托盘菜单中
+
+use crate::platform::{self, EmbedEvent};
+use crate::{AppState, MainWindow};
+use once_cell::sync::OnceCell;
+use std::collections::BTreeMap;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// 重新嵌入的初始/最大等待时间
+const RETRY_INITIAL: Duration = Duration::from_secs(2);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+// 桌面外壳重启后等待它重新创建桌面窗口
+const SHELL_SETTLE: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmbedStatus {
+    Healthy,
+    // 有主窗口等待重新嵌入：窗口数和其中最多的已尝试次数
+    Retrying { windows: usize, attempts: u32 },
+}
+
+impl EmbedStatus {
+    // 托盘菜单中显示的文字
+    pub fn label(&self) -> String {
+        match self {
+            EmbedStatus::Healthy => "桌面嵌入: 正常".to_string(),
+            EmbedStatus::Retrying { windows, attempts } => {
+                format!("桌面嵌入: {} 个窗口重试中 (已尝试 {} 次)", windows, attempts)
+            }
+        }
+    }
+}
+
+enum Request {
+    Event(EmbedEvent),
+    Retry(String),
+}
+
+struct Pending {
+    attempts: u32,
+    next: Instant,
+}
+
+static SENDER: OnceCell<Mutex<Sender<Request>>> = OnceCell::new();
+
+// 启动健康检查线程并订阅平台的嵌入事件；on_status 在状态变化时于检查线程中调用
+pub fn init<F>(app_state: Arc<Mutex<AppState>>, on_status: F)
+where
+    F: Fn(&EmbedStatus) + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+    let events = Mutex::new(tx.clone());
+    platform::backend().watch_embed(Box::new(move |event| {
+        let _ = events.lock().unwrap().send(Request::Event(event));
+    }));
+    if SENDER.set(Mutex::new(tx)).is_err() {
+        log::warn!("[ClassPaper] 桌面嵌入健康检查已初始化");
+        return;
+    }
+    thread::spawn(move || run(app_state, rx, on_status));
+}
+
+// 设置主窗口的桌面嵌入；失败时提示用户，并交给健康检查在后台重试
+pub fn embed(window: &MainWindow) {
+    let backend = platform::backend();
+    if let Err(message) = backend.embed_as_wallpaper(&window.ui, &window.name, &window.monitor) {
+        backend.show_error(&message);
+        retry(&window.name);
+    }
+}
+
+// 在后台重新嵌入标题为 window_name 的主窗口，已在等待重试的不会重复加入
+pub fn retry(window_name: &str) {
+    match SENDER.get() {
+        Some(sender) => {
+            let _ = sender.lock().unwrap().send(Request::Retry(window_name.to_string()));
+        }
+        None => log::warn!("[ClassPaper] 桌面嵌入健康检查未初始化，主窗口 {} 不会自动重试", window_name),
+    }
+}
+
+fn run<F>(app_state: Arc<Mutex<AppState>>, rx: mpsc::Receiver<Request>, on_status: F)
+where
+    F: Fn(&EmbedStatus),
+{
+    let mut pending: BTreeMap<String, Pending> = BTreeMap::new();
+    let mut status = EmbedStatus::Healthy;
+    loop {
+        let request = match pending.values().map(|p| p.next).min() {
+            Some(next) => rx.recv_timeout(next.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match request {
+            Ok(Request::Event(event)) => {
+                let delay = match event {
+                    EmbedEvent::ShellRestarted => SHELL_SETTLE,
+                    EmbedEvent::EmbedLost => RETRY_INITIAL,
+                };
+                let names: Vec<String> = {
+                    let state = app_state.lock().unwrap();
+                    // 小窗口模式下主窗口没有嵌入桌面
+                    if state.widget {
+                        continue;
+                    }
+                    state.windows.iter().map(|w| w.name.clone()).collect()
+                };
+                log::warn!("[ClassPaper] 桌面嵌入失效 ({:?})，{} 秒后重新嵌入 {} 个主窗口", event, delay.as_secs(), names.len());
+                for name in names {
+                    let entry = pending.entry(name).or_insert(Pending {
+                        attempts: 0,
+                        next: Instant::now() + delay,
+                    });
+                    entry.next = entry.next.min(Instant::now() + delay);
+                }
+            }
+            Ok(Request::Retry(name)) => {
+                pending.entry(name).or_insert(Pending {
+                    attempts: 0,
+                    next: Instant::now() + RETRY_INITIAL,
+                });
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        let now = Instant::now();
+        let due: Vec<String> = pending
+            .iter()
+            .filter(|(_, p)| p.next <= now)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in due {
+            // 只在查找窗口时持锁，嵌入可能要等待窗口出现
+            let window = {
+                let state = app_state.lock().unwrap();
+                if state.shutting_down {
+                    return;
+                }
+                state
+                    .windows
+                    .iter()
+                    .find(|w| w.name == name)
+                    .map(|w| (Arc::clone(&w.ui), w.monitor.clone()))
+            };
+            // 窗口已被移除，或浏览器已退出、由守护线程重启并重新嵌入
+            let Some((ui, monitor)) = window.filter(|(ui, _)| !ui.done()) else {
+                pending.remove(&name);
+                continue;
+            };
+            let Some(retry) = pending.get_mut(&name) else {
+                continue;
+            };
+            retry.attempts += 1;
+            match platform::backend().embed_as_wallpaper(&ui, &name, &monitor) {
+                Ok(()) => {
+                    log::info!("[ClassPaper] 主窗口 {} 的桌面嵌入已恢复 (第 {} 次尝试)", name, retry.attempts);
+                    pending.remove(&name);
+                }
+                Err(e) => {
+                    let backoff = RETRY_INITIAL
+                        .saturating_mul(1 << (retry.attempts - 1).min(5))
+                        .min(RETRY_MAX);
+                    retry.next = Instant::now() + backoff;
+                    log::warn!(
+                        "[ClassPaper] 主窗口 {} 重新嵌入失败 (第 {} 次)，{} 秒后重试: {}",
+                        name,
+                        retry.attempts,
+                        backoff.as_secs(),
+                        e.replace("\n\n", " ").replace('\n', " ")
+                    );
+                }
+            }
+        }
+        let current = match pending.values().map(|p| p.attempts).max() {
+            None => EmbedStatus::Healthy,
+            Some(attempts) => EmbedStatus::Retrying {
+                windows: pending.len(),
+                attempts,
+            },
+        };
+        if current != status {
+            log::info!("[ClassPaper] {}", current.label());
+            on_status(&current);
+            status = current;
+        }
+    }
+}
@@ -0,0 +1,166 @@
+// 主窗口的交互方式：点击穿透到桌面、页面接收输入，或平时点击穿透、按热键切换为接收输入。
+// 启动时按 config.toml 的 [Interaction] 段设置，托盘菜单中可以随时切换（不写回配置文件）
+
+use crate::platform::{self, Hotkey};
+use crate::AppState;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InteractionMode {
+    // 点击落在桌面图标和桌面上，页面只用于显示
+    #[default]
+    ClickThrough,
+    // 页面接收鼠标和键盘，显示在桌面图标之上
+    Interactive,
+    // 平时点击穿透，按下热键后接收输入，再按一次恢复
+    InteractiveOnHotkey,
+}
+
+impl InteractionMode {
+    // 托盘菜单中依次切换的下一个方式
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub fn next(self) -> Self {
+        match self {
+            InteractionMode::ClickThrough => InteractionMode::Interactive,
+            InteractionMode::Interactive => InteractionMode::InteractiveOnHotkey,
+            InteractionMode::InteractiveOnHotkey => InteractionMode::ClickThrough,
+        }
+    }
+}
+
+struct Interaction {
+    app_state: Arc<Mutex<AppState>>,
+    hotkey: Option<Hotkey>,
+    on_change: Box<dyn Fn(&str) + Send + Sync>,
+    state: Mutex<State>,
+}
+
+struct State {
+    mode: InteractionMode,
+    // 热键模式下是否已按热键切换为接收输入
+    hotkey_active: bool,
+    hotkey_registered: bool,
+}
+
+static INTERACTION: OnceCell<Interaction> = OnceCell::new();
+
+// 设置启动时的交互方式；hotkey 无法解析时热键模式不可用。on_change 在方式或热键状态改变后
+// 以托盘菜单中显示的文字调用
+pub fn init<F>(app_state: Arc<Mutex<AppState>>, mode: InteractionMode, hotkey: &str, on_change: F)
+where
+    F: Fn(&str) + Send + Sync + 'static,
+{
+    let hotkey = match hotkey.parse::<Hotkey>() {
+        Ok(hotkey) => Some(hotkey),
+        Err(e) => {
+            log::warn!("[ClassPaper] {}，热键切换交互方式不可用", e);
+            None
+        }
+    };
+    let interaction = Interaction {
+        app_state,
+        hotkey,
+        on_change: Box::new(on_change),
+        state: Mutex::new(State {
+            mode: InteractionMode::ClickThrough,
+            hotkey_active: false,
+            hotkey_registered: false,
+        }),
+    };
+    if INTERACTION.set(interaction).is_err() {
+        log::warn!("[ClassPaper] 交互方式已初始化");
+        return;
+    }
+    set_mode(mode);
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn mode() -> InteractionMode {
+    INTERACTION
+        .get()
+        .map_or(InteractionMode::default(), |interaction| interaction.state.lock().unwrap().mode)
+}
+
+// 切换交互方式并应用到所有主窗口；热键模式从点击穿透开始
+pub fn set_mode(mode: InteractionMode) {
+    let Some(interaction) = INTERACTION.get() else {
+        return;
+    };
+    let mut state = interaction.state.lock().unwrap();
+    state.mode = mode;
+    state.hotkey_active = false;
+    if mode == InteractionMode::InteractiveOnHotkey && !state.hotkey_registered {
+        state.hotkey_registered = register_hotkey(interaction);
+    }
+    drop(state);
+    log::info!("[ClassPaper] 交互方式: {:?}", mode);
+    apply(interaction);
+}
+
+// 第一次切换到热键模式时注册热键，之后一直保留
+fn register_hotkey(interaction: &Interaction) -> bool {
+    let Some(hotkey) = &interaction.hotkey else {
+        return false;
+    };
+    let listener = Box::new(|| {
+        let Some(interaction) = INTERACTION.get() else {
+            return;
+        };
+        let mut state = interaction.state.lock().unwrap();
+        if state.mode != InteractionMode::InteractiveOnHotkey {
+            return;
+        }
+        state.hotkey_active = !state.hotkey_active;
+        drop(state);
+        apply(interaction);
+    });
+    match platform::backend().watch_hotkey(hotkey, listener) {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!("[ClassPaper] {}，热键模式下将保持点击穿透", e);
+            false
+        }
+    }
+}
+
+// 按当前方式设置所有主窗口，并通知托盘更新文字
+fn apply(interaction: &Interaction) {
+    let state = interaction.state.lock().unwrap();
+    let interactive = match state.mode {
+        InteractionMode::ClickThrough => false,
+        InteractionMode::Interactive => true,
+        InteractionMode::InteractiveOnHotkey => state.hotkey_active,
+    };
+    let label = match state.mode {
+        InteractionMode::ClickThrough => "交互方式: 点击穿透".to_string(),
+        InteractionMode::Interactive => "交互方式: 可交互".to_string(),
+        InteractionMode::InteractiveOnHotkey => format!(
+            "交互方式: 按 {} 切换 ({})",
+            interaction.hotkey.as_ref().map_or_else(|| "热键".to_string(), Hotkey::to_string),
+            if interactive { "当前可交互" } else { "当前点击穿透" }
+        ),
+    };
+    drop(state);
+    platform::set_interactive(interactive);
+    let state = interaction.app_state.lock().unwrap();
+    // 小窗口是普通窗口，总是接收输入
+    let windows: Vec<_> = if state.widget {
+        Vec::new()
+    } else {
+        state
+            .windows
+            .iter()
+            .map(|w| (Arc::clone(&w.ui), w.name.clone(), w.monitor.clone()))
+            .collect()
+    };
+    drop(state);
+    for (ui, name, monitor) in windows {
+        if let Err(e) = platform::backend().set_interactive(&ui, &name, &monitor, interactive) {
+            log::warn!("[ClassPaper] 设置主窗口 {} 的交互方式失败: {}", name, e);
+        }
+    }
+    (interaction.on_change)(&label);
+}
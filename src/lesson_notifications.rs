@@ -0,0 +1,404 @@
+// 上下课系统通知：按 res/config/config.js 中的课表，在上课前和下课时通过平台的系统通知提醒，
+// 与页面播放的提示音互不影响。课表每次检查时重新读取，设置页保存后不需要重启；
+// [Notifications] 的 MutedPeriods 中的节次和 QuietHours 时段内不提醒
+
+use crate::platform;
+use crate::NotificationConfig;
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::thread;
+use std::time::Duration;
+
+// 课表由设置页以 `const CONFIG = {...};` 的形式写入，对象部分是 JSON
+const SCHEDULE_FILE: &str = "res/config/config.js";
+const SCHEDULE_PREFIX: &str = "const CONFIG";
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+// 错过提醒时间超过该时长（程序刚启动、电脑从睡眠中恢复）时不再补发
+const LATE_LIMIT_SECONDS: i64 = 120;
+// 课表中表示没有课的名称，与页面一致不提醒
+const NO_LESSON: &str = "无";
+
+#[derive(Deserialize)]
+struct PageConfig {
+    lessons: Lessons,
+}
+
+#[derive(Deserialize)]
+struct Lessons {
+    // 周一到周日
+    #[serde(default)]
+    schedule: Vec<Day>,
+    times: Times,
+}
+
+#[derive(Deserialize)]
+struct Day {
+    #[serde(default)]
+    classes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Times {
+    semester: Option<Semester>,
+    // 与每天的 classes 按位置对应
+    #[serde(default)]
+    schedule: Vec<PeriodTime>,
+}
+
+#[derive(Deserialize)]
+struct Semester {
+    begin: String,
+    end: String,
+}
+
+#[derive(Deserialize)]
+struct PeriodTime {
+    period: Option<u32>,
+    begin: String,
+    end: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Edge {
+    Start,
+    End,
+}
+
+struct Notice {
+    at: NaiveDateTime,
+    period: u32,
+    edge: Edge,
+    summary: String,
+    body: String,
+}
+
+// 按 settings 启动检查线程，未启用时不做任何事
+pub fn start(settings: &NotificationConfig) {
+    if !settings.enabled {
+        return;
+    }
+    if !settings.lesson_start && !settings.lesson_end {
+        log::info!("[ClassPaper] 上课和下课通知均已关闭");
+        return;
+    }
+    let quiet_hours: Vec<(NaiveTime, NaiveTime)> = settings
+        .quiet_hours
+        .iter()
+        .filter_map(|range| match parse_range(range) {
+            Some(range) => Some(range),
+            None => {
+                log::warn!("[ClassPaper] 无法识别免打扰时段 {}，应写作 \"22:00-07:00\"", range);
+                None
+            }
+        })
+        .collect();
+    let settings = settings.clone();
+    log::info!("[ClassPaper] 上下课通知已启用");
+    thread::spawn(move || {
+        let mut sent: HashSet<(NaiveDate, u32, Edge)> = HashSet::new();
+        let mut last_error: Option<String> = None;
+        loop {
+            let now = Local::now().naive_local();
+            match load_schedule() {
+                Ok(page) => {
+                    last_error = None;
+                    for notice in notices(&page, &settings, &quiet_hours, now.date()) {
+                        let late = (now - notice.at).num_seconds();
+                        if !(0..=LATE_LIMIT_SECONDS).contains(&late) {
+                            continue;
+                        }
+                        if !sent.insert((now.date(), notice.period, notice.edge)) {
+                            continue;
+                        }
+                        match platform::backend().notify(&notice.summary, &notice.body) {
+                            Ok(()) => log::info!("[ClassPaper] 课程通知: {} ({})", notice.summary, notice.body),
+                            Err(e) => log::warn!("[ClassPaper] 显示课程通知失败: {}", e),
+                        }
+                    }
+                }
+                // 同样的错误只记录一次，设置页修改课表后恢复
+                Err(e) => {
+                    if last_error.as_ref() != Some(&e) {
+                        log::warn!("[ClassPaper] {}，暂停上下课通知", e);
+                        last_error = Some(e);
+                    }
+                }
+            }
+            sent.retain(|(date, _, _)| *date == now.date());
+            thread::sleep(CHECK_INTERVAL);
+        }
+    });
+}
+
+fn load_schedule() -> Result<PageConfig, String> {
+    let content =
+        std::fs::read_to_string(SCHEDULE_FILE).map_err(|e| format!("读取课表 {} 失败: {}", SCHEDULE_FILE, e))?;
+    parse_schedule(&content)
+}
+
+fn parse_schedule(content: &str) -> Result<PageConfig, String> {
+    let start = content
+        .find(SCHEDULE_PREFIX)
+        .and_then(|prefix| content[prefix..].find('{').map(|brace| prefix + brace))
+        .ok_or_else(|| format!("课表 {} 中没有 {}", SCHEDULE_FILE, SCHEDULE_PREFIX))?;
+    // 只解析对象本身，后面的兼容变量不是 JSON
+    serde_json::Deserializer::from_str(&content[start..])
+        .into_iter::<PageConfig>()
+        .next()
+        .unwrap_or_else(|| Err(serde::de::Error::custom("内容为空")))
+        .map_err(|e| format!("解析课表 {} 失败: {}", SCHEDULE_FILE, e))
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time.trim(), "%H:%M").ok()
+}
+
+// "HH:MM-HH:MM"，结束早于开始时跨过午夜
+fn parse_range(range: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (begin, end) = range.split_once('-')?;
+    Some((parse_time(begin)?, parse_time(end)?))
+}
+
+fn in_quiet_hours(quiet_hours: &[(NaiveTime, NaiveTime)], time: NaiveTime) -> bool {
+    quiet_hours.iter().any(|&(begin, end)| {
+        if begin <= end {
+            begin <= time && time < end
+        } else {
+            time >= begin || time < end
+        }
+    })
+}
+
+// date 这一天要发出的通知；学期外（与页面一致）没有通知
+fn notices(
+    page: &PageConfig,
+    settings: &NotificationConfig,
+    quiet_hours: &[(NaiveTime, NaiveTime)],
+    date: NaiveDate,
+) -> Vec<Notice> {
+    let times = &page.lessons.times;
+    if let Some(semester) = &times.semester {
+        let begin = NaiveDate::parse_from_str(&semester.begin, "%Y-%m-%d").ok();
+        let end = NaiveDate::parse_from_str(&semester.end, "%Y-%m-%d").ok();
+        if begin.is_some_and(|begin| date < begin) || end.is_some_and(|end| date > end) {
+            return Vec::new();
+        }
+    }
+    let Some(day) = page.lessons.schedule.get(date.weekday().num_days_from_monday() as usize) else {
+        return Vec::new();
+    };
+    // 每节课的 (节次, 课程名, 上课时间, 下课时间)，跳过没有课和时间无法识别的
+    let lessons: Vec<(u32, &str, NaiveTime, NaiveTime)> = times
+        .schedule
+        .iter()
+        .enumerate()
+        .filter_map(|(i, time)| {
+            let name = day.classes.get(i).map(|name| name.trim())?;
+            if name.is_empty() || name == NO_LESSON {
+                return None;
+            }
+            let period = time.period.unwrap_or(i as u32 + 1);
+            Some((period, name, parse_time(&time.begin)?, parse_time(&time.end)?))
+        })
+        .collect();
+    let mut notices = Vec::new();
+    for (i, &(period, name, begin, end)) in lessons.iter().enumerate() {
+        if settings.muted_periods.contains(&period) {
+            continue;
+        }
+        if settings.lesson_start {
+            let minutes = settings.minutes_before_start;
+            notices.push(Notice {
+                at: date.and_time(begin) - chrono::Duration::minutes(minutes.into()),
+                period,
+                edge: Edge::Start,
+                summary: if minutes == 0 {
+                    format!("{} 上课了", name)
+                } else {
+                    format!("{} {} 分钟后上课", name, minutes)
+                },
+                body: format!("第 {} 节 {}-{}", period, begin.format("%H:%M"), end.format("%H:%M")),
+            });
+        }
+        if settings.lesson_end {
+            let minutes = settings.minutes_before_end;
+            notices.push(Notice {
+                at: date.and_time(end) - chrono::Duration::minutes(minutes.into()),
+                period,
+                edge: Edge::End,
+                summary: if minutes == 0 {
+                    format!("{} 下课了", name)
+                } else {
+                    format!("{} {} 分钟后下课", name, minutes)
+                },
+                body: match lessons.get(i + 1) {
+                    Some(&(_, next, next_begin, _)) => format!("下一节: {} {}", next, next_begin.format("%H:%M")),
+                    None => "今天的课程已结束".to_string(),
+                },
+            });
+        }
+    }
+    notices.retain(|notice| !in_quiet_hours(quiet_hours, notice.at.time()));
+    notices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 周一有三节课（第 2 节为“无”，第 4 节没有写节次），周二一节，周三之后没有课表
+    const PAGE: &str = r#"// 由设置页生成
+const CONFIG = {
+  "lessons": {
+    "schedule": [
+      { "day": "周一", "classes": ["语文", "无", "数学", " 英语 "] },
+      { "day": "周二", "classes": ["物理"] }
+    ],
+    "times": {
+      "semester": { "begin": "2025-09-01", "end": "2026-01-20" },
+      "schedule": [
+        { "period": 1, "begin": "08:00", "end": "08:40" },
+        { "period": 2, "begin": "08:50", "end": "09:30" },
+        { "period": 3, "begin": "09:40", "end": "10:20" },
+        { "begin": "22:30", "end": "23:10" }
+      ]
+    }
+  },
+  "sth": "};"
+};
+
+// 为了保持向后兼容，导出原有的变量名
+const lessons = CONFIG.lessons.schedule.map(day => day.classes.join(",")).join("\n");
+"#;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn time(s: &str) -> NaiveTime {
+        parse_time(s).unwrap()
+    }
+
+    // (时间, 节次, 上课/下课, 标题, 正文)
+    fn describe(notices: &[Notice]) -> Vec<(String, u32, Edge, &str, &str)> {
+        notices
+            .iter()
+            .map(|n| (n.at.format("%H:%M").to_string(), n.period, n.edge, n.summary.as_str(), n.body.as_str()))
+            .collect()
+    }
+
+    fn monday_notices(settings: &NotificationConfig, quiet_hours: &[(NaiveTime, NaiveTime)]) -> Vec<Notice> {
+        let page = parse_schedule(PAGE).unwrap();
+        notices(&page, settings, quiet_hours, date("2025-09-01"))
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let page = parse_schedule(PAGE).unwrap();
+        assert_eq!(page.lessons.schedule.len(), 2);
+        assert_eq!(page.lessons.schedule[0].classes[2], "数学");
+        assert_eq!(page.lessons.times.schedule.len(), 4);
+        assert_eq!(page.lessons.times.schedule[3].period, None);
+
+        assert!(parse_schedule("const lessons = \"\";").is_err_and(|e| e.contains(SCHEDULE_PREFIX)));
+        assert!(parse_schedule("const CONFIG = ").is_err());
+        assert!(parse_schedule("const CONFIG = { \"lessons\": {} };").is_err());
+    }
+
+    #[test]
+    fn test_notices() {
+        let notices = monday_notices(&NotificationConfig::default(), &[]);
+        assert_eq!(
+            describe(&notices),
+            vec![
+                ("07:58".to_string(), 1, Edge::Start, "语文 2 分钟后上课", "第 1 节 08:00-08:40"),
+                ("08:40".to_string(), 1, Edge::End, "语文 下课了", "下一节: 数学 09:40"),
+                ("09:38".to_string(), 3, Edge::Start, "数学 2 分钟后上课", "第 3 节 09:40-10:20"),
+                ("10:20".to_string(), 3, Edge::End, "数学 下课了", "下一节: 英语 22:30"),
+                ("22:28".to_string(), 4, Edge::Start, "英语 2 分钟后上课", "第 4 节 22:30-23:10"),
+                ("23:10".to_string(), 4, Edge::End, "英语 下课了", "今天的课程已结束"),
+            ]
+        );
+        assert_eq!(notices[0].at.date(), date("2025-09-01"));
+    }
+
+    #[test]
+    fn test_notices_settings() {
+        // 静音的节次不提醒，但仍作为上一节的“下一节”显示
+        let settings = NotificationConfig {
+            lesson_start: false,
+            minutes_before_end: 5,
+            muted_periods: vec![3],
+            ..NotificationConfig::default()
+        };
+        assert_eq!(
+            describe(&monday_notices(&settings, &[])),
+            vec![
+                ("08:35".to_string(), 1, Edge::End, "语文 5 分钟后下课", "下一节: 数学 09:40"),
+                ("23:05".to_string(), 4, Edge::End, "英语 5 分钟后下课", "今天的课程已结束"),
+            ]
+        );
+
+        let settings = NotificationConfig {
+            minutes_before_start: 0,
+            lesson_end: false,
+            ..NotificationConfig::default()
+        };
+        let notices = monday_notices(&settings, &[]);
+        assert_eq!(notices.len(), 3);
+        assert_eq!(describe(&notices)[0], ("08:00".to_string(), 1, Edge::Start, "语文 上课了", "第 1 节 08:00-08:40"));
+    }
+
+    #[test]
+    fn test_notices_quiet_hours() {
+        // 跨过午夜的免打扰时段去掉晚上的两条
+        let quiet_hours = [(time("22:00"), time("07:00"))];
+        let notices = monday_notices(&NotificationConfig::default(), &quiet_hours);
+        assert_eq!(notices.len(), 4);
+        assert!(notices.iter().all(|n| n.period != 4));
+    }
+
+    #[test]
+    fn test_notices_semester() {
+        let page = parse_schedule(PAGE).unwrap();
+        let settings = NotificationConfig::default();
+        // 学期开始前和结束后没有通知
+        assert!(notices(&page, &settings, &[], date("2025-08-25")).is_empty());
+        assert!(notices(&page, &settings, &[], date("2026-01-26")).is_empty());
+        // 学期最后一天（周二）
+        assert_eq!(notices(&page, &settings, &[], date("2026-01-20")).len(), 2);
+        // 课表中没有的周三
+        assert!(notices(&page, &settings, &[], date("2025-09-03")).is_empty());
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("22:00-07:00"), Some((time("22:00"), time("07:00"))));
+        assert_eq!(parse_range(" 12:00 - 13:30 "), Some((time("12:00"), time("13:30"))));
+        assert_eq!(parse_range("22:00"), None);
+        assert_eq!(parse_range("22:00-25:00"), None);
+        assert_eq!(parse_range("晚上-早上"), None);
+        assert_eq!(parse_range(""), None);
+    }
+
+    #[test]
+    fn test_in_quiet_hours() {
+        let noon = [(time("12:00"), time("13:30"))];
+        assert!(in_quiet_hours(&noon, time("12:00")));
+        assert!(in_quiet_hours(&noon, time("13:29")));
+        assert!(!in_quiet_hours(&noon, time("13:30")));
+        assert!(!in_quiet_hours(&noon, time("11:59")));
+
+        let night = [(time("22:00"), time("07:00"))];
+        assert!(in_quiet_hours(&night, time("22:00")));
+        assert!(in_quiet_hours(&night, time("23:59")));
+        assert!(in_quiet_hours(&night, time("00:00")));
+        assert!(in_quiet_hours(&night, time("06:59")));
+        assert!(!in_quiet_hours(&night, time("07:00")));
+        assert!(!in_quiet_hours(&night, time("21:59")));
+
+        assert!(!in_quiet_hours(&[], time("12:00")));
+        assert!(in_quiet_hours(&[noon[0], night[0]], time("12:30")));
+    }
+}
@@ -1,28 +1,202 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use std::{
+    collections::BTreeMap,
     path::Path,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
+mod bindings;
+mod embed_health;
+mod interaction;
+mod lesson_notifications;
+mod offline_cache;
+mod platform;
+#[cfg(test)]
+mod test_util;
+mod widget;
+#[cfg(windows)]
 mod winapi;
+mod window_state;
 
-use alcro::{Content, UIBuilder, UI};
-use ctrlc;
+use alcro::{
+    Bounds, Content, DeviceMetrics, InterceptAction, InterceptStage, JSError, LocateChromeError, UIBuilder,
+    UILaunchError, WindowState, UI,
+};
+use bindings::Page;
+use embed_health::EmbedStatus;
+use interaction::InteractionMode;
+use offline_cache::OfflineCache;
 use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use platform::Monitor;
+use window_state::WindowKind;
 use rand::{distributions::Alphanumeric, Rng};
 use serde::{Deserialize, Serialize};
-use serde_json;
 use simplelog::{ConfigBuilder, WriteLogger, CombinedLogger, TermLogger, TerminalMode, ColorChoice, LevelFilter, ThreadLogMode, LevelPadding};
+#[cfg(windows)]
 use std::process::Command;
 use toml::Value as TomlValue;
+#[cfg(windows)]
 use tray_item::TrayItem;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 struct AppConfig {
     #[serde(default, rename = "Default")]
     default: DefaultConfig,
+    // URL 通配符（* 匹配任意字符，? 匹配单个字符）到本地文件的映射，命中的请求直接用本地文件应答
+    #[serde(default, rename = "ResourceMap")]
+    resource_map: BTreeMap<String, String>,
+    #[serde(default, rename = "OfflineCache")]
+    offline_cache: OfflineCacheConfig,
+    // 显示器 id 到页面 URL 的映射，空字符串表示该显示器不显示页面；
+    // 未列出的主显示器显示 [Default] 的 URL，未列出的其他显示器不显示
+    #[serde(default, rename = "Monitors")]
+    monitors: BTreeMap<String, String>,
+    #[serde(default, rename = "Interaction")]
+    interaction: InteractionConfig,
+    #[serde(default, rename = "Widget")]
+    widget: WidgetConfig,
+    #[serde(default, rename = "Notifications")]
+    notifications: NotificationConfig,
+}
+
+// 小窗口模式：Enabled 时主窗口不作为壁纸，而是主显示器上一个无边框的小窗口，显示 [Default] 的页面
+#[derive(Debug, Serialize, Deserialize)]
+struct WidgetConfig {
+    #[serde(default, rename = "Enabled")]
+    enabled: bool,
+    #[serde(default = "default_widget_width", rename = "Width")]
+    width: i32,
+    #[serde(default = "default_widget_height", rename = "Height")]
+    height: i32,
+    // 不透明度，1.0 为完全不透明
+    #[serde(default = "default_widget_opacity", rename = "Opacity")]
+    opacity: f64,
+    #[serde(default = "default_true", rename = "AlwaysOnTop")]
+    always_on_top: bool,
+}
+
+fn default_widget_width() -> i32 {
+    360
+}
+
+fn default_widget_height() -> i32 {
+    540
+}
+
+fn default_widget_opacity() -> f64 {
+    1.0
+}
+
+impl Default for WidgetConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            width: default_widget_width(),
+            height: default_widget_height(),
+            opacity: default_widget_opacity(),
+            always_on_top: true,
+        }
+    }
+}
+
+// 上下课系统通知：按 res/config/config.js 的课表，在上课前 MinutesBeforeStart 分钟、下课前 MinutesBeforeEnd 分钟
+// 提醒；MutedPeriods 中的节次不提醒，QuietHours 为 "HH:MM-HH:MM" 形式的免打扰时段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NotificationConfig {
+    #[serde(default, rename = "Enabled")]
+    enabled: bool,
+    #[serde(default = "default_true", rename = "LessonStart")]
+    lesson_start: bool,
+    #[serde(default = "default_minutes_before_start", rename = "MinutesBeforeStart")]
+    minutes_before_start: u32,
+    #[serde(default = "default_true", rename = "LessonEnd")]
+    lesson_end: bool,
+    #[serde(default, rename = "MinutesBeforeEnd")]
+    minutes_before_end: u32,
+    #[serde(default, rename = "MutedPeriods")]
+    muted_periods: Vec<u32>,
+    #[serde(default, rename = "QuietHours")]
+    quiet_hours: Vec<String>,
+}
+
+fn default_minutes_before_start() -> u32 {
+    2
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lesson_start: true,
+            minutes_before_start: default_minutes_before_start(),
+            lesson_end: true,
+            minutes_before_end: 0,
+            muted_periods: Vec::new(),
+            quiet_hours: Vec::new(),
+        }
+    }
+}
+
+// 主窗口是否接收鼠标键盘输入；Hotkey 在 Mode 为 interactive-on-hotkey 时切换
+#[derive(Debug, Serialize, Deserialize)]
+struct InteractionConfig {
+    #[serde(default, rename = "Mode")]
+    mode: InteractionMode,
+    #[serde(default = "default_hotkey", rename = "Hotkey")]
+    hotkey: String,
+}
+
+fn default_hotkey() -> String {
+    "Ctrl+Alt+W".to_string()
+}
+
+impl Default for InteractionConfig {
+    fn default() -> Self {
+        Self {
+            mode: InteractionMode::default(),
+            hotkey: default_hotkey(),
+        }
+    }
+}
+
+// URL 为 http(s) 地址时，把加载成功的远程内容缓存到本地，断网时用缓存显示
+#[derive(Debug, Serialize, Deserialize)]
+struct OfflineCacheConfig {
+    #[serde(default = "default_true", rename = "Enabled")]
+    enabled: bool,
+    #[serde(default = "default_cache_dir", rename = "Directory")]
+    directory: String,
+    // 缓存目录的大小上限，单位 MB，超过时删除最早保存的内容；0 表示不限制
+    #[serde(default = "default_cache_max_size", rename = "MaxSizeMB")]
+    max_size_mb: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_cache_dir() -> String {
+    "./cache/offline".to_string()
+}
+
+fn default_cache_max_size() -> u64 {
+    200
+}
+
+impl Default for OfflineCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            directory: default_cache_dir(),
+            max_size_mb: default_cache_max_size(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,6 +205,16 @@ struct DefaultConfig {
     url: String,
     #[serde(default, rename = "BrowserPath")]
     browser_path: String,
+    // 页面缩放比例，投影仪分辨率特殊导致布局错乱时调整，1.0 为不缩放
+    #[serde(default = "default_zoom", rename = "Zoom")]
+    zoom: f64,
+    // 模拟的设备像素比（window.devicePixelRatio），0 表示使用系统设置
+    #[serde(default, rename = "DeviceScaleFactor")]
+    device_scale_factor: f64,
+}
+
+fn default_zoom() -> f64 {
+    1.0
 }
 
 fn default_url() -> String {
@@ -42,22 +226,102 @@ impl Default for DefaultConfig {
         Self {
             url: default_url(),
             browser_path: String::new(),
+            zoom: default_zoom(),
+            device_scale_factor: 0.0,
         }
     }
 }
 
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            default: DefaultConfig::default(),
-        }
-    }
+// 等待浏览器响应 eval 等调用的最长时间，避免渲染进程卡死时托盘处理函数持锁阻塞
+const BROWSER_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 支持的最低浏览器主版本号，更旧的浏览器（常见于 Win7 机房）无法可靠使用 --remote-debugging-pipe
+const MIN_BROWSER_VERSION: u32 = 80;
+
+// 以 --headless 启动时浏览器不显示窗口，也不创建托盘、不设置桌面穿透、不弹出任何对话框，
+// 供无人值守部署和自动化测试使用
+static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+fn headless() -> bool {
+    HEADLESS.load(Ordering::Relaxed)
+}
+
+// 浏览器守护线程：检查主窗口的间隔、重启退避的初始/最大等待时间
+const SUPERVISOR_INTERVAL: Duration = Duration::from_secs(1);
+const RELAUNCH_BACKOFF_INITIAL: Duration = Duration::from_secs(1);
+const RELAUNCH_BACKOFF_MAX: Duration = Duration::from_secs(60);
+// 在 CRASH_WINDOW 时间内崩溃达到 MAX_CRASHES 次后放弃自动重启
+const MAX_CRASHES: usize = 5;
+const CRASH_WINDOW: Duration = Duration::from_secs(600);
+// 心跳：每隔 HEARTBEAT_INTERVAL 在页面中执行一次 eval，超过 HEARTBEAT_TIMEOUT 未返回记为一次未响应
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(3);
+// 连续未响应达到该次数后先重载页面，重载后仍无响应则重启浏览器
+const HEARTBEAT_MAX_MISSES: u32 = 3;
+// 收到显示器变化通知后等待配置稳定的时间
+const DISPLAY_SETTLE: Duration = Duration::from_millis(1500);
+
+// 设置窗口；在主窗口浏览器中打开时记下该浏览器，浏览器被守护线程重启后重新打开
+struct SettingsWindow {
+    ui: Arc<UI>,
+    host: Option<Arc<UI>>,
+}
+
+// 显示在某个显示器上的主窗口
+#[derive(Clone)]
+struct MainWindow {
+    monitor: Monitor,
+    url: String,
+    name: String,
+    ui: Arc<UI>,
 }
 
 struct AppState {
-    window: Option<Arc<UI>>,
-    window_name: String,
-    settings_windows: Vec<Arc<UI>>,
+    // 每个显示页面的显示器一个，主显示器的排在最前
+    windows: Vec<MainWindow>,
+    // 主窗口标题的前缀和已创建的主窗口数，新窗口按序号命名，显示器变化后新建的窗口也不会重名
+    window_prefix: String,
+    window_serial: usize,
+    settings_windows: Vec<SettingsWindow>,
+    // 当前是否为小窗口模式，启动时取自 [Widget] 的 Enabled，可在托盘中切换
+    widget: bool,
+    // 程序正在退出或重启时置位，避免守护线程把主动关闭的窗口当成崩溃
+    shutting_down: bool,
+}
+
+impl AppState {
+    // 主显示器上的主窗口，设置窗口优先在它的浏览器中打开
+    fn primary_window(&self) -> Option<Arc<UI>> {
+        self.windows.first().map(|w| Arc::clone(&w.ui))
+    }
+
+    // 新主窗口的标题，平台实现按标题包含关系查找窗口（浏览器会追加自己的名称）；
+    // 用方括号括起，[xxx-1] 不会出现在 [xxx-10] 中，序号变长后也不会找错窗口
+    fn next_window_name(&mut self) -> String {
+        self.window_serial += 1;
+        format!("[{}-{}]", self.window_prefix, self.window_serial)
+    }
+
+    // 标记程序正在退出并取出所有窗口，由调用方释放锁后逐个关闭；守护线程看到 shutting_down 后不再重启浏览器
+    fn shut_down(&mut self) -> (Vec<MainWindow>, Vec<SettingsWindow>) {
+        self.shutting_down = true;
+        (std::mem::take(&mut self.windows), std::mem::take(&mut self.settings_windows))
+    }
+
+    // 去掉用户已关闭的设置窗口；所在浏览器已退出的保留，等守护线程重启浏览器后重新打开
+    fn prune_settings_windows(&mut self) {
+        self.settings_windows.retain(|w| {
+            !w.ui.done() || matches!(w.host, Some(ref host) if host.done() || host.crashed())
+        });
+    }
+
+    // 取出在 host 浏览器中打开的设置窗口，返回是否有这样的窗口
+    fn take_settings_windows_in(&mut self, host: &Arc<UI>) -> bool {
+        let before = self.settings_windows.len();
+        self.settings_windows
+            .retain(|w| !matches!(w.host, Some(ref h) if Arc::ptr_eq(h, host)));
+        self.settings_windows.len() != before
+    }
 }
 
 fn generate_random_string(length: usize) -> String {
@@ -72,7 +336,7 @@ fn encode_chinese_characters(path: &str) -> String {
     // 只对中文字符做转码
     path.chars()
         .map(|c| {
-            if c >= '\u{4e00}' && c <= '\u{9fa5}' {
+            if ('\u{4e00}'..='\u{9fa5}').contains(&c) {
                 utf8_percent_encode(&c.to_string(), NON_ALPHANUMERIC).to_string()
             } else {
                 c.to_string()
@@ -97,13 +361,10 @@ fn normalize_url(url: &str) -> String {
             abs_path_str = abs_path_str[4..].to_string();
         }
         // 去除路径中的"./"或".\\"前缀
-        let abs_path_str = if abs_path_str.starts_with("./") {
-            &abs_path_str[2..]
-        } else if abs_path_str.starts_with(".\\") {
-            &abs_path_str[2..]
-        } else {
-            &abs_path_str
-        };
+        let abs_path_str = abs_path_str
+            .strip_prefix("./")
+            .or_else(|| abs_path_str.strip_prefix(".\\"))
+            .unwrap_or(&abs_path_str);
         // Windows下去除多余的斜杠
         #[cfg(windows)]
         let abs_path_str = {
@@ -126,6 +387,130 @@ fn normalize_url(url: &str) -> String {
     }
 }
 
+// 按 [Monitors] 确定显示器要显示的页面，返回 None 表示该显示器不显示
+fn monitor_url(config: &AppConfig, monitor: &Monitor) -> Option<String> {
+    let url = match config.monitors.get(&monitor.id) {
+        Some(url) => url,
+        None if monitor.primary => &config.default.url,
+        None => return None,
+    };
+    if url.trim().is_empty() {
+        return None;
+    }
+    Some(normalize_url(url))
+}
+
+// 主窗口在 monitor 上显示的页面：壁纸模式按 [Monitors]，小窗口模式只在主显示器上显示 [Default] 的页面
+fn main_window_url(config: &AppConfig, monitor: &Monitor, widget: bool) -> Option<String> {
+    if widget {
+        return monitor.primary.then(|| normalize_url(&config.default.url));
+    }
+    monitor_url(config, monitor)
+}
+
+// 为 monitors 中需要显示页面的显示器创建主窗口并加入 AppState，返回新窗口的标题；
+// 某个显示器创建失败时跳过它。不持锁启动浏览器，期间不阻塞托盘和守护线程
+fn create_main_windows(app_state: &Arc<Mutex<AppState>>, config: &AppConfig, monitors: Vec<Monitor>) -> Vec<String> {
+    let mut names = Vec::new();
+    let widget = app_state.lock().unwrap().widget;
+    for monitor in monitors {
+        let Some(url) = main_window_url(config, &monitor, widget) else {
+            log::info!("[ClassPaper] 显示器 {} 不显示页面", monitor.id);
+            continue;
+        };
+        let name = app_state.lock().unwrap().next_window_name();
+        log::info!(
+            "[ClassPaper][加载配置URL] 显示器 {} ({}x{}+{}+{}): {}",
+            monitor.id, monitor.width, monitor.height, monitor.x, monitor.y, url
+        );
+        match create_window(&url, &name, config, &monitor, widget) {
+            Ok(ui) => {
+                log::info!("[ClassPaper] 主窗口已创建: {}", name);
+                let mut state = app_state.lock().unwrap();
+                state.windows.push(MainWindow {
+                    monitor,
+                    url,
+                    name: name.clone(),
+                    ui: Arc::new(ui),
+                });
+                state.windows.sort_by_key(|w| !w.monitor.primary);
+                names.push(name);
+            }
+            Err(e) => log::error!("[ClassPaper] 显示器 {} 的主窗口创建失败: {}", monitor.id, e),
+        }
+    }
+    names
+}
+
+// 按窗口模式显示新启动的主窗口：壁纸模式嵌入桌面，失败时提示并交给健康检查重试；小窗口模式设置为小窗口
+fn show_main_window(window: &MainWindow, widget: bool, config: &AppConfig) {
+    if widget {
+        widget::show(&window.ui, &window.name, &config.widget, &window.monitor);
+    } else {
+        embed_health::embed(window);
+    }
+}
+
+// 显示新主窗口并为每个窗口启动守护线程
+fn show_main_windows(app_state: &Arc<Mutex<AppState>>, names: &[String], config: &AppConfig) {
+    // 嵌入失败时会弹出提示，不能持锁进行
+    let (windows, widget) = {
+        let state = app_state.lock().unwrap();
+        let windows: Vec<MainWindow> = state.windows.iter().filter(|w| names.contains(&w.name)).cloned().collect();
+        (windows, state.widget)
+    };
+    for window in &windows {
+        show_main_window(window, widget, config);
+    }
+    for name in names {
+        start_browser_supervisor(Arc::clone(app_state), name.clone());
+    }
+}
+
+// 关闭主窗口 old 的浏览器，在它的显示器上重新启动并显示；失败时保留旧窗口，由守护线程继续重试。
+// 浏览器启动较慢，只在读取和更新 AppState 时持锁，期间主窗口被移除或替换时关闭新启动的浏览器。
+// 返回是否有随旧浏览器关闭、需要重新打开的设置窗口
+fn recreate_main_window(app_state: &Arc<Mutex<AppState>>, name: &str, old: &Arc<UI>, config: &AppConfig) -> bool {
+    let (monitor, url, widget) = {
+        let state = app_state.lock().unwrap();
+        match state.windows.iter().find(|w| w.name == name && Arc::ptr_eq(&w.ui, old)) {
+            Some(w) => (
+                w.monitor.clone(),
+                main_window_url(config, &w.monitor, state.widget).unwrap_or_else(|| w.url.clone()),
+                state.widget,
+            ),
+            None => return false,
+        }
+    };
+    log::info!("[ClassPaper] 正在关闭旧主窗口 {}...", name);
+    old.close_blocking(3000);
+    let Ok(ui) = create_window(&url, name, config, &monitor, widget) else {
+        log::error!("[ClassPaper] 重新创建主窗口 {} 失败", name);
+        return false;
+    };
+    let mut state = app_state.lock().unwrap();
+    let shutting_down = state.shutting_down;
+    let Some(entry) = state
+        .windows
+        .iter_mut()
+        .find(|w| w.name == name && Arc::ptr_eq(&w.ui, old))
+        .filter(|_| !shutting_down)
+    else {
+        drop(state);
+        log::info!("[ClassPaper] 主窗口 {} 已在重新创建期间被替换，关闭新启动的浏览器", name);
+        ui.close_blocking(3000);
+        return false;
+    };
+    entry.ui = Arc::new(ui);
+    entry.url = url;
+    let window = entry.clone();
+    let reopen_settings = state.take_settings_windows_in(old);
+    drop(state);
+    log::info!("[ClassPaper] 新主窗口 {} 已创建", name);
+    show_main_window(&window, widget, config);
+    reopen_settings
+}
+
 fn parse_config() -> AppConfig {
     match std::fs::read_to_string("config.toml") {
         Ok(content) => {
@@ -150,7 +535,12 @@ fn parse_config() -> AppConfig {
                         .to_string();
                     // 构造新格式
                     let new = AppConfig {
-                        default: DefaultConfig { url, browser_path },
+                        default: DefaultConfig {
+                            url,
+                            browser_path,
+                            ..DefaultConfig::default()
+                        },
+                        ..AppConfig::default()
                     };
                     // 自动迁移为新格式
                     if let Ok(new_content) = toml::to_string(&new) {
@@ -172,25 +562,87 @@ fn parse_config() -> AppConfig {
             if let Ok(content) = toml::to_string(&config) {
                 let _ = std::fs::write("config.toml", content);
             } else {
-                winapi::show_error_notification(&format!("配置文件创建失败\n\n无法读取或创建配置文件 config.toml\n\n错误信息: {}\n\n程序将使用默认配置运行。", e));
+                platform::backend().show_error(&format!("配置文件创建失败\n\n无法读取或创建配置文件 config.toml\n\n错误信息: {}\n\n程序将使用默认配置运行。", e));
             }
             config
         }
     }
 }
 
-fn create_window(url: &str, window_name: &str, browser_path: &str) -> Result<UI, Box<dyn std::error::Error>> {
+// 把 alcro 的启动错误翻译为更具体的说明，便于用户判断是重试还是更换浏览器
+fn describe_launch_error(e: &UILaunchError) -> String {
+    match e {
+        UILaunchError::ChromeInitError(JSError::BrowserExited) => {
+            format!("浏览器启动后立即退出（浏览器版本过旧或被安全软件拦截）\n{}", e)
+        }
+        UILaunchError::ChromeInitError(JSError::Transport(_)) => {
+            format!("无法与浏览器进程通信\n{}", e)
+        }
+        UILaunchError::ChromeInitError(JSError::Timeout(_)) => {
+            format!("浏览器长时间无响应，可稍后重试\n{}", e)
+        }
+        UILaunchError::ChromeInitError(JSError::Protocol { code, message, .. }) => {
+            format!("浏览器拒绝了调试协议命令 (错误码 {}): {}", code, message)
+        }
+        UILaunchError::LocateChromeError(LocateChromeError::ChromeNotInstalledError) => {
+            format!("未找到可用的 Chrome/Edge 浏览器，请安装浏览器或在配置文件中指定 BrowserPath\n{}", e)
+        }
+        UILaunchError::BrowserTooOld { version, minimum } => {
+            format!("浏览器版本过旧: {}\n至少需要 {} 及以上版本，请更新浏览器或在设置中指定较新的浏览器路径", version, minimum)
+        }
+        _ => e.to_string(),
+    }
+}
+
+fn create_window(url: &str, window_name: &str, config: &AppConfig, monitor: &Monitor, widget: bool) -> Result<UI, Box<dyn std::error::Error>> {
+    match launch_window(url, window_name, config, monitor, widget) {
+        Ok(ui) => Ok(ui),
+        Err(e) => {
+            platform::backend().handle_window_creation_error(&describe_launch_error(&e));
+            Err(Box::new(e))
+        }
+    }
+}
+
+// 启动主窗口浏览器并绑定 Rust 函数，失败时不弹窗，由调用方决定如何提示
+fn launch_window(url: &str, window_name: &str, config: &AppConfig, monitor: &Monitor, widget: bool) -> Result<UI, UILaunchError> {
     let mut builder = UIBuilder::new();
-    builder.content(Content::Url(url));
-    if !browser_path.is_empty() {
-        builder.browser_path(browser_path);
+    // 先打开空白页，设置好请求拦截和窗口标题后再跳转到 url，第一次加载也经过资源映射和离线缓存
+    builder.content(Content::Url("about:blank"));
+    if !config.default.browser_path.is_empty() {
+        builder.browser_path(&config.default.browser_path);
     }
     // 根据 URL 类型智能决定是否禁用缓存
-    let mut chrome_args = vec![
-        "--kiosk",
+    // 小窗口是普通的浏览器窗口，不由平台实现截取画面
+    let offscreen = headless() || (!widget && platform::backend().renders_offscreen());
+    let mut chrome_args = Vec::new();
+    // 小窗口不使用 kiosk 模式，由 alcro 以 --app 打开没有地址栏的窗口
+    if offscreen {
+        chrome_args.push("--headless");
+    } else if !widget {
+        chrome_args.push("--kiosk");
+    }
+    chrome_args.extend_from_slice(&[
         "--autoplay-policy=no-user-gesture-required",
         // 其他参数
-    ];
+    ]);
+    // 窗口先放到所在显示器上，kiosk 模式才会在该显示器上全屏；无界面运行时按显示器尺寸设置窗口大小。
+    // 小窗口按配置的大小放在上次保存的位置
+    let widget_bounds = widget.then(|| widget::bounds(&config.widget, monitor));
+    let window_position = match widget_bounds {
+        Some(bounds) => format!("--window-position={},{}", bounds.left, bounds.top),
+        None => format!("--window-position={},{}", monitor.x, monitor.y),
+    };
+    let window_size = format!("--window-size={},{}", monitor.width, monitor.height);
+    match widget_bounds {
+        Some(bounds) => {
+            builder.size(bounds.width, bounds.height);
+        }
+        None => chrome_args.push(&window_size),
+    }
+    if !offscreen {
+        chrome_args.push(&window_position);
+    }
     if !url.starts_with("http://") && !url.starts_with("https://") {
         // 本地文件禁用缓存
         chrome_args.extend_from_slice(&[
@@ -210,10 +662,27 @@ fn create_window(url: &str, window_name: &str, browser_path: &str) -> Result<UI,
     }
     println!("==========================================\n");
     builder.custom_args(&chrome_args);
+    builder.timeout(BROWSER_TIMEOUT);
+    builder.min_version(MIN_BROWSER_VERSION);
+    // 无人值守时找不到浏览器直接报错，不能弹出下载提示阻塞启动
+    builder.interactive(!headless());
     
     match builder.run() {
         Ok(ui) => {
-            let _ = ui.eval(&format!("document.title = '{}';", window_name));
+            if let Some(version) = ui.browser_version() {
+                log::info!("[ClassPaper] 浏览器版本: {}", version.product);
+            }
+            if let Err(e) = platform::keep_window_title(&ui, window_name) {
+                log::warn!("[ClassPaper] 设置窗口标题 {} 失败: {}", window_name, e);
+            }
+            setup_interception(&ui, url, config);
+            if widget {
+                widget::prepare(&ui);
+            }
+            // 绑定Rust函数到JS
+            bindings::bind(&ui, Page::Main, Some(monitor));
+            ui.load(Content::Url(url))?;
+            apply_display_settings(&ui, &config.default);
             // // 自动全屏JS
             // let fullscreen_js = r#"
             //     (function() {
@@ -236,259 +705,611 @@ fn create_window(url: &str, window_name: &str, browser_path: &str) -> Result<UI,
             //     })();
             // "#;
             // let _ = ui.eval(fullscreen_js);
-            // 绑定Rust函数到JS
-            let _ = ui.bind("getWidth", |_| Ok(winapi::get_screen_width().into()));
-            let _ = ui.bind("getHeight", |_| Ok(winapi::get_screen_height().into()));
-            let _ = ui.bind("readFile", |args| {
-                if let Some(path) = args.get(0).and_then(|v| v.as_str()) {
-                    match std::fs::read_to_string(path) {
-                        Ok(content) => Ok(serde_json::Value::String(content)),
-                        Err(e) => Err(format!("读取文件失败: {}", e).into()),
-                    }
-                } else {
-                    Err("参数错误".into())
-                }
-            });
-            let _ = ui.bind("writeFile", |args| {
-                if let (Some(path), Some(content)) = (
-                    args.get(0).and_then(|v| v.as_str()),
-                    args.get(1).and_then(|v| v.as_str()),
-                ) {
-                    match std::fs::write(path, content) {
-                        Ok(_) => Ok(true.into()),
-                        Err(e) => Err(format!("写入文件失败: {}", e).into()),
-                    }
-                } else {
-                    Err("参数错误".into())
-                }
-            });
-            let _ = ui.bind("readDir", |args| {
-                if let Some(dir) = args.get(0).and_then(|v| v.as_str()) {
-                    match std::fs::read_dir(dir) {
-                        Ok(entries) => {
-                            let names: Vec<_> = entries
-                                .filter_map(|e| e.ok().map(|e| e.file_name().to_string_lossy().to_string()))
-                                .collect();
-                            Ok(names.into())
-                        }
-                        Err(e) => Err(format!("读取目录失败: {}", e).into()),
-                    }
-                } else {
-                    Err("参数错误".into())
-                }
-            });
             Ok(ui)
         }
-        Err(e) => {
-            let error_str = format!("{}", e);
-            winapi::handle_window_creation_error(&error_str);
-            Err(Box::new(e))
-        }
+        Err(e) => Err(e),
     }
 }
 
-// cleanup_profile_dir 已移除
+// 根据扩展名推断本地资源的 Content-Type，未知类型交给浏览器自行判断
+fn content_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match ext.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        _ => "application/octet-stream",
+    }
+}
 
-fn open_settings_window(app_state: Arc<Mutex<AppState>>) {
-    let settings_path = normalize_url("./res/settings.html");
-    let settings_ui = match UIBuilder::new()
-        .content(Content::Url(&settings_path))
-        .run() {
-        Ok(ui) => Arc::new(ui),
+// 按 [ResourceMap] 用本地文件应答匹配的请求，例如替换学校门户页面中的样式表或离线字体。
+// 每次请求都重新读取文件，修改本地文件后刷新页面即可生效
+fn map_resource(mappings: &[(String, std::path::PathBuf)], request: &alcro::InterceptedRequest) -> InterceptAction {
+    let Some((_, file)) = mappings.iter().find(|(pattern, _)| request.matches(pattern)) else {
+        return InterceptAction::Continue;
+    };
+    match std::fs::read(file) {
+        Ok(body) => {
+            log::debug!("[ClassPaper] 本地资源映射: {} -> {}", request.url, file.display());
+            InterceptAction::Fulfill {
+                status: 200,
+                headers: vec![
+                    ("Content-Type".to_string(), content_type(file).to_string()),
+                    ("Access-Control-Allow-Origin".to_string(), "*".to_string()),
+                ],
+                body,
+            }
+        }
         Err(e) => {
-            let error_str = format!("{}", e);
-            winapi::handle_window_creation_error(&format!("无法创建设置窗口: {}", error_str));
-            return;
+            log::warn!("[ClassPaper] 读取映射的本地资源 {} 失败，改为请求原地址: {}", file.display(), e);
+            InterceptAction::Continue
         }
+    }
+}
+
+// 在主窗口中拦截请求：请求阶段处理本地资源映射，远程页面的响应阶段处理离线缓存
+fn setup_interception(ui: &UI, url: &str, config: &AppConfig) {
+    let mappings: Vec<(String, std::path::PathBuf)> = config
+        .resource_map
+        .iter()
+        .map(|(pattern, file)| (pattern.clone(), Path::new(file).to_path_buf()))
+        .collect();
+    let mut patterns: Vec<(&str, InterceptStage)> = config
+        .resource_map
+        .keys()
+        .map(|pattern| (pattern.as_str(), InterceptStage::Request))
+        .collect();
+    let remote = url.starts_with("http://") || url.starts_with("https://");
+    let cache = if remote && config.offline_cache.enabled {
+        patterns.push(("http://*", InterceptStage::Response));
+        patterns.push(("https://*", InterceptStage::Response));
+        Some(OfflineCache::new(
+            &config.offline_cache.directory,
+            config.offline_cache.max_size_mb * 1024 * 1024,
+        ))
+    } else {
+        None
     };
+    if patterns.is_empty() {
+        return;
+    }
+    let result = ui.intercept_at(&patterns, move |request| match request.response {
+        None => map_resource(&mappings, request),
+        Some(_) => match cache {
+            Some(ref cache) => cache.respond(request),
+            None => InterceptAction::Continue,
+        },
+    });
+    match result {
+        Ok(()) => log::info!(
+            "[ClassPaper] 已启用 {} 条本地资源映射，离线缓存: {}",
+            config.resource_map.len(),
+            if remote && config.offline_cache.enabled { "开启" } else { "关闭" }
+        ),
+        Err(e) => log::warn!("[ClassPaper] 启用请求拦截失败: {}", e),
+    }
+}
+
+// 按配置调整主窗口的设备像素比和缩放，浏览器被自动重启后同样会重新应用
+fn apply_display_settings(ui: &UI, config: &DefaultConfig) {
+    if config.device_scale_factor > 0.0 {
+        let metrics = DeviceMetrics {
+            width: 0,
+            height: 0,
+            device_scale_factor: config.device_scale_factor,
+            mobile: false,
+        };
+        match ui.set_device_metrics(metrics) {
+            Ok(()) => log::info!("[ClassPaper] 设备像素比已设置为 {}", config.device_scale_factor),
+            Err(e) => log::warn!("[ClassPaper] 设置设备像素比失败: {}", e),
+        }
+    }
+    if config.zoom > 0.0 && config.zoom != 1.0 {
+        match ui.set_zoom(config.zoom) {
+            Ok(()) => log::info!("[ClassPaper] 页面缩放已设置为 {}", config.zoom),
+            Err(e) => log::warn!("[ClassPaper] 设置页面缩放失败: {}", e),
+        }
+    }
+}
 
-    // 绑定 readConfig
-    let _ = settings_ui.bind("readConfig", |_| {
-        match std::fs::read_to_string("config.toml") {
-            Ok(content) => {
-                println!(
-                    "[settings][readConfig] 读取到的 config.toml 内容:\n{}",
-                    content
+// 主窗口的心跳状态，主窗口被替换后重新计数
+struct Heartbeat {
+    window: Arc<UI>,
+    next: Instant,
+    last_ok: Instant,
+    misses: u32,
+    reloaded: bool,
+}
+
+impl Heartbeat {
+    fn new(window: Arc<UI>) -> Self {
+        let now = Instant::now();
+        Self {
+            window,
+            next: now + HEARTBEAT_INTERVAL,
+            last_ok: now,
+            misses: 0,
+            reloaded: false,
+        }
+    }
+
+    // 到时间则发送一次心跳，返回需要重启浏览器的原因；页面连续无响应时先尝试重载
+    fn check(&mut self) -> Option<&'static str> {
+        let now = Instant::now();
+        if now < self.next {
+            return None;
+        }
+        self.next = now + HEARTBEAT_INTERVAL;
+        match self.window.eval_with_timeout("Date.now()", HEARTBEAT_TIMEOUT) {
+            Ok(_) => {
+                if self.misses > 0 || self.reloaded {
+                    log::info!(
+                        "[ClassPaper] 主窗口页面恢复响应，此前无响应约 {} 秒",
+                        now.duration_since(self.last_ok).as_secs()
+                    );
+                }
+                self.last_ok = Instant::now();
+                self.misses = 0;
+                self.reloaded = false;
+                None
+            }
+            Err(e) if e.needs_restart() => {
+                log::warn!("[ClassPaper] 主窗口心跳失败，浏览器连接已断开: {}", e);
+                Some("连接已断开")
+            }
+            Err(JSError::Timeout(_)) => {
+                self.misses += 1;
+                log::warn!(
+                    "[ClassPaper] 主窗口心跳超时 ({} ms 内未响应)，已连续 {} 次，距上次正常响应 {} 秒",
+                    HEARTBEAT_TIMEOUT.as_millis(),
+                    self.misses,
+                    self.last_ok.elapsed().as_secs()
                 );
-                // 先用 toml 解析为 serde_json::Value
-                match toml::from_str::<serde_json::Value>(&content) {
-                    Ok(json) => {
-                        // 强制转换为 { Default: { URL, BrowserPath } } 格式，字段名大写
-                        // 兼容 [default] 或 [Default] 段
-                        let mut result = serde_json::Map::new();
-                        let default_obj = json.get("Default").or_else(|| json.get("default"));
-                        if let Some(default) = default_obj {
-                            let mut default_map = serde_json::Map::new();
-                            if let Some(url) = default.get("url").or_else(|| default.get("URL")) {
-                                default_map.insert("URL".to_string(), url.clone());
-                            }
-                            if let Some(browser_path) = default
-                                .get("browser_path")
-                                .or_else(|| default.get("BrowserPath"))
-                            {
-                                default_map.insert("BrowserPath".to_string(), browser_path.clone());
-                            }
-                            result.insert(
-                                "Default".to_string(),
-                                serde_json::Value::Object(default_map),
-                            );
-                        }
-                        let json_result = serde_json::Value::Object(result);
-                        println!("[settings][readConfig] 兼容后的 JSON: {}", json_result);
-                        Ok(json_result)
+                if self.misses < HEARTBEAT_MAX_MISSES {
+                    return None;
+                }
+                if self.reloaded {
+                    log::error!("[ClassPaper] 主窗口页面重载后仍无响应，准备重启浏览器");
+                    return Some("页面重载后仍无响应");
+                }
+                self.misses = 0;
+                self.reloaded = true;
+                let started = Instant::now();
+                match self.window.reload(BROWSER_TIMEOUT) {
+                    Ok(()) => {
+                        log::warn!(
+                            "[ClassPaper] 主窗口页面无响应，已重载页面 (耗时 {} ms)",
+                            started.elapsed().as_millis()
+                        );
+                        None
                     }
                     Err(e) => {
-                        println!("[settings][readConfig] TOML 解析失败: {}", e);
-                        Err(format!("TOML 解析失败: {}", e).into())
+                        log::error!("[ClassPaper] 主窗口页面无响应且重载失败: {}", e);
+                        Some("页面无响应且重载失败")
                     }
                 }
             }
             Err(e) => {
-                println!("[settings][readConfig] 读取配置失败: {}", e);
-                Err(format!("读取配置失败: {}", e).into())
+                // 页面仍在运行，只是脚本出错，不视为无响应
+                log::warn!("[ClassPaper] 主窗口心跳返回错误: {}", e);
+                None
             }
         }
-    });
-    // 绑定 saveConfig
-    let _ = settings_ui.bind("saveConfig", |args| {
-        if let Some(config_json) = args.get(0).and_then(|v| v.as_str()) {
-            // 先解析为 serde_json::Value
-            let value: serde_json::Value = match serde_json::from_str(config_json) {
-                Ok(v) => v,
-                Err(e) => {
-                    winapi::show_error_notification(&format!("配置解析失败\n\n无法解析配置数据\n\n错误信息: {}\n\n请检查输入的配置格式是否正确。", e));
-                    return Err(format!("解析配置JSON失败: {}", e).into());
+    }
+}
+
+// 守护标题为 window_name 的主窗口浏览器：进程退出、页面崩溃或心跳长时间无响应时按指数退避自动重启并重新设置桌面穿透，
+// 短时间内连续崩溃过多则提示用户并暂停，直到用户通过托盘手动重启。每个主窗口各有一个守护线程，窗口被移除后线程结束
+fn start_browser_supervisor(app_state: Arc<Mutex<AppState>>, window_name: String) {
+    thread::spawn(move || {
+        let mut crashes: Vec<Instant> = Vec::new();
+        let mut given_up: Option<Arc<UI>> = None;
+        let mut heartbeat: Option<Heartbeat> = None;
+        loop {
+            thread::sleep(SUPERVISOR_INTERVAL);
+            let window = {
+                let mut state = app_state.lock().unwrap();
+                if state.shutting_down {
+                    return;
+                }
+                state.prune_settings_windows();
+                match state.windows.iter().find(|w| w.name == window_name) {
+                    Some(window) => Arc::clone(&window.ui),
+                    None => {
+                        log::info!("[ClassPaper] 主窗口 {} 已移除，停止守护", window_name);
+                        return;
+                    }
                 }
             };
-            // 只序列化 Default 字段为 [Default] 段，字段名大写
-            let mut toml_map = toml::map::Map::new();
-            if let Some(default) = value.get("Default") {
-                toml_map.insert(
-                    "Default".to_string(),
-                    toml::Value::try_from(default.clone())
-                        .unwrap_or(toml::Value::String(String::new())),
-                );
+            if let Some(ref last) = given_up {
+                if Arc::ptr_eq(last, &window) {
+                    continue;
+                }
+                // 用户已手动重启过，重新开始计数
+                given_up = None;
+                crashes.clear();
             }
-            let toml_value = toml::Value::Table(toml_map);
-            let toml_str = match toml::to_string(&toml_value) {
-                Ok(s) => s,
-                Err(e) => {
-                    winapi::show_error_notification(&format!("配置序列化失败\n\n无法将配置转换为TOML格式\n\n错误信息: {}\n\n请检查配置数据格式。", e));
-                    return Err(format!("序列化TOML失败: {}", e).into());
+            // 心跳在不持有 AppState 锁的情况下进行，避免页面卡死时阻塞托盘菜单
+            let heartbeat = match heartbeat {
+                Some(ref mut hb) if Arc::ptr_eq(&hb.window, &window) => hb,
+                _ => heartbeat.insert(Heartbeat::new(window.clone())),
+            };
+            let reason = if window.crashed() {
+                "页面崩溃"
+            } else if window.done() {
+                "进程已退出"
+            } else {
+                match heartbeat.check() {
+                    Some(reason) => reason,
+                    None => continue,
                 }
             };
-            // 写入文件
-            match std::fs::write("config.toml", toml_str) {
-                Ok(_) => Ok(true.into()),
-                Err(e) => {
-                    winapi::show_error_notification(&format!("配置保存失败\n\n无法保存配置文件 config.toml\n\n错误信息: {}\n\n可能原因：\n• 文件权限不足\n• 文件被其他程序占用\n• 磁盘空间不足\n\n请检查文件权限或重启程序后再试。", e));
-                    Err(format!("写入配置失败: {}", e).into())
+            let dead = window;
+            let now = Instant::now();
+            crashes.retain(|t| now.duration_since(*t) < CRASH_WINDOW);
+            crashes.push(now);
+            if crashes.len() >= MAX_CRASHES {
+                log::error!("[ClassPaper] 主窗口 {} 的浏览器在 {} 秒内崩溃 {} 次，停止自动重启", window_name, CRASH_WINDOW.as_secs(), crashes.len());
+                platform::backend().show_error(&format!("网页显示程序反复崩溃\n\n浏览器在 {} 分钟内崩溃了 {} 次，已停止自动重启。\n\n可能原因：\n• 浏览器版本不兼容\n• 系统内存不足\n• 被安全软件拦截\n\n请检查后通过托盘菜单“重启网页显示程序”手动恢复。", CRASH_WINDOW.as_secs() / 60, crashes.len()));
+                given_up = Some(dead);
+                continue;
+            }
+            let backoff = RELAUNCH_BACKOFF_INITIAL
+                .saturating_mul(1 << (crashes.len() - 1))
+                .min(RELAUNCH_BACKOFF_MAX);
+            log::warn!(
+                "[ClassPaper] 检测到主窗口 {} 的浏览器{}，{} 秒后自动重启 (第 {} 次)",
+                window_name,
+                reason,
+                backoff.as_secs(),
+                crashes.len()
+            );
+            thread::sleep(backoff);
+            let config = parse_config();
+            // 只在读取和更新 AppState 时持锁，浏览器启动较慢，期间不能阻塞托盘和绑定
+            let (monitor, url, widget) = {
+                let state = app_state.lock().unwrap();
+                if state.shutting_down {
+                    return;
+                }
+                // 等待期间用户可能已手动重启
+                match state.windows.iter().find(|w| w.name == window_name && Arc::ptr_eq(&w.ui, &dead)) {
+                    // 配置中已不再为该显示器指定页面时沿用原来的页面，由用户通过托盘重启生效
+                    Some(w) => (
+                        w.monitor.clone(),
+                        main_window_url(&config, &w.monitor, state.widget).unwrap_or_else(|| w.url.clone()),
+                        state.widget,
+                    ),
+                    None => continue,
                 }
+            };
+            if !dead.done() {
+                dead.close_blocking(3000);
             }
-        } else {
-            Err("参数错误".into())
-        }
-    });
-    // 绑定 writeFile
-    let _ = settings_ui.bind("writeFile", |args| {
-        if let (Some(path), Some(content)) = (
-            args.get(0).and_then(|v| v.as_str()),
-            args.get(1).and_then(|v| v.as_str()),
-        ) {
-            match std::fs::write(path, content) {
-                Ok(_) => Ok(true.into()),
+            let ui = match launch_window(&url, &window_name, &config, &monitor, widget) {
+                Ok(ui) => ui,
                 Err(e) => {
-                    winapi::show_error_notification(&format!("文件写入失败\n\n无法写入文件: {}\n\n错误信息: {}\n\n可能原因：\n• 文件权限不足\n• 文件被其他程序占用\n• 磁盘空间不足\n• 路径不存在", path, e));
-                    Err(format!("写入文件失败: {}", e).into())
+                    log::error!("[ClassPaper] 自动重启主窗口浏览器失败: {}", describe_launch_error(&e));
+                    continue;
                 }
-            }
-        } else {
-            Err("参数错误".into())
-        }
-    });
-    // 绑定 scanWallpaperDir
-    let _ = settings_ui.bind("scanWallpaperDir", |_| {
-        let mut wallpapers = Vec::new();
-        match std::fs::read_dir("res/wallpaper") {
-            Ok(entries) => {
-                for entry in entries.flatten() {
-                    let path = entry.path();
-                    if path.is_file() {
-                        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                            let ext = ext.to_lowercase();
-                            if ext == "jpg" || ext == "jpeg" || ext == "png" || ext == "gif" {
-                                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                                    wallpapers.push(format!("wallpaper/{}", name));
-                                }
+            };
+            let mut state = app_state.lock().unwrap();
+            let shutting_down = state.shutting_down;
+            // 启动期间窗口没有被手动重启，换上新窗口
+            match state.windows.iter_mut().find(|w| w.name == window_name && Arc::ptr_eq(&w.ui, &dead)) {
+                Some(entry) if !shutting_down => {
+                    let window = Arc::new(ui);
+                    entry.ui = Arc::clone(&window);
+                    entry.url = url;
+                    // 在旧浏览器中打开的设置窗口随之关闭，在新浏览器中重新打开
+                    let reopen_settings = state.take_settings_windows_in(&dead);
+                    drop(state);
+                    if widget {
+                        thread::sleep(Duration::from_millis(300));
+                        widget::show(&window, &window_name, &config.widget, &monitor);
+                        log::info!("[ClassPaper] 小窗口 {} 的浏览器已自动重启", window_name);
+                    } else {
+                        match platform::backend().embed_as_wallpaper(&window, &window_name, &monitor) {
+                            Ok(()) => log::info!("[ClassPaper] 主窗口 {} 的浏览器已自动重启并重新设置桌面穿透", window_name),
+                            Err(e) => {
+                                log::warn!("[ClassPaper] 主窗口 {} 的浏览器已自动重启，但桌面穿透设置失败: {}", window_name, e);
+                                embed_health::retry(&window_name);
                             }
                         }
                     }
+                    if reopen_settings {
+                        log::info!("[ClassPaper] 重新打开随浏览器关闭的设置窗口");
+                        open_settings_window(Arc::clone(&app_state));
+                    }
+                }
+                _ => {
+                    drop(state);
+                    log::info!("[ClassPaper] 主窗口已在自动重启期间被替换，关闭新启动的浏览器");
+                    ui.close_blocking(3000);
                 }
-            }
-            Err(e) => {
-                winapi::show_error_notification(&format!("壁纸目录扫描失败\n\n无法扫描壁纸目录: res/wallpaper\n\n错误信息: {}\n\n可能原因：\n• 目录不存在\n• 权限不足\n• 路径错误\n\n请检查壁纸目录是否存在并有读取权限。", e));
-                log::warn!("[ClassPaper] 扫描壁纸目录失败: {}", e);
             }
         }
-        Ok(serde_json::Value::Array(
-            wallpapers
-                .into_iter()
-                .map(serde_json::Value::String)
-                .collect(),
-        ))
     });
-    // 绑定 reloadMainWindow
-    let app_state_reload = Arc::clone(&app_state);
-    let _ = settings_ui.bind("reloadMainWindow", move |_| {
-        let state = app_state_reload.lock().unwrap();
-        if let Some(ref window) = state.window {
-            let _ = window.eval("location.reload(true)");
-        }
-        Ok(true.into())
+}
+
+// 监听显示器变化：插拔投影仪、改变分辨率或 DPI 后让主窗口铺满新的显示器区域，
+// 为新接入的显示器创建主窗口，关闭已移除的显示器上的主窗口
+fn start_display_watcher(app_state: Arc<Mutex<AppState>>) {
+    let (tx, rx) = mpsc::channel();
+    platform::backend().watch_displays(Box::new(move || {
+        let _ = tx.send(());
+    }));
+    thread::spawn(move || {
+        while rx.recv().is_ok() {
+            // 系统在显示器变化的过程中会连续发出多次通知，等配置稳定后一并处理
+            thread::sleep(DISPLAY_SETTLE);
+            while rx.try_recv().is_ok() {}
+            handle_display_change(&app_state);
+        }
     });
-    // 绑定 openURLInBrowser
-    let _ = settings_ui.bind("openURLInBrowser", |args| {
-        if let Some(url) = args.get(0).and_then(|v| v.as_str()) {
-            #[cfg(target_os = "windows")]
-            let result = Command::new("cmd").args(["/c", "start", url]).spawn();
-            #[cfg(target_os = "macos")]
-            let result = Command::new("open").arg(url).spawn();
-            #[cfg(target_os = "linux")]
-            let result = Command::new("xdg-open").arg(url).spawn();
-            match result {
-                Ok(_) => Ok(true.into()),
-                Err(e) => {
-                    winapi::show_error_notification(&format!("浏览器打开失败\n\n无法打开链接: {}\n\n错误信息: {}\n\n可能原因：\n• 未安装默认浏览器\n• 浏览器路径配置错误\n• 系统关联设置问题\n\n请检查浏览器设置或手动访问该链接。", url, e));
-                    Err(format!("打开浏览器失败: {}", e).into())
-                }
+}
+
+fn handle_display_change(app_state: &Arc<Mutex<AppState>>) {
+    let monitors = platform::monitors();
+    let config = parse_config();
+    log::info!(
+        "[ClassPaper] 显示器发生变化，当前显示器: {}",
+        monitors
+            .iter()
+            .map(|m| format!("{} ({}x{}+{}+{})", m.id, m.width, m.height, m.x, m.y))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let backend = platform::backend();
+    let offscreen = headless() || backend.renders_offscreen();
+    // 持锁时只整理要做的事，关闭、调整和重新创建浏览器较慢，在释放锁之后进行，不阻塞托盘和绑定
+    let mut state = app_state.lock().unwrap();
+    if state.shutting_down {
+        return;
+    }
+    // 小窗口是普通窗口，只需保证仍在某个显示器内
+    if state.widget {
+        let windows: Vec<Arc<UI>> = state.windows.iter().map(|w| Arc::clone(&w.ui)).collect();
+        drop(state);
+        for window in &windows {
+            widget::keep_visible(window, &monitors);
+        }
+        return;
+    }
+    // 已移除或按配置不再显示页面的显示器上的主窗口
+    let (windows, removed): (Vec<MainWindow>, Vec<MainWindow>) = std::mem::take(&mut state.windows)
+        .into_iter()
+        .partition(|w| monitors.iter().any(|m| m.id == w.monitor.id && monitor_url(&config, m).is_some()));
+    state.windows = windows;
+    let mut reopen_settings = false;
+    for window in &removed {
+        reopen_settings |= state.take_settings_windows_in(&window.ui);
+    }
+    // 位置或大小改变的显示器上的主窗口
+    let mut changed = Vec::new();
+    for window in state.windows.iter_mut() {
+        let Some(monitor) = monitors.iter().find(|m| m.id == window.monitor.id) else {
+            continue;
+        };
+        if *monitor == window.monitor {
+            continue;
+        }
+        log::info!(
+            "[ClassPaper] 显示器 {} 从 {}x{}+{}+{} 变为 {}x{}+{}+{}，调整主窗口 {}",
+            monitor.id,
+            window.monitor.width,
+            window.monitor.height,
+            window.monitor.x,
+            window.monitor.y,
+            monitor.width,
+            monitor.height,
+            monitor.x,
+            monitor.y,
+            window.name
+        );
+        window.monitor = monitor.clone();
+        changed.push((window.name.clone(), Arc::clone(&window.ui), monitor.clone()));
+    }
+    // 新接入的显示器
+    let added: Vec<Monitor> = monitors
+        .into_iter()
+        .filter(|m| !state.windows.iter().any(|w| w.monitor.id == m.id))
+        .collect();
+    drop(state);
+
+    for window in removed {
+        log::info!("[ClassPaper] 显示器 {} 已移除或不再显示页面，关闭主窗口 {}", window.monitor.id, window.name);
+        window.ui.close_blocking(3000);
+        backend.remove_wallpaper(&window.name, &window.monitor);
+    }
+    // 重新铺满并通知页面
+    for (name, ui, monitor) in changed {
+        // 无界面运行的浏览器由窗口大小决定页面视口，需要跟着调整；无法调整时重新创建
+        if offscreen {
+            let bounds = Bounds {
+                left: 0,
+                top: 0,
+                width: monitor.width,
+                height: monitor.height,
+                window_state: WindowState::Normal,
+            };
+            if let Err(e) = ui.set_bounds(bounds) {
+                log::warn!("[ClassPaper] 调整主窗口 {} 的大小失败，重新创建: {}", name, e);
+                reopen_settings |= recreate_main_window(app_state, &name, &ui, &config);
+                continue;
             }
-        } else {
-            Err("参数错误".into())
         }
-    });
-    
-    // 绑定 getBuildDate 函数，返回编译日期
-    let _ = settings_ui.bind("getBuildDate", |_| {
-        // 使用编译时环境变量获取构建日期
-        let build_date = option_env!("BUILD_DATE").unwrap_or("2026-01-28");
-        Ok(serde_json::Value::String(build_date.to_string()))
-    });
+        if let Err(e) = backend.fit_to_monitor(&ui, &name, &monitor) {
+            log::warn!("[ClassPaper] 主窗口 {} 适配显示器失败: {}", name, e);
+            embed_health::retry(&name);
+        }
+        if let Err(e) = ui.emit("classpaper:displaychange", &monitor) {
+            log::debug!("[ClassPaper] 通知主窗口 {} 显示器变化失败: {}", name, e);
+        }
+    }
+    let names = create_main_windows(app_state, &config, added);
+    if !names.is_empty() {
+        // 与启动时一样，等浏览器窗口出现后再嵌入
+        thread::sleep(Duration::from_millis(300));
+        show_main_windows(app_state, &names, &config);
+    }
+    if reopen_settings {
+        log::info!("[ClassPaper] 重新打开随浏览器关闭的设置窗口");
+        open_settings_window(Arc::clone(app_state));
+    }
+}
+
+// cleanup_profile_dir 已移除
+
+// 设置窗口上次关闭时的位置、大小和状态，第一次打开时为 1000x720
+fn settings_bounds() -> Bounds {
+    let default = Bounds {
+        left: 100,
+        top: 100,
+        width: 1000,
+        height: 720,
+        window_state: WindowState::Normal,
+    };
+    window_state::restore(WindowKind::Settings, default)
+}
+
+// 优先在主窗口的浏览器进程中打开设置窗口，老旧电脑上可省下一个浏览器进程的内存
+fn open_settings_in_main_browser(app_state: &Arc<Mutex<AppState>>, settings_path: &str) -> Option<(UI, Arc<UI>)> {
+    let main_window = app_state.lock().unwrap().primary_window()?;
+    if main_window.done() || main_window.crashed() {
+        return None;
+    }
+    match main_window.new_window(Content::Url(settings_path)) {
+        Ok(ui) => {
+            // 主窗口为 kiosk 模式，新窗口默认也会全屏，恢复为上次关闭时的位置和大小
+            if let Err(e) = window_state::apply(&ui, settings_bounds()) {
+                log::debug!("[ClassPaper] 调整设置窗口大小失败: {}", e);
+            }
+            log::info!("[ClassPaper] 设置窗口已在主窗口浏览器中打开");
+            Some((ui, main_window))
+        }
+        Err(e) => {
+            log::warn!("[ClassPaper] 无法在主窗口浏览器中打开设置窗口，改为启动新的浏览器: {}", e);
+            None
+        }
+    }
+}
+
+// 设置窗口由托盘打开，所在的主窗口浏览器被自动重启后也会重新打开
+fn open_settings_window(app_state: Arc<Mutex<AppState>>) {
+    let settings_path = normalize_url("./res/settings.html");
+    let (settings_ui, host) = match open_settings_in_main_browser(&app_state, &settings_path) {
+        Some((ui, host)) => (Arc::new(ui), Some(host)),
+        None => match UIBuilder::new()
+            .content(Content::Url(&settings_path))
+            .timeout(BROWSER_TIMEOUT)
+            .min_version(MIN_BROWSER_VERSION)
+            .run() {
+            Ok(ui) => {
+                if let Err(e) = window_state::apply(&ui, settings_bounds()) {
+                    log::debug!("[ClassPaper] 调整设置窗口大小失败: {}", e);
+                }
+                (Arc::new(ui), None)
+            }
+            Err(e) => {
+                let error_str = describe_launch_error(&e);
+                platform::backend().handle_window_creation_error(&format!("无法创建设置窗口: {}", error_str));
+                return;
+            }
+        },
+    };
+
+    bindings::bind(&settings_ui, Page::Settings, None);
+    window_state::track(WindowKind::Settings, Arc::downgrade(&settings_ui));
     // 设置窗口标题
     let _ = settings_ui.eval("document.title = 'ClassPaper 设置';");
     // 最后 push Arc 到 AppState
     {
         let mut state = app_state.lock().unwrap();
-        state.settings_windows.push(settings_ui);
+        state.settings_windows.push(SettingsWindow { ui: settings_ui, host });
+    }
+}
+
+// 在壁纸模式和小窗口模式之间切换并写入 config.toml：关闭所有主窗口，按新的模式重新创建
+#[cfg(windows)]
+fn toggle_widget_mode(app_state: &Arc<Mutex<AppState>>) {
+    let config = parse_config();
+    let (old, widget, reopen_settings) = {
+        let mut state = app_state.lock().unwrap();
+        if state.shutting_down {
+            return;
+        }
+        state.widget = !state.widget;
+        // 先从 AppState 中移除，守护线程找不到窗口后自行结束，不会把关闭当成崩溃
+        let old = std::mem::take(&mut state.windows);
+        let mut reopen_settings = false;
+        for window in &old {
+            reopen_settings |= state.take_settings_windows_in(&window.ui);
+        }
+        (old, state.widget, reopen_settings)
+    };
+    log::info!("[ClassPaper] 切换为{}", if widget { "小窗口模式" } else { "壁纸模式" });
+    if let Err(e) = save_widget_enabled(widget) {
+        log::warn!("[ClassPaper] 保存窗口模式失败，下次启动仍使用原来的模式: {}", e);
+    }
+    for window in old {
+        log::info!("[ClassPaper] 正在关闭主窗口 {}...", window.name);
+        window.ui.close_blocking(3000);
+        platform::backend().remove_wallpaper(&window.name, &window.monitor);
+    }
+    let names = create_main_windows(app_state, &config, platform::monitors());
+    if !names.is_empty() {
+        thread::sleep(Duration::from_millis(300));
+        show_main_windows(app_state, &names, &config);
+    }
+    if reopen_settings {
+        log::info!("[ClassPaper] 重新打开随浏览器关闭的设置窗口");
+        open_settings_window(Arc::clone(app_state));
     }
 }
 
+// 只改写 config.toml 中 [Widget] 的 Enabled，其他段和字段原样保留
+#[cfg(windows)]
+fn save_widget_enabled(enabled: bool) -> Result<(), String> {
+    let content = std::fs::read_to_string("config.toml").unwrap_or_default();
+    let mut config = content
+        .parse::<TomlValue>()
+        .map_err(|e| format!("config.toml 格式错误: {}", e))?;
+    let widget = config
+        .as_table_mut()
+        .ok_or("config.toml 格式错误")?
+        .entry("Widget")
+        .or_insert_with(|| TomlValue::Table(Default::default()));
+    let Some(widget) = widget.as_table_mut() else {
+        return Err("config.toml 中的 Widget 不是一个段".to_string());
+    };
+    widget.insert("Enabled".to_string(), TomlValue::Boolean(enabled));
+    let content = toml::to_string(&config).map_err(|e| e.to_string())?;
+    std::fs::write("config.toml", content).map_err(|e| e.to_string())
+}
+
 fn close_all_and_exit(app_state: &Arc<Mutex<AppState>>) -> ! {
-    let state = app_state.lock().unwrap();
-    if let Some(ref window) = state.window {
-        window.close_blocking(3000);
+    let (windows, settings_windows) = app_state.lock().unwrap().shut_down();
+    for window in &windows {
+        window.ui.close_blocking(3000);
     }
-    for win in &state.settings_windows {
-        win.close_blocking(3000);
+    for win in &settings_windows {
+        win.ui.close_blocking(3000);
     }
     // 让后台线程有机会检测到管道关闭
     std::thread::sleep(std::time::Duration::from_millis(500));
@@ -499,148 +1320,139 @@ fn close_all_and_exit(app_state: &Arc<Mutex<AppState>>) -> ! {
 
 
 
-fn main() -> std::io::Result<()> {
-    // 日志初始化增强（美化格式/本地时间/分级/彩色/线程/文件/行号）
-    let mut builder = ConfigBuilder::new();
-    builder.set_thread_mode(ThreadLogMode::Both);
-    builder.set_thread_level(LevelFilter::Info);
-    builder.set_location_level(LevelFilter::Debug);
-    builder.set_level_padding(LevelPadding::Right);
-    builder.set_time_offset_to_local().ok();
-    let log_config = builder.build();
-    let log_file = std::fs::OpenOptions::new().create(true).append(true).open("app.log").unwrap_or_else(|e| {
-        eprintln!("[日志] 无法打开 app.log: {}，日志将输出到 stderr/nul", e);
-        winapi::show_error_notification(&format!("日志文件创建失败\n\n无法创建或写入日志文件 app.log\n\n错误信息: {}\n\n程序将继续运行，但日志将不会保存到文件。", e));
-        #[cfg(windows)]
-        { std::fs::OpenOptions::new().write(true).open("nul").unwrap() }
-        #[cfg(not(windows))]
-        { std::fs::File::create("/dev/stderr").unwrap() }
-    });
-    let log_level = std::env::var("RUST_LOG").ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(LevelFilter::Info);
-    if let Err(e) = CombinedLogger::init(vec![
-        WriteLogger::new(log_level, log_config.clone(), log_file),
-        #[cfg(debug_assertions)]
-        TermLogger::new(LevelFilter::Debug, log_config, TerminalMode::Mixed, ColorChoice::Auto),
-    ]) {
-        log::error!("[ClassPaper] 日志系统初始化失败: {}", e);
-        winapi::show_error_notification(&format!("日志系统初始化失败\n\n无法初始化日志系统\n\n错误信息: {}\n\n程序将继续运行，但日志功能可能受限。", e));
-    }
-    log::info!("[ClassPaper] 日志系统初始化完成，日志级别: {:?}", log_level);
-    // DPI感知
-    winapi::set_dpi_aware();
-    log::info!("[ClassPaper] DPI 感知已设置");
-    let config = parse_config();
-    let url = normalize_url(&config.default.url);
-    log::info!("[ClassPaper][加载配置URL] {}", url);
-    let window_name = format!("classpaper{}", generate_random_string(6));
-    let app_state = Arc::new(Mutex::new(AppState {
-        window: None,
-        window_name: window_name.clone(),
-        settings_windows: Vec::new(),
-    }));
-    // ctrlc 优雅退出
-    let app_state_ctrlc = Arc::clone(&app_state);
-    let app_state_ctrlc2 = Arc::clone(&app_state_ctrlc);
-    if let Err(e) = ctrlc::set_handler(move || {
-        log::warn!("[ClassPaper] 收到 Ctrl+C 信号，准备优雅退出");
-        close_all_and_exit(&app_state_ctrlc2);
-    }) {
-        log::error!("[ClassPaper] 设置 Ctrl+C 处理程序失败: {}", e);
-        winapi::show_error_notification(&format!("信号处理设置失败\n\n无法设置程序退出信号处理\n\n错误信息: {}\n\n程序仍可正常运行，但可能无法优雅退出。", e));
-    }
+// 托盘菜单中需要在主线程更新的文字，没有托盘的平台上丢弃
+#[cfg_attr(not(windows), allow(dead_code))]
+enum TrayUpdate {
+    Embed(EmbedStatus),
+    Interaction(String),
+}
+
+// 创建托盘菜单；返回的托盘对象需要一直持有，否则图标会消失，另返回显示桌面嵌入状态和交互方式的菜单项 id
+#[cfg(windows)]
+fn setup_tray(app_state: &Arc<Mutex<AppState>>) -> Option<(TrayItem, u32, u32)> {
     let mut tray = match TrayItem::new("ClassPaper", tray_item::IconSource::Resource("IDI_ICON1")) {
         Ok(tray) => tray,
         Err(e) => {
             log::error!("[ClassPaper] 无法创建系统托盘: {}", e);
-            winapi::show_error_notification(&format!("系统托盘创建失败\n\n无法创建系统托盘图标\n\n错误信息: {}\n\n程序仍可运行，但将无法通过托盘菜单操作。", e));
+            platform::backend().show_error(&format!("系统托盘创建失败\n\n无法创建系统托盘图标\n\n错误信息: {}\n\n程序仍可运行，但将无法通过托盘菜单操作。", e));
             // 创建一个虚拟的托盘对象，允许程序继续运行
             match TrayItem::new("ClassPaper", tray_item::IconSource::Resource("IDI_ICON1")) {
                 Ok(tray) => tray,
                 Err(_) => {
                     // 如果仍然失败，程序可以继续运行但无托盘功能
-                    return Ok(());
+                    return None;
                 }
             }
         }
     };
     log::info!("[ClassPaper] 托盘已创建");
-    let app_state_reload = Arc::clone(&app_state);
+    let status_label = match tray.inner_mut().add_label_with_id(&EmbedStatus::Healthy.label()) {
+        Ok(id) => id,
+        Err(e) => {
+            log::error!("[ClassPaper] 无法添加桌面嵌入状态菜单项: {}", e);
+            return None;
+        }
+    };
+    let app_state_reload = Arc::clone(app_state);
     tray.add_menu_item("重载网页", move || {
         log::info!("[托盘] 点击了重载网页");
-        let state = app_state_reload.lock().unwrap();
-        if let Some(ref window) = state.window {
-            let _ = window.eval("location.reload(true)");
-            log::debug!("[托盘] 已请求主窗口重载");
+        // 页面卡住时要等到超时，不能持锁进行
+        let windows: Vec<(String, Arc<UI>)> = app_state_reload
+            .lock()
+            .unwrap()
+            .windows
+            .iter()
+            .map(|w| (w.name.clone(), Arc::clone(&w.ui)))
+            .collect();
+        for (name, ui) in &windows {
+            match ui.eval_with_timeout("location.reload(true)", BROWSER_TIMEOUT) {
+                Ok(_) => log::debug!("[托盘] 已请求主窗口 {} 重载", name),
+                Err(e) if e.needs_restart() => {
+                    log::warn!("[托盘] 主窗口 {} 的浏览器已不可用，需要重启网页显示程序: {}", name, e)
+                }
+                Err(e) => log::warn!("[托盘] 主窗口 {} 重载失败: {}", name, e),
+            }
         }
     })
     .expect("无法添加重载菜单项");
-    let app_state_penetration = Arc::clone(&app_state);
+    let app_state_penetration = Arc::clone(app_state);
     tray.add_menu_item("设置程序桌面穿透", move || {
         log::info!("[托盘] 点击了桌面穿透");
-        let state = app_state_penetration.lock().unwrap();
-        winapi::setup_desktop_penetration(&state.window_name);
+        // 嵌入失败时会弹出提示，不能持锁进行
+        let (windows, widget) = {
+            let state = app_state_penetration.lock().unwrap();
+            (state.windows.clone(), state.widget)
+        };
+        if widget {
+            log::info!("[托盘] 小窗口模式下不设置桌面穿透");
+            return;
+        }
+        for window in &windows {
+            embed_health::embed(window);
+        }
         log::debug!("[托盘] 已请求设置桌面穿透");
     })
     .expect("无法添加穿透菜单项");
-    let app_state_restart = Arc::clone(&app_state);
+    // 点击依次切换点击穿透、可交互、热键切换，文字由 interaction::init 的回调更新
+    let interaction_item = match tray.inner_mut().add_menu_item_with_id("交互方式: 点击穿透", || {
+        log::info!("[托盘] 点击了交互方式");
+        interaction::set_mode(interaction::mode().next());
+    }) {
+        Ok(id) => id,
+        Err(e) => {
+            log::error!("[ClassPaper] 无法添加交互方式菜单项: {}", e);
+            return None;
+        }
+    };
+    let app_state_widget = Arc::clone(app_state);
+    tray.add_menu_item("切换壁纸/小窗口模式", move || {
+        log::info!("[托盘] 点击了切换壁纸/小窗口模式");
+        toggle_widget_mode(&app_state_widget);
+    })
+    .expect("无法添加小窗口模式菜单项");
+    let app_state_restart = Arc::clone(app_state);
     tray.add_menu_item("重启网页显示程序", move || {
         log::info!("[托盘] 点击了重启网页显示程序");
         let config = parse_config();
-        let url = normalize_url(&config.default.url);
-        let browser_path = config.default.browser_path.clone();
-        let mut state = app_state_restart.lock().unwrap();
-        // 严格关闭所有旧窗口（包括主窗口和设置窗口）
-        if let Some(ref window) = state.window {
-            log::info!("[ClassPaper] 正在关闭旧主窗口...");
-            window.close_blocking(3000);
-            log::info!("[ClassPaper] 旧主窗口已关闭");
-        }
-        for (i, win) in state.settings_windows.iter().enumerate() {
+        // 严格关闭所有设置窗口，再逐个在原来的显示器上重建主窗口；关闭和启动浏览器时不持锁
+        let (settings_windows, windows) = {
+            let mut state = app_state_restart.lock().unwrap();
+            let windows: Vec<(String, Arc<UI>)> =
+                state.windows.iter().map(|w| (w.name.clone(), Arc::clone(&w.ui))).collect();
+            (std::mem::take(&mut state.settings_windows), windows)
+        };
+        for (i, win) in settings_windows.iter().enumerate() {
             log::info!("[ClassPaper] 正在关闭设置窗口 {}...", i + 1);
-            win.close_blocking(3000);
+            win.ui.close_blocking(3000);
             log::info!("[ClassPaper] 设置窗口 {} 已关闭", i + 1);
         }
-        state.settings_windows.clear(); // 清空设置窗口列表
-        // 确保所有窗口都关闭后，再创建新窗口
-        match create_window(&url, &state.window_name, &browser_path) {
-            Ok(new_ui) => {
-                let new_window = Arc::new(new_ui);
-                log::info!("[ClassPaper] 新主窗口已创建");
-                state.window = Some(new_window.clone());
-                winapi::setup_desktop_penetration(&state.window_name);
-                log::debug!("[托盘] 已请求重启网页显示程序并设置桌面穿透，所有旧窗口已确保关闭");
-            }
-            Err(_) => {
-                log::error!("[ClassPaper] 重启网页显示程序失败");
-            }
+        for (name, ui) in &windows {
+            recreate_main_window(&app_state_restart, name, ui, &config);
         }
+        log::debug!("[托盘] 已请求重启网页显示程序并设置桌面穿透，所有旧窗口已确保关闭");
     })
     .expect("无法添加重启菜单项");
-    let app_state_settings = Arc::clone(&app_state);
+    let app_state_settings = Arc::clone(app_state);
     tray.add_menu_item("设置", move || {
         log::info!("[托盘] 点击了设置");
         open_settings_window(app_state_settings.clone());
         log::debug!("[托盘] 已请求打开设置窗口");
     })
     .expect("无法添加设置菜单项");
-    let app_state_restart_app = Arc::clone(&app_state);
+    let app_state_restart_app = Arc::clone(app_state);
     tray.add_menu_item("重启程序", move || {
         log::warn!("[托盘] 点击了重启主程序");
         // 先严格关闭所有窗口
-        {
-            let state = app_state_restart_app.lock().unwrap();
-            if let Some(ref window) = state.window {
-                log::info!("[ClassPaper] 正在关闭主窗口...");
-                window.close_blocking(3000);
-                log::info!("[ClassPaper] 主窗口已关闭");
-            }
-            for (i, win) in state.settings_windows.iter().enumerate() {
-                log::info!("[ClassPaper] 正在关闭设置窗口 {}...", i + 1);
-                win.close_blocking(3000);
-                log::info!("[ClassPaper] 设置窗口 {} 已关闭", i + 1);
-            }
+        let (windows, settings_windows) = app_state_restart_app.lock().unwrap().shut_down();
+        for window in &windows {
+            log::info!("[ClassPaper] 正在关闭主窗口 {}...", window.name);
+            window.ui.close_blocking(3000);
+            log::info!("[ClassPaper] 主窗口 {} 已关闭", window.name);
+        }
+        for (i, win) in settings_windows.iter().enumerate() {
+            log::info!("[ClassPaper] 正在关闭设置窗口 {}...", i + 1);
+            win.ui.close_blocking(3000);
+            log::info!("[ClassPaper] 设置窗口 {} 已关闭", i + 1);
         }
         // 确保所有窗口都关闭后，再重启程序
         if let Ok(exec_path) = std::env::current_exe() {
@@ -649,34 +1461,130 @@ fn main() -> std::io::Result<()> {
         }
         std::process::exit(0);
     }).expect("无法添加重启程序菜单项");
-    let app_state_quit = Arc::clone(&app_state);
+    let app_state_quit = Arc::clone(app_state);
     let app_state_quit2 = Arc::clone(&app_state_quit);
     tray.add_menu_item("退出程序", move || {
         log::warn!("[托盘] 点击了退出程序");
         close_all_and_exit(&app_state_quit2);
     })
     .expect("无法添加退出菜单项");
-    let window = match create_window(
-        &url,
-        &window_name,
-        &config.default.browser_path,
-    ) {
-        Ok(ui) => Arc::new(ui),
-        Err(_) => {
-            // 错误已经在create_window中处理了
+    Some((tray, status_label, interaction_item))
+}
+
+fn main() -> std::io::Result<()> {
+    // 先确定是否无界面运行，日志初始化失败时的提示方式取决于它
+    if std::env::args().skip(1).any(|arg| arg == "--headless") {
+        HEADLESS.store(true, Ordering::Relaxed);
+    }
+    platform::init(headless());
+    // 日志初始化增强（美化格式/本地时间/分级/彩色/线程/文件/行号）
+    let mut builder = ConfigBuilder::new();
+    builder.set_thread_mode(ThreadLogMode::Both);
+    builder.set_thread_level(LevelFilter::Info);
+    builder.set_location_level(LevelFilter::Debug);
+    builder.set_level_padding(LevelPadding::Right);
+    builder.set_time_offset_to_local().ok();
+    let log_config = builder.build();
+    let log_file = std::fs::OpenOptions::new().create(true).append(true).open("app.log").unwrap_or_else(|e| {
+        eprintln!("[日志] 无法打开 app.log: {}，日志将输出到 stderr/nul", e);
+        platform::backend().show_error(&format!("日志文件创建失败\n\n无法创建或写入日志文件 app.log\n\n错误信息: {}\n\n程序将继续运行，但日志将不会保存到文件。", e));
+        #[cfg(windows)]
+        { std::fs::OpenOptions::new().write(true).open("nul").unwrap() }
+        #[cfg(not(windows))]
+        { std::fs::File::create("/dev/stderr").unwrap() }
+    });
+    let log_level = std::env::var("RUST_LOG").ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(LevelFilter::Info);
+    if let Err(e) = CombinedLogger::init(vec![
+        WriteLogger::new(log_level, log_config.clone(), log_file),
+        #[cfg(debug_assertions)]
+        TermLogger::new(LevelFilter::Debug, log_config, TerminalMode::Mixed, ColorChoice::Auto),
+    ]) {
+        log::error!("[ClassPaper] 日志系统初始化失败: {}", e);
+        platform::backend().show_error(&format!("日志系统初始化失败\n\n无法初始化日志系统\n\n错误信息: {}\n\n程序将继续运行，但日志功能可能受限。", e));
+    }
+    log::info!("[ClassPaper] 日志系统初始化完成，日志级别: {:?}", log_level);
+    if headless() {
+        log::info!("[ClassPaper] 以无界面模式运行，不创建托盘和桌面穿透");
+    }
+    // DPI感知
+    if platform::backend().set_dpi_aware() {
+        log::info!("[ClassPaper] DPI 感知已设置");
+    }
+    let config = parse_config();
+    let app_state = Arc::new(Mutex::new(AppState {
+        windows: Vec::new(),
+        window_prefix: format!("classpaper{}", generate_random_string(6)),
+        window_serial: 0,
+        settings_windows: Vec::new(),
+        widget: config.widget.enabled,
+        shutting_down: false,
+    }));
+    bindings::init(Arc::clone(&app_state));
+    // ctrlc 优雅退出
+    let app_state_ctrlc = Arc::clone(&app_state);
+    let app_state_ctrlc2 = Arc::clone(&app_state_ctrlc);
+    if let Err(e) = ctrlc::set_handler(move || {
+        log::warn!("[ClassPaper] 收到 Ctrl+C 信号，准备优雅退出");
+        close_all_and_exit(&app_state_ctrlc2);
+    }) {
+        log::error!("[ClassPaper] 设置 Ctrl+C 处理程序失败: {}", e);
+        platform::backend().show_error(&format!("信号处理设置失败\n\n无法设置程序退出信号处理\n\n错误信息: {}\n\n程序仍可正常运行，但可能无法优雅退出。", e));
+    }
+    if headless() {
+        let names = create_main_windows(&app_state, &config, platform::monitors());
+        if names.is_empty() {
             std::process::exit(1);
         }
+        for name in names {
+            start_browser_supervisor(Arc::clone(&app_state), name);
+        }
+        start_display_watcher(Arc::clone(&app_state));
+        lesson_notifications::start(&config.notifications);
+        std::thread::park();
+        close_all_and_exit(&app_state);
+    }
+    #[cfg(windows)]
+    let (mut tray, status_label, interaction_item) = match setup_tray(&app_state) {
+        Some(tray) => tray,
+        None => return Ok(()),
     };
-    log::info!("[ClassPaper] 主窗口已创建: {}", window_name);
-    let mut state = app_state.lock().unwrap();
-    state.window = Some(window);
-    drop(state);
+    // 桌面嵌入状态和交互方式由主线程显示在托盘菜单中
+    let (tray_tx, tray_rx) = mpsc::channel::<TrayUpdate>();
+    let embed_tx = tray_tx.clone();
+    embed_health::init(Arc::clone(&app_state), move |status| {
+        let _ = embed_tx.send(TrayUpdate::Embed(status.clone()));
+    });
+    let interaction_tx = Mutex::new(tray_tx);
+    interaction::init(Arc::clone(&app_state), config.interaction.mode, &config.interaction.hotkey, move |label| {
+        let _ = interaction_tx.lock().unwrap().send(TrayUpdate::Interaction(label.to_string()));
+    });
+    let names = create_main_windows(&app_state, &config, platform::monitors());
+    if names.is_empty() {
+        // 错误已经在create_window中处理了
+        std::process::exit(1);
+    }
     thread::sleep(std::time::Duration::from_millis(300));
-    let state = app_state.lock().unwrap();
-    winapi::setup_desktop_penetration(&state.window_name);
-    drop(state);
+    show_main_windows(&app_state, &names, &config);
     log::info!("[ClassPaper] 桌面穿透已设置");
-    std::thread::park();
+    start_display_watcher(Arc::clone(&app_state));
+    lesson_notifications::start(&config.notifications);
+    #[cfg(windows)]
+    for update in tray_rx {
+        let result = match &update {
+            TrayUpdate::Embed(status) => tray.inner_mut().set_label(&status.label(), status_label),
+            TrayUpdate::Interaction(label) => tray.inner_mut().set_menu_item_label(label, interaction_item),
+        };
+        if let Err(e) = result {
+            log::warn!("[托盘] 更新菜单文字失败: {}", e);
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        drop(tray_rx);
+        std::thread::park();
+    }
     log::info!("[ClassPaper] 主线程即将退出，准备关闭所有窗口");
     close_all_and_exit(&app_state);
 }
@@ -688,4 +1596,56 @@ fn main() -> std::io::Result<()> {
 // Logger::try_with_str("info")?.log_to_file().directory("log_files").rotate(
 //     Criterion::Size(10_000_000), Naming::Numbers, Cleanup::KeepLogFiles(7),
 // ).start()?;
-// ---
\ No newline at end of file
+// ---
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // 每个测试使用自己的临时目录
+    pub(crate) fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("classpaper-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // 以无界面方式启动主窗口，没有安装浏览器时返回 None 跳过测试
+    pub(crate) fn launch_main(url: &str, config: &AppConfig) -> Option<UI> {
+        HEADLESS.store(true, Ordering::Relaxed);
+        let monitor = Monitor {
+            id: "test".to_string(),
+            x: 0,
+            y: 0,
+            width: 800,
+            height: 600,
+            primary: true,
+        };
+        test_util::launched(launch_window(url, "[classpaper-test-1]", config, &monitor, false))
+    }
+
+    // 等待页面文字等于 text
+    pub(crate) fn wait_for_body(ui: &UI, text: &str) {
+        test_util::wait_for(ui, "document.body ? document.body.textContent : ''", |v| v.as_str() == Some(text));
+    }
+
+    // 请求拦截在第一次跳转之前设置，[ResourceMap] 对启动时加载的页面本身也生效
+    #[test]
+    fn test_resource_map_first_load() {
+        let dir = temp_dir("resource-map");
+        let page = dir.join("index.html");
+        std::fs::write(&page, "<html><body>本地页面</body></html>").unwrap();
+        let mut config = AppConfig::default();
+        config.offline_cache.enabled = false;
+        config
+            .resource_map
+            .insert("http://classpaper.invalid/*".to_string(), page.to_string_lossy().into_owned());
+
+        let Some(ui) = launch_main("http://classpaper.invalid/index.html", &config) else {
+            return;
+        };
+        wait_for_body(&ui, "本地页面");
+        ui.close();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,243 @@
+// 远程壁纸页面的离线缓存：把从网络成功加载的响应保存到本地目录，学校网络断开时用缓存应答，
+// 避免桌面变成空白。缓存状态通过 getOfflineStatus 绑定提供给页面显示
+
+use alcro::{InterceptAction, InterceptedRequest};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+// 与网页内容一起保存的响应信息
+#[derive(Serialize, Deserialize)]
+struct CacheMeta {
+    url: String,
+    content_type: Option<String>,
+    // 保存时间，毫秒时间戳
+    saved_at: i64,
+}
+
+// 提供给页面的缓存状态
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OfflineStatus {
+    pub enabled: bool,
+    // 最近一次远程请求是否成功
+    pub online: bool,
+    // 最近一次从网络成功加载的时间，毫秒时间戳
+    pub last_online: Option<i64>,
+    // 最近一次网络错误，例如 InternetDisconnected
+    pub last_error: Option<String>,
+    // 断网期间用缓存应答的请求数
+    pub served_from_cache: u64,
+    // 断网期间没有缓存可用的请求数
+    pub missed: u64,
+    // 最近一次用缓存应答的内容的保存时间，毫秒时间戳
+    pub cached_at: Option<i64>,
+}
+
+static STATUS: Lazy<Mutex<OfflineStatus>> = Lazy::new(|| Mutex::new(OfflineStatus::default()));
+
+pub fn status() -> OfflineStatus {
+    STATUS.lock().unwrap().clone()
+}
+
+pub struct OfflineCache {
+    dir: PathBuf,
+    // 缓存内容的总大小上限，单位字节，0 表示不限制
+    max_size: u64,
+}
+
+impl OfflineCache {
+    pub fn new(dir: &str, max_size: u64) -> Self {
+        STATUS.lock().unwrap().enabled = true;
+        Self {
+            dir: PathBuf::from(dir),
+            max_size,
+        }
+    }
+
+    // 缓存文件名：去掉 # 之后部分的 URL 的 FNV-1a 哈希，保证不同 Rust 版本下一致
+    fn key(url: &str) -> String {
+        let url = url.split('#').next().unwrap_or(url);
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in url.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        format!("{:016x}", hash)
+    }
+
+    fn store(&self, url: &str, content_type: Option<&str>, body: &[u8]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let key = Self::key(url);
+        let meta = CacheMeta {
+            url: url.to_string(),
+            content_type: content_type.map(str::to_string),
+            saved_at: chrono::Local::now().timestamp_millis(),
+        };
+        // 先写临时文件再改名，程序在写入时退出也不会留下不完整的缓存
+        let body_path = self.dir.join(format!("{}.body", key));
+        let tmp_path = self.dir.join(format!("{}.tmp", key));
+        std::fs::write(&tmp_path, body)?;
+        std::fs::rename(&tmp_path, &body_path)?;
+        let meta = serde_json::to_string(&meta).map_err(std::io::Error::other)?;
+        std::fs::write(self.dir.join(format!("{}.json", key)), meta)
+    }
+
+    // 缓存内容超过 max_size 时按保存时间从早到晚删除，直到不超过
+    fn evict(&self) -> std::io::Result<()> {
+        if self.max_size == 0 {
+            return Ok(());
+        }
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "body") {
+                continue;
+            }
+            let metadata = std::fs::metadata(&path)?;
+            entries.push((metadata.modified()?, metadata.len(), path));
+        }
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_size {
+                break;
+            }
+            // 先删除响应信息，删除一半时 load 不会用到没有正文的缓存
+            let _ = std::fs::remove_file(path.with_extension("json"));
+            std::fs::remove_file(&path)?;
+            total -= len;
+            log::debug!("[ClassPaper] 离线缓存超过上限，删除 {}", path.display());
+        }
+        Ok(())
+    }
+
+    fn load(&self, url: &str) -> Option<(CacheMeta, Vec<u8>)> {
+        let key = Self::key(url);
+        let meta = std::fs::read_to_string(self.dir.join(format!("{}.json", key))).ok()?;
+        let meta: CacheMeta = serde_json::from_str(&meta).ok()?;
+        // 哈希冲突时不使用别的页面的缓存
+        if meta.url.split('#').next() != url.split('#').next() {
+            return None;
+        }
+        let body = std::fs::read(self.dir.join(format!("{}.body", key))).ok()?;
+        Some((meta, body))
+    }
+
+    // 处理在响应阶段暂停的请求：成功的 GET 响应写入缓存，网络错误时用缓存应答
+    pub fn respond(&self, request: &InterceptedRequest) -> InterceptAction {
+        let Some(ref response) = request.response else {
+            return InterceptAction::Continue;
+        };
+        if request.method != "GET" {
+            return InterceptAction::Continue;
+        }
+        if let Some(ref error) = response.error {
+            // 页面跳转等主动取消的请求不是断网
+            if error == "Aborted" {
+                return InterceptAction::Continue;
+            }
+            let cached = self.load(&request.url);
+            let mut status = STATUS.lock().unwrap();
+            status.online = false;
+            status.last_error = Some(error.clone());
+            let Some((meta, body)) = cached else {
+                status.missed += 1;
+                log::warn!("[ClassPaper] 网络错误 {} 且无离线缓存: {}", error, request.url);
+                return InterceptAction::Continue;
+            };
+            status.served_from_cache += 1;
+            status.cached_at = Some(meta.saved_at);
+            drop(status);
+            log::info!("[ClassPaper] 网络错误 {}，使用离线缓存: {}", error, request.url);
+            let mut headers = vec![("Access-Control-Allow-Origin".to_string(), "*".to_string())];
+            if let Some(content_type) = meta.content_type {
+                headers.push(("Content-Type".to_string(), content_type));
+            }
+            return InterceptAction::Fulfill {
+                status: 200,
+                headers,
+                body,
+            };
+        }
+        if let (Some(200), Some(ref body)) = (response.status, &response.body) {
+            // 保存的是浏览器解压后的内容，只保留 Content-Type，不能带上 Content-Encoding 等头
+            let content_type = response
+                .headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+                .map(|(_, value)| value.as_str());
+            // 单个响应超过上限时不缓存，否则会把其他内容全部挤掉
+            if self.max_size == 0 || (body.len() as u64) <= self.max_size {
+                if let Err(e) = self.store(&request.url, content_type, body).and_then(|()| self.evict()) {
+                    log::warn!("[ClassPaper] 写入离线缓存失败 {}: {}", request.url, e);
+                }
+            }
+        }
+        let mut status = STATUS.lock().unwrap();
+        if !status.online && status.last_online.is_some() {
+            log::info!("[ClassPaper] 网络已恢复");
+        }
+        status.online = true;
+        status.last_online = Some(chrono::Local::now().timestamp_millis());
+        InterceptAction::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{launch_main, temp_dir, wait_for_body};
+    use crate::AppConfig;
+    use std::time::{Duration, SystemTime};
+
+    // 把 url 的缓存标记为 seconds 秒前保存
+    fn age(cache: &OfflineCache, url: &str, seconds: u64) {
+        let path = cache.dir.join(format!("{}.body", OfflineCache::key(url)));
+        let file = std::fs::File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - Duration::from_secs(seconds)).unwrap();
+    }
+
+    #[test]
+    fn test_evict() {
+        let dir = temp_dir("offline-evict");
+        let cache = OfflineCache::new(dir.to_str().unwrap(), 25);
+        cache.store("http://example.com/a", None, &[0; 10]).unwrap();
+        age(&cache, "http://example.com/a", 30);
+        cache.store("http://example.com/b", None, &[0; 10]).unwrap();
+        age(&cache, "http://example.com/b", 20);
+        cache.evict().unwrap();
+        assert!(cache.load("http://example.com/a").is_some());
+
+        // 超过上限时删除最早保存的 a
+        cache.store("http://example.com/c", Some("text/css"), &[0; 10]).unwrap();
+        cache.evict().unwrap();
+        assert!(cache.load("http://example.com/a").is_none());
+        assert!(!dir.join(format!("{}.json", OfflineCache::key("http://example.com/a"))).exists());
+        assert!(cache.load("http://example.com/b").is_some());
+        assert_eq!(cache.load("http://example.com/c").unwrap().1, vec![0; 10]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    // 断网时冷启动：第一次加载就用缓存应答，桌面不会空白
+    #[test]
+    fn test_offline_first_load() {
+        let dir = temp_dir("offline-first-load");
+        let url = "http://classpaper.invalid/index.html";
+        let cache = OfflineCache::new(dir.to_str().unwrap(), 0);
+        cache
+            .store(url, Some("text/html; charset=utf-8"), "<html><body>缓存的页面</body></html>".as_bytes())
+            .unwrap();
+        let mut config = AppConfig::default();
+        config.offline_cache.directory = dir.to_string_lossy().into_owned();
+
+        let Some(ui) = launch_main(url, &config) else {
+            return;
+        };
+        wait_for_body(&ui, "缓存的页面");
+        assert!(status().served_from_cache > 0);
+        ui.close();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
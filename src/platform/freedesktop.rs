@@ -0,0 +1,140 @@
+// freedesktop 桌面通知（org.freedesktop.Notifications 的 Notify 方法），X11 和 Wayland 共用。
+// 会话总线的连接在第一次通知时建立并一直保留；通知服务没有响应时等待 NOTIFY_TIMEOUT 后放弃
+
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::time::Duration;
+use zbus::blocking::{connection, Connection};
+use zbus::zvariant::Value;
+
+const APP_NAME: &str = "ClassPaper";
+const SERVICE: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(5);
+// 通知的紧急程度：0 低、1 普通、2 紧急，紧急的通知不会自动消失
+pub const URGENCY_NORMAL: u8 = 1;
+pub const URGENCY_CRITICAL: u8 = 2;
+// 由通知服务决定显示多久
+const DEFAULT_EXPIRE: i32 = -1;
+
+static SESSION: OnceCell<Connection> = OnceCell::new();
+
+pub fn notify(summary: &str, body: &str, urgency: u8) -> Result<(), String> {
+    let connection = SESSION.get_or_try_init(|| {
+        connection::Builder::session()
+            .and_then(|builder| builder.method_timeout(NOTIFY_TIMEOUT).build())
+            .map_err(|e| format!("无法连接 D-Bus 会话总线: {}", e))
+    })?;
+    let id = send(connection, summary, body, urgency)?;
+    log::debug!("[ClassPaper] 已发送桌面通知 {}: {}", id, summary);
+    Ok(())
+}
+
+// 发送一条通知，返回通知服务分配的 id
+fn send(connection: &Connection, summary: &str, body: &str, urgency: u8) -> Result<u32, String> {
+    let hints = HashMap::from([("urgency", Value::U8(urgency))]);
+    let actions: Vec<&str> = Vec::new();
+    let reply = connection
+        .call_method(
+            Some(SERVICE),
+            PATH,
+            Some(SERVICE),
+            "Notify",
+            &(APP_NAME, 0u32, "", summary, body, actions, hints, DEFAULT_EXPIRE),
+        )
+        .map_err(|e| format!("发送桌面通知失败: {}", e))?;
+    reply
+        .body()
+        .deserialize::<u32>()
+        .map_err(|e| format!("通知服务的应答无法识别: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use zbus::zvariant::OwnedValue;
+    use zbus::Guid;
+
+    // 收到的通知 (程序名, 标题, 正文, 紧急程度)
+    type Received = Arc<Mutex<Vec<(String, String, String, Option<u8>)>>>;
+
+    struct MockServer {
+        received: Received,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl MockServer {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            app_name: String,
+            _replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            body: String,
+            _actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let urgency = hints.get("urgency").and_then(|v| u8::try_from(v).ok());
+            let mut received = self.received.lock().unwrap();
+            received.push((app_name, summary, body, urgency));
+            received.len() as u32
+        }
+    }
+
+    // 在一对本地套接字上建立点对点连接，一端提供模拟的通知服务，不需要会话总线
+    fn connect(received: Received) -> (Connection, Connection) {
+        let (server_stream, client_stream) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || {
+            connection::Builder::async_io_unix_stream(server_stream)
+                .server(Guid::generate())
+                .unwrap()
+                .p2p()
+                .serve_at(PATH, MockServer { received })
+                .unwrap()
+                .build()
+                .unwrap()
+        });
+        let client = connection::Builder::async_io_unix_stream(client_stream)
+            .p2p()
+            .method_timeout(NOTIFY_TIMEOUT)
+            .build()
+            .unwrap();
+        (server.join().unwrap(), client)
+    }
+
+    #[test]
+    fn test_notify() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let (_server, client) = connect(Arc::clone(&received));
+
+        assert_eq!(send(&client, "数学 2 分钟后上课", "第 3 节 08:50-09:30", URGENCY_NORMAL).unwrap(), 1);
+        assert_eq!(send(&client, "ClassPaper 错误", "无法创建窗口", URGENCY_CRITICAL).unwrap(), 2);
+
+        let received = received.lock().unwrap();
+        assert_eq!(
+            received[0],
+            (
+                APP_NAME.to_string(),
+                "数学 2 分钟后上课".to_string(),
+                "第 3 节 08:50-09:30".to_string(),
+                Some(URGENCY_NORMAL)
+            )
+        );
+        assert_eq!(received[1].1, "ClassPaper 错误");
+        assert_eq!(received[1].3, Some(URGENCY_CRITICAL));
+    }
+
+    #[test]
+    fn test_notify_without_server() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let (server, client) = connect(received);
+        drop(server);
+
+        assert!(send(&client, "数学 上课了", "", URGENCY_NORMAL).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+// 无界面实现：不嵌入桌面，错误只写入日志，供 --headless、自动化测试和暂不支持的平台使用
+
+use super::{DesktopBackend, Monitor, WidgetStyle};
+use alcro::UI;
+
+// 无法获取屏幕尺寸时页面使用的默认分辨率
+pub(super) const DEFAULT_SCREEN_SIZE: (i32, i32) = (1920, 1080);
+
+pub struct Headless;
+
+impl DesktopBackend for Headless {
+    fn name(&self) -> &'static str {
+        "Headless"
+    }
+
+    fn screen_size(&self) -> (i32, i32) {
+        DEFAULT_SCREEN_SIZE
+    }
+
+    fn set_dpi_aware(&self) -> bool {
+        false
+    }
+
+    fn embed_as_wallpaper(&self, _ui: &UI, window_name: &str, monitor: &Monitor) -> Result<(), String> {
+        log::debug!("[ClassPaper] 无界面模式，跳过桌面嵌入: {} ({})", window_name, monitor.id);
+        Ok(())
+    }
+
+    fn show_as_widget(&self, _ui: &UI, window_name: &str, _style: &WidgetStyle) -> Result<(), String> {
+        log::debug!("[ClassPaper] 无界面模式，跳过小窗口设置: {}", window_name);
+        Ok(())
+    }
+
+    fn notify(&self, summary: &str, body: &str) -> Result<(), String> {
+        log::info!("[ClassPaper] 无界面模式，通知只写入日志: {} {}", summary, body);
+        Ok(())
+    }
+
+    fn show_error(&self, message: &str) {
+        log::error!("[ClassPaper] {}", message.replace("\n\n", " ").replace('\n', " "));
+    }
+}
@@ -0,0 +1,391 @@
+// 桌面平台抽象：显示器和屏幕尺寸、把浏览器窗口嵌入桌面作为壁纸、错误提示和 DPI 设置。
+// 启动时按运行环境选择一个实现，其余代码只通过 backend() 调用，不直接依赖 Win32 等平台接口
+
+#[cfg(target_os = "linux")]
+mod freedesktop;
+mod headless;
+#[cfg(windows)]
+mod windows;
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(target_os = "linux")]
+mod x11;
+
+use alcro::{JSError, UI};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// 显示器在整个桌面中的位置和大小，单位为像素；id 是系统给出的名称（如 \\.\DISPLAY2、HDMI-A-1），
+// 配置文件的 [Monitors] 段用它指定每个显示器显示的页面
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Monitor {
+    pub id: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub primary: bool,
+}
+
+// 显示器变化的回调，在平台实现的事件线程中调用，不应阻塞
+pub type DisplayListener = Box<dyn Fn() + Send + Sync>;
+
+// 可能使桌面嵌入失效的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbedEvent {
+    // 桌面外壳（资源管理器、窗口管理器）重新启动，之前的嵌入全部失效
+    ShellRestarted,
+    // 嵌入的窗口被移出桌面层或反复被其他窗口遮挡，无法就地修复
+    EmbedLost,
+}
+
+// 嵌入事件的回调，在平台实现的事件线程中调用，不应阻塞
+pub type EmbedListener = Box<dyn Fn(EmbedEvent) + Send + Sync>;
+
+// 全局热键的回调，在平台实现的事件线程中调用，不应阻塞
+pub type HotkeyListener = Box<dyn Fn() + Send + Sync>;
+
+// 全局热键的主键：字母、数字或 F1–F12
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    // 大写字母或数字
+    Char(char),
+    Function(u8),
+}
+
+// 全局热键，配置中写作 "Ctrl+Alt+W" 这样的形式，修饰键不区分大小写
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hotkey {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    // Windows 徽标键或 Super 键
+    pub super_key: bool,
+    pub key: Key,
+}
+
+impl FromStr for Hotkey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mut ctrl, mut alt, mut shift, mut super_key, mut key) = (false, false, false, false, None);
+        for part in s.split('+').map(str::trim) {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => ctrl = true,
+                "alt" => alt = true,
+                "shift" => shift = true,
+                "super" | "win" | "meta" => super_key = true,
+                name if key.is_none() => {
+                    key = match name.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                        Some(n @ 1..=12) => Some(Key::Function(n)),
+                        _ if name.len() == 1 && name.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                            name.chars().next().map(|c| Key::Char(c.to_ascii_uppercase()))
+                        }
+                        _ => return Err(format!("无法识别热键 {} 中的按键 {}", s, part)),
+                    }
+                }
+                _ => return Err(format!("热键 {} 只能有一个主键", s)),
+            }
+        }
+        let Some(key) = key else {
+            return Err(format!("热键 {} 缺少主键", s));
+        };
+        // 没有修饰键的热键会占用普通按键
+        if !(ctrl || alt || super_key) {
+            return Err(format!("热键 {} 需要包含 Ctrl、Alt 或 Super", s));
+        }
+        Ok(Hotkey {
+            ctrl,
+            alt,
+            shift,
+            super_key,
+            key,
+        })
+    }
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (held, name) in [(self.ctrl, "Ctrl+"), (self.alt, "Alt+"), (self.shift, "Shift+"), (self.super_key, "Super+")] {
+            if held {
+                f.write_str(name)?;
+            }
+        }
+        match self.key {
+            Key::Char(c) => write!(f, "{}", c),
+            Key::Function(n) => write!(f, "F{}", n),
+        }
+    }
+}
+
+// 小窗口模式下窗口的外观
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WidgetStyle {
+    // 不透明度，1.0 为完全不透明
+    pub opacity: f64,
+    pub always_on_top: bool,
+}
+
+pub trait DesktopBackend: Send + Sync {
+    // 日志中显示的实现名称
+    fn name(&self) -> &'static str;
+
+    // 主屏幕的宽和高，单位为像素
+    fn screen_size(&self) -> (i32, i32);
+
+    // 所有显示器；无法枚举时只有一个与主屏幕同样大小的显示器
+    fn monitors(&self) -> Vec<Monitor> {
+        vec![single_monitor(self.screen_size())]
+    }
+
+    // 声明程序自行处理 DPI 缩放，返回是否成功
+    fn set_dpi_aware(&self) -> bool;
+
+    // 浏览器是否以无界面方式运行，由实现截取页面画面自行显示（不能摆放浏览器窗口的平台，如 Wayland）
+    fn renders_offscreen(&self) -> bool {
+        false
+    }
+
+    // 把显示 ui、标题为 window_name 的浏览器窗口嵌入 monitor 的桌面图标之下并铺满；
+    // 失败时返回给用户看的说明，由调用方决定是否提示（重试期间只记录日志）
+    fn embed_as_wallpaper(&self, ui: &UI, window_name: &str, monitor: &Monitor) -> Result<(), String>;
+
+    // 显示器的位置或大小改变后，让已嵌入的窗口重新铺满 monitor；默认重新嵌入
+    fn fit_to_monitor(&self, ui: &UI, window_name: &str, monitor: &Monitor) -> Result<(), String> {
+        self.embed_as_wallpaper(ui, window_name, monitor)
+    }
+
+    // 主窗口因显示器移除或不再显示页面而关闭后，清理实现为它创建的资源；默认无需处理
+    fn remove_wallpaper(&self, _window_name: &str, _monitor: &Monitor) {}
+
+    // 显示器接入、移除或分辨率、DPI 改变时调用 listener，短时间内可能连续调用多次；默认不检测
+    fn watch_displays(&self, _listener: DisplayListener) {}
+
+    // 桌面外壳重启或嵌入失效时调用 listener；默认不检测
+    fn watch_embed(&self, _listener: EmbedListener) {}
+
+    // 设置已嵌入的主窗口是否接收鼠标和键盘，false 时点击穿透到桌面；嵌入时实现按 interactive() 设置。
+    // 不支持时返回说明
+    fn set_interactive(&self, _ui: &UI, _window_name: &str, _monitor: &Monitor, _interactive: bool) -> Result<(), String> {
+        Err(format!("{} 不支持切换交互方式", self.name()))
+    }
+
+    // 把标题为 window_name 的浏览器窗口设为小窗口：去掉边框，不显示在任务栏中，按 style 置顶和设置不透明度。
+    // 位置和大小由调用方通过 UI::set_bounds 设置；不支持时返回说明，窗口保持为普通窗口
+    fn show_as_widget(&self, _ui: &UI, _window_name: &str, _style: &WidgetStyle) -> Result<(), String> {
+        Err(format!("{} 不支持小窗口模式", self.name()))
+    }
+
+    // 注册全局热键，按下时调用 listener；不支持或热键已被占用时返回说明
+    fn watch_hotkey(&self, _hotkey: &Hotkey, _listener: HotkeyListener) -> Result<(), String> {
+        Err(format!("{} 不支持全局热键", self.name()))
+    }
+
+    // 显示一条系统通知，summary 为标题、body 为正文；不支持或通知服务不可用时返回说明
+    fn notify(&self, _summary: &str, _body: &str) -> Result<(), String> {
+        Err(format!("{} 不支持系统通知", self.name()))
+    }
+
+    // 向用户显示错误信息
+    fn show_error(&self, message: &str);
+
+    fn handle_window_creation_error(&self, error: &str) {
+        let error_message = format!(
+            "无法创建alcro窗口:\n{}\n\n可能的原因:\n- Chrome/Edge浏览器未安装\n- 浏览器路径配置错误\n- 系统资源不足\n- 权限问题",
+            error
+        );
+        log::error!("[ClassPaper] {}", error_message);
+        self.show_error(&error_message);
+    }
+}
+
+static BACKEND: OnceCell<Box<dyn DesktopBackend>> = OnceCell::new();
+
+// 主窗口当前是否接收输入，新嵌入的窗口按它设置
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn interactive() -> bool {
+    INTERACTIVE.load(Ordering::Relaxed)
+}
+
+// 只改变之后嵌入的窗口，已嵌入的窗口由调用方通过 DesktopBackend::set_interactive 逐个设置
+pub fn set_interactive(interactive: bool) {
+    INTERACTIVE.store(interactive, Ordering::Relaxed);
+}
+
+// 平台实现按标题查找浏览器窗口。页面的 <title>、脚本修改标题和刷新、跳转都会改掉它，
+// 所以在每个文档中都设置一次，并监视之后的修改，改掉时立即改回 window_name
+pub fn keep_window_title(ui: &UI, window_name: &str) -> Result<(), JSError> {
+    let title = serde_json::to_string(window_name).unwrap_or_default();
+    ui.load_js(&format!(
+        r#"(() => {{
+    const title = {};
+    const apply = () => {{
+        if (document.title !== title) document.title = title;
+    }};
+    new MutationObserver(apply).observe(document, {{ subtree: true, childList: true, characterData: true }});
+    document.addEventListener("DOMContentLoaded", apply);
+    apply();
+}})();"#,
+        title
+    ))
+}
+
+// 选择平台实现：无界面模式或没有对应实现的平台使用只记录日志的 Headless
+pub fn init(headless: bool) -> &'static dyn DesktopBackend {
+    BACKEND
+        .get_or_init(|| {
+            let backend = select(headless);
+            log::info!("[ClassPaper] 桌面平台: {}", backend.name());
+            backend
+        })
+        .as_ref()
+}
+
+#[cfg(windows)]
+fn select(headless: bool) -> Box<dyn DesktopBackend> {
+    if headless {
+        Box::new(headless::Headless)
+    } else {
+        Box::new(windows::Win32)
+    }
+}
+
+// Linux 桌面：Wayland 会话优先使用背景层，合成器不支持时退回 XWayland 上的 X11
+#[cfg(target_os = "linux")]
+fn select(headless: bool) -> Box<dyn DesktopBackend> {
+    if headless {
+        return Box::new(headless::Headless);
+    }
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        if let Some(wayland) = wayland::Wayland::connect() {
+            return Box::new(wayland);
+        }
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        if let Some(x11) = x11::X11::connect() {
+            return Box::new(x11);
+        }
+    }
+    log::warn!("[ClassPaper] 未找到可用的桌面环境，窗口将以普通全屏窗口显示");
+    Box::new(headless::Headless)
+}
+
+// Linux 上通过桌面通知显示错误，通知服务不可用时只记录日志
+#[cfg(target_os = "linux")]
+fn notify_error(message: &str) {
+    log::error!("[ClassPaper] {}", message.replace("\n\n", " ").replace('\n', " "));
+    if let Err(e) = freedesktop::notify("ClassPaper 错误", message, freedesktop::URGENCY_CRITICAL) {
+        log::warn!("[ClassPaper] 无法显示错误提示: {}", e);
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn select(headless: bool) -> Box<dyn DesktopBackend> {
+    if !headless {
+        log::warn!("[ClassPaper] 当前平台不支持嵌入桌面，窗口将以普通全屏窗口显示");
+    }
+    Box::new(headless::Headless)
+}
+
+// 当前的显示器，主显示器排在最前；实现没有枚举到任何显示器时按主屏幕尺寸给出一个
+pub fn monitors() -> Vec<Monitor> {
+    let mut monitors = backend().monitors();
+    if monitors.is_empty() {
+        monitors.push(single_monitor(backend().screen_size()));
+    }
+    monitors.sort_by_key(|monitor| !monitor.primary);
+    monitors
+}
+
+fn single_monitor((width, height): (i32, i32)) -> Monitor {
+    Monitor {
+        id: "default".to_string(),
+        x: 0,
+        y: 0,
+        width,
+        height,
+        primary: true,
+    }
+}
+
+// 未调用 init 时（例如日志初始化失败需要提示）按有界面模式选择
+pub fn backend() -> &'static dyn DesktopBackend {
+    match BACKEND.get() {
+        Some(backend) => backend.as_ref(),
+        None => init(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{launch, wait_for};
+    use alcro::Content;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_hotkey() {
+        let hotkey: Hotkey = "Ctrl+Alt+W".parse().unwrap();
+        assert_eq!(
+            hotkey,
+            Hotkey {
+                ctrl: true,
+                alt: true,
+                shift: false,
+                super_key: false,
+                key: Key::Char('W'),
+            }
+        );
+        // 修饰键不区分大小写、可有空格，主键可以在任意位置
+        let hotkey: Hotkey = " shift + f12 + WIN ".parse().unwrap();
+        assert!(hotkey.shift && hotkey.super_key && !hotkey.ctrl && !hotkey.alt);
+        assert_eq!(hotkey.key, Key::Function(12));
+        assert_eq!(hotkey.to_string(), "Shift+Super+F12");
+        assert_eq!("control+meta+7".parse::<Hotkey>().unwrap().to_string(), "Ctrl+Super+7");
+        assert_eq!("Alt+F1".parse::<Hotkey>().unwrap().key, Key::Function(1));
+    }
+
+    #[test]
+    fn test_parse_hotkey_errors() {
+        let error = |s: &str| s.parse::<Hotkey>().unwrap_err();
+        assert_eq!(error("Ctrl+Alt"), "热键 Ctrl+Alt 缺少主键");
+        assert!("".parse::<Hotkey>().is_err());
+        assert_eq!(error("Ctrl+A+B"), "热键 Ctrl+A+B 只能有一个主键");
+        assert_eq!(error("Ctrl+F13"), "无法识别热键 Ctrl+F13 中的按键 F13");
+        assert_eq!(error("Ctrl+F0"), "无法识别热键 Ctrl+F0 中的按键 F0");
+        assert_eq!(error("Ctrl+Space"), "无法识别热键 Ctrl+Space 中的按键 Space");
+        assert_eq!(error("Ctrl+é"), "无法识别热键 Ctrl+é 中的按键 é");
+        assert_eq!(error("Shift+W"), "热键 Shift+W 需要包含 Ctrl、Alt 或 Super");
+        assert_eq!(error("W"), "热键 W 需要包含 Ctrl、Alt 或 Super");
+    }
+
+    #[test]
+    fn test_keep_window_title() {
+        let Some(ui) = launch() else {
+            return;
+        };
+        ui.load(Content::Html("<html><head><title>背景课表</title></head><body></body></html>"))
+            .unwrap();
+        let title = "[classpaper'\"-1]";
+        keep_window_title(&ui, title).unwrap();
+        wait_for(&ui, "document.title", |v| v.as_str() == Some(title));
+
+        // 页面脚本修改标题
+        ui.eval("document.title = '课表'").unwrap();
+        wait_for(&ui, "document.title", |v| v.as_str() == Some(title));
+
+        // 刷新后页面的 <title> 重新生效
+        ui.reload(Duration::from_secs(5)).unwrap();
+        wait_for(&ui, "document.title", |v| v.as_str() == Some(title));
+
+        // 跳转到另一个页面
+        ui.load(Content::Html("<html><head><title>设置</title></head><body></body></html>"))
+            .unwrap();
+        wait_for(&ui, "document.title", |v| v.as_str() == Some(title));
+    }
+}
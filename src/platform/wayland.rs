@@ -0,0 +1,703 @@
+// Wayland 实现：合成器不允许客户端移动或置底窗口，改为通过 wlr-layer-shell 在每个输出的背景层创建铺满的表面，
+// 浏览器以无界面方式运行，用 DevTools 截取页面画面帧绘制到对应的表面上；可交互时把表面上的鼠标事件转发给浏览器。
+// 支持 sway、Hyprland、KDE 等实现了 wlr-layer-shell 的合成器
+
+use super::headless::DEFAULT_SCREEN_SIZE;
+use super::{DesktopBackend, DisplayListener, EmbedEvent, EmbedListener, Monitor};
+use alcro::{MouseButton, MouseEvent, MouseEventKind, ScreencastFormat, ScreencastFrame, UI};
+use smithay_client_toolkit::compositor::{CompositorHandler, CompositorState, Region};
+use smithay_client_toolkit::output::{OutputHandler, OutputState};
+use smithay_client_toolkit::reexports::client::globals::registry_queue_init;
+use smithay_client_toolkit::reexports::client::protocol::{wl_output, wl_pointer, wl_seat, wl_shm, wl_surface};
+use smithay_client_toolkit::reexports::client::{Connection, QueueHandle};
+use smithay_client_toolkit::registry::{ProvidesRegistryState, RegistryState};
+use smithay_client_toolkit::seat::pointer::{PointerEvent, PointerEventKind, PointerHandler};
+use smithay_client_toolkit::seat::{Capability, SeatHandler, SeatState};
+use smithay_client_toolkit::shell::wlr_layer::{
+    Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface, LayerSurfaceConfigure,
+};
+use smithay_client_toolkit::shell::WaylandSurface;
+use smithay_client_toolkit::shm::slot::{Buffer, SlotPool};
+use smithay_client_toolkit::shm::{Shm, ShmHandler};
+use smithay_client_toolkit::{
+    delegate_compositor, delegate_layer, delegate_output, delegate_pointer, delegate_registry, delegate_seat,
+    delegate_shm, registry_handlers,
+};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// 画面帧的 JPEG 质量，壁纸大部分时间静止，取较高的质量
+const FRAME_QUALITY: u8 = 90;
+// 背景层表面的命名空间，合成器可以据此对它单独设置规则
+const NAMESPACE: &str = "classpaper";
+// 两次按下同一鼠标按键的间隔小于它时算作双击，单位为毫秒
+const DOUBLE_CLICK_MS: u32 = 400;
+
+type Error = Box<dyn std::error::Error>;
+
+// 解码后的画面帧，每个像素 3 字节 RGB
+struct Frame {
+    pixels: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+// 一个输出上的背景层表面及其缓冲区，由事件线程（configure）和画面帧线程共同使用
+struct Surface {
+    monitor_id: String,
+    layer: Option<LayerSurface>,
+    pool: SlotPool,
+    // 正在显示的缓冲区，合成器释放前不能复用
+    buffer: Option<Buffer>,
+    size: (u32, u32),
+    configured: bool,
+    // 最近一帧，表面大小改变时重新绘制
+    frame: Option<Frame>,
+    // 把表面上的鼠标事件转发给浏览器，嵌入时创建；点击穿透时表面收不到鼠标事件
+    input: Option<Sender<MouseEvent>>,
+}
+
+impl Surface {
+    // 把最近一帧缩放到表面大小后提交；收到第一个 configure 之前不能提交缓冲区
+    fn draw(&mut self) {
+        let (Some(layer), Some(frame)) = (&self.layer, &self.frame) else {
+            return;
+        };
+        let (width, height) = (self.size.0 as usize, self.size.1 as usize);
+        if !self.configured || width == 0 || height == 0 || frame.width == 0 || frame.height == 0 {
+            return;
+        }
+        let (buffer, canvas) =
+            match self
+                .pool
+                .create_buffer(width as i32, height as i32, width as i32 * 4, wl_shm::Format::Xrgb8888)
+            {
+                Ok(buffer) => buffer,
+                Err(e) => {
+                    log::warn!("[ClassPaper] 创建背景层缓冲区失败: {}", e);
+                    return;
+                }
+            };
+        // 浏览器的视口与输出大小相同时逐像素复制，否则按最近邻缩放
+        for (y, row) in canvas.chunks_exact_mut(width * 4).enumerate() {
+            let source_row = y * frame.height / height * frame.width;
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let i = (source_row + x * frame.width / width) * 3;
+                pixel.copy_from_slice(&[frame.pixels[i + 2], frame.pixels[i + 1], frame.pixels[i], 0xFF]);
+            }
+        }
+        layer.wl_surface().damage_buffer(0, 0, width as i32, height as i32);
+        if let Err(e) = buffer.attach_to(layer.wl_surface()) {
+            log::warn!("[ClassPaper] 绑定背景层缓冲区失败: {}", e);
+            return;
+        }
+        layer.commit();
+        self.buffer = Some(buffer);
+    }
+}
+
+type Surfaces = Arc<Mutex<Vec<Arc<Mutex<Surface>>>>>;
+// 当前的输出及对应的显示器信息，由事件线程在输出变化时更新
+type Outputs = Arc<Mutex<Vec<(wl_output::WlOutput, Monitor)>>>;
+// 输出变化和背景层表面被关闭时调用的回调，由 watch_displays 和 watch_embed 设置
+type Listener = Arc<Mutex<Option<DisplayListener>>>;
+type LostListener = Arc<Mutex<Option<EmbedListener>>>;
+
+// 事件线程处理 Wayland 事件时使用的状态
+struct State {
+    registry: RegistryState,
+    output_state: OutputState,
+    shm: Shm,
+    outputs: Outputs,
+    surfaces: Surfaces,
+    listener: Listener,
+    lost_listener: LostListener,
+    seat_state: SeatState,
+    pointer: Option<wl_pointer::WlPointer>,
+    // 按住的鼠标按键（DevTools 的 buttons 位），以及上一次按下的按键、时间和连击次数
+    buttons: u32,
+    last_press: Option<(u32, u32)>,
+    clicks: u32,
+}
+
+impl State {
+    // Wayland 没有主显示器的概念，以第一个输出为主显示器；没有名称的输出按全局编号命名
+    fn refresh_outputs(&mut self) {
+        let outputs = self
+            .output_state
+            .outputs()
+            .filter_map(|output| {
+                let info = self.output_state.info(&output)?;
+                let (width, height) = info.logical_size?;
+                let (x, y) = info.logical_position.unwrap_or(info.location);
+                let monitor = Monitor {
+                    id: info.name.clone().unwrap_or_else(|| format!("output-{}", info.id)),
+                    x,
+                    y,
+                    width,
+                    height,
+                    primary: false,
+                };
+                Some((output, monitor))
+            })
+            .enumerate()
+            .map(|(i, (output, monitor))| (output, Monitor { primary: i == 0, ..monitor }))
+            .collect();
+        *self.outputs.lock().unwrap() = outputs;
+    }
+
+    // 输出接入、移除或属性改变后更新显示器信息并通知
+    fn outputs_changed(&mut self) {
+        self.refresh_outputs();
+        if let Some(ref listener) = *self.listener.lock().unwrap() {
+            listener();
+        }
+    }
+
+    fn surface_of(&self, layer: &LayerSurface) -> Option<Arc<Mutex<Surface>>> {
+        self.surfaces
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|surface| surface.lock().unwrap().layer.as_ref() == Some(layer))
+            .cloned()
+    }
+
+    fn surface_with(&self, wl_surface: &wl_surface::WlSurface) -> Option<Arc<Mutex<Surface>>> {
+        self.surfaces
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|surface| {
+                surface
+                    .lock()
+                    .unwrap()
+                    .layer
+                    .as_ref()
+                    .is_some_and(|layer| layer.wl_surface() == wl_surface)
+            })
+            .cloned()
+    }
+
+    // 把一个指针事件转换为浏览器的鼠标事件，位置为表面上的逻辑像素，与页面的 CSS 像素相同
+    fn mouse_event(&mut self, event: &PointerEvent) -> Option<MouseEvent> {
+        let (x, y) = event.position;
+        let event = match event.kind {
+            PointerEventKind::Enter { .. } | PointerEventKind::Motion { .. } => MouseEvent::moved(x, y, self.buttons),
+            PointerEventKind::Leave { .. } => return None,
+            PointerEventKind::Press { time, button, .. } => {
+                let (mouse_button, bit) = mouse_button(button);
+                self.buttons |= bit;
+                self.clicks = match self.last_press {
+                    Some((last, at)) if last == button && time.wrapping_sub(at) < DOUBLE_CLICK_MS => self.clicks + 1,
+                    _ => 1,
+                };
+                self.last_press = Some((button, time));
+                MouseEvent {
+                    kind: MouseEventKind::MousePressed,
+                    button: mouse_button,
+                    click_count: self.clicks,
+                    ..MouseEvent::moved(x, y, self.buttons)
+                }
+            }
+            PointerEventKind::Release { button, .. } => {
+                let (mouse_button, bit) = mouse_button(button);
+                self.buttons &= !bit;
+                MouseEvent {
+                    kind: MouseEventKind::MouseReleased,
+                    button: mouse_button,
+                    click_count: self.clicks,
+                    ..MouseEvent::moved(x, y, self.buttons)
+                }
+            }
+            PointerEventKind::Axis {
+                horizontal, vertical, ..
+            } => MouseEvent {
+                kind: MouseEventKind::MouseWheel,
+                delta_x: horizontal.absolute,
+                delta_y: vertical.absolute,
+                ..MouseEvent::moved(x, y, self.buttons)
+            },
+        };
+        Some(event)
+    }
+}
+
+// Linux 输入事件的按键码（BTN_LEFT 等）对应的鼠标按键及其在 buttons 中的位
+fn mouse_button(code: u32) -> (MouseButton, u32) {
+    match code {
+        0x110 => (MouseButton::Left, 1),
+        0x111 => (MouseButton::Right, 2),
+        0x112 => (MouseButton::Middle, 4),
+        0x113 => (MouseButton::Back, 8),
+        0x114 => (MouseButton::Forward, 16),
+        _ => (MouseButton::None, 0),
+    }
+}
+
+pub struct Wayland {
+    conn: Connection,
+    qh: QueueHandle<State>,
+    compositor: CompositorState,
+    layer_shell: LayerShell,
+    // 与事件线程中的 Shm 是同一个全局对象，用于为新表面创建缓冲池
+    shm: Shm,
+    outputs: Outputs,
+    surfaces: Surfaces,
+    listener: Listener,
+    lost_listener: LostListener,
+}
+
+impl Wayland {
+    // 连接 WAYLAND_DISPLAY 指定的合成器，不支持 wlr-layer-shell 时返回 None，由调用方改用其他实现
+    pub fn connect() -> Option<Self> {
+        match Self::try_connect() {
+            Ok(wayland) => Some(wayland),
+            Err(e) => {
+                log::warn!("[ClassPaper] 无法使用 Wayland 背景层: {}", e);
+                None
+            }
+        }
+    }
+
+    fn try_connect() -> Result<Self, Error> {
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<State>(&conn)?;
+        let qh = queue.handle();
+        let compositor = CompositorState::bind(&globals, &qh)?;
+        let layer_shell = LayerShell::bind(&globals, &qh)?;
+        let shm = Shm::bind(&globals, &qh)?;
+        let outputs = Outputs::default();
+        let surfaces = Surfaces::default();
+        let listener = Listener::default();
+        let lost_listener = LostListener::default();
+        let mut state = State {
+            registry: RegistryState::new(&globals),
+            output_state: OutputState::new(&globals, &qh),
+            shm: Shm::from(shm.wl_shm().clone()),
+            outputs: Arc::clone(&outputs),
+            surfaces: Arc::clone(&surfaces),
+            listener: Arc::clone(&listener),
+            lost_listener: Arc::clone(&lost_listener),
+            seat_state: SeatState::new(&globals, &qh),
+            pointer: None,
+            buttons: 0,
+            last_press: None,
+            clicks: 0,
+        };
+        // 第一次往返绑定输出，第二次收到输出的大小等信息
+        queue.roundtrip(&mut state)?;
+        queue.roundtrip(&mut state)?;
+        state.refresh_outputs();
+        thread::spawn(move || loop {
+            if let Err(e) = queue.blocking_dispatch(&mut state) {
+                log::error!("[ClassPaper] 与 Wayland 合成器的连接已断开: {}", e);
+                break;
+            }
+        });
+        Ok(Self {
+            conn,
+            qh,
+            compositor,
+            layer_shell,
+            shm,
+            outputs,
+            surfaces,
+            listener,
+            lost_listener,
+        })
+    }
+
+    // 取得 monitor 上的背景层表面，第一次嵌入时创建，浏览器重启后继续使用同一个表面
+    fn ensure_layer(&self, monitor: &Monitor) -> Result<Arc<Mutex<Surface>>, Error> {
+        let mut surfaces = self.surfaces.lock().unwrap();
+        let surface = match surfaces.iter().find(|s| s.lock().unwrap().monitor_id == monitor.id) {
+            Some(surface) => Arc::clone(surface),
+            None => {
+                let (width, height) = (monitor.width.max(1) as usize, monitor.height.max(1) as usize);
+                let surface = Arc::new(Mutex::new(Surface {
+                    monitor_id: monitor.id.clone(),
+                    layer: None,
+                    pool: SlotPool::new(width * height * 4, &self.shm)?,
+                    buffer: None,
+                    size: (0, 0),
+                    configured: false,
+                    frame: None,
+                    input: None,
+                }));
+                surfaces.push(Arc::clone(&surface));
+                surface
+            }
+        };
+        drop(surfaces);
+        let mut locked = surface.lock().unwrap();
+        if locked.layer.is_some() {
+            drop(locked);
+            return Ok(surface);
+        }
+        let output = self
+            .outputs
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, m)| m.id == monitor.id)
+            .map(|(output, _)| output.clone());
+        // 输出已被移除时不创建，否则合成器会把表面放到其他输出上
+        let Some(output) = output else {
+            return Err(format!("未找到输出 {}", monitor.id).into());
+        };
+        let layer = self.layer_shell.create_layer_surface(
+            &self.qh,
+            self.compositor.create_surface(&self.qh),
+            Layer::Background,
+            Some(NAMESPACE),
+            Some(&output),
+        );
+        // 四边锚定且大小为 0，由合成器按输出大小 configure；-1 表示不避让面板，铺满整个输出
+        layer.set_anchor(Anchor::all());
+        layer.set_size(0, 0);
+        layer.set_exclusive_zone(-1);
+        layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+        layer.commit();
+        locked.layer = Some(layer);
+        locked.configured = false;
+        drop(locked);
+        Ok(surface)
+    }
+
+    // 设置背景层表面的输入区域：接收输入时为整个表面，否则为空，点击落到下面
+    fn set_input_region(&self, surface: &Surface, interactive: bool) -> Result<(), Error> {
+        let Some(layer) = &surface.layer else {
+            return Ok(());
+        };
+        if interactive {
+            layer.wl_surface().set_input_region(None);
+        } else {
+            let region = Region::new(&self.compositor)?;
+            layer.wl_surface().set_input_region(Some(region.wl_region()));
+        }
+        layer.commit();
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+// 解码画面帧并绘制到背景层表面，在 alcro 的画面帧线程中执行
+fn show_frame(surface: &Mutex<Surface>, conn: &Connection, frame: ScreencastFrame) {
+    let mut decoder = jpeg_decoder::Decoder::new(frame.data.as_slice());
+    let pixels = match decoder.decode() {
+        Ok(pixels) => pixels,
+        Err(e) => {
+            log::warn!("[ClassPaper] 解码画面帧失败: {}", e);
+            return;
+        }
+    };
+    let Some(info) = decoder.info() else {
+        return;
+    };
+    if info.pixel_format != jpeg_decoder::PixelFormat::RGB24 {
+        log::warn!("[ClassPaper] 不支持的画面帧像素格式: {:?}", info.pixel_format);
+        return;
+    }
+    let mut surface = surface.lock().unwrap();
+    surface.frame = Some(Frame {
+        pixels,
+        width: usize::from(info.width),
+        height: usize::from(info.height),
+    });
+    surface.draw();
+    drop(surface);
+    if let Err(e) = conn.flush() {
+        log::warn!("[ClassPaper] 提交画面帧失败: {}", e);
+    }
+}
+
+impl DesktopBackend for Wayland {
+    fn name(&self) -> &'static str {
+        "Wayland"
+    }
+
+    fn screen_size(&self) -> (i32, i32) {
+        self.outputs
+            .lock()
+            .unwrap()
+            .first()
+            .map_or(DEFAULT_SCREEN_SIZE, |(_, monitor)| (monitor.width, monitor.height))
+    }
+
+    fn monitors(&self) -> Vec<Monitor> {
+        self.outputs.lock().unwrap().iter().map(|(_, monitor)| monitor.clone()).collect()
+    }
+
+    // 缩放由合成器处理
+    fn set_dpi_aware(&self) -> bool {
+        false
+    }
+
+    fn renders_offscreen(&self) -> bool {
+        true
+    }
+
+    fn embed_as_wallpaper(&self, ui: &UI, _window_name: &str, monitor: &Monitor) -> Result<(), String> {
+        log::info!("[ClassPaper] 开始设置桌面嵌入 (Wayland 背景层): {}", monitor.id);
+        let surface = self.ensure_layer(monitor).map_err(|e| {
+            log::error!("[ClassPaper] 创建背景层表面失败: {}", e);
+            format!("桌面嵌入设置失败\n\n无法在显示器 {} 上创建背景层\n\n错误信息: {}\n\n请查看app.log获取详细信息。", monitor.id, e)
+        })?;
+        // 鼠标事件在单独的线程中发给浏览器，不阻塞事件线程；重新嵌入替换发送端后线程结束
+        let (input, events) = mpsc::channel::<MouseEvent>();
+        let mouse_input = ui.mouse_input();
+        thread::spawn(move || {
+            for event in events {
+                if let Err(e) = mouse_input.dispatch(event) {
+                    log::debug!("[ClassPaper] 转发鼠标事件失败: {}", e);
+                }
+            }
+        });
+        let mut locked = surface.lock().unwrap();
+        locked.input = Some(input);
+        let region = self.set_input_region(&locked, super::interactive());
+        drop(locked);
+        if let Err(e) = region {
+            log::warn!("[ClassPaper] 设置背景层的输入区域失败: {}", e);
+        }
+        let conn = self.conn.clone();
+        let max_size = Some((monitor.width.max(1) as u32, monitor.height.max(1) as u32));
+        match ui.start_screencast(ScreencastFormat::Jpeg, FRAME_QUALITY, max_size, move |frame| {
+            show_frame(&surface, &conn, frame)
+        }) {
+            Ok(()) => {
+                let _ = self.conn.flush();
+                log::info!("[ClassPaper] 桌面嵌入设置成功，页面画面显示在背景层");
+                Ok(())
+            }
+            Err(e) => {
+                log::error!("[ClassPaper] 截取页面画面失败: {}", e);
+                Err(format!("桌面嵌入设置失败\n\n无法截取网页显示程序的画面\n\n错误信息: {}\n\n请查看app.log获取详细信息。", e))
+            }
+        }
+    }
+
+    // 销毁显示器上的背景层表面，否则会一直显示最后一帧；之后到达的画面帧不再绘制
+    fn remove_wallpaper(&self, _window_name: &str, monitor: &Monitor) {
+        self.surfaces.lock().unwrap().retain(|surface| {
+            let mut surface = surface.lock().unwrap();
+            if surface.monitor_id != monitor.id {
+                return true;
+            }
+            surface.layer = None;
+            surface.buffer = None;
+            surface.frame = None;
+            surface.input = None;
+            false
+        });
+        let _ = self.conn.flush();
+    }
+
+    // 只转发鼠标，键盘输入需要 xkbcommon 解析键位，没有转发
+    fn set_interactive(&self, _ui: &UI, _window_name: &str, monitor: &Monitor, interactive: bool) -> Result<(), String> {
+        let surface = self
+            .surfaces
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|s| s.lock().unwrap().monitor_id == monitor.id)
+            .cloned();
+        let Some(surface) = surface else {
+            return Err(format!("显示器 {} 上没有背景层表面", monitor.id));
+        };
+        let locked = surface.lock().unwrap();
+        self.set_input_region(&locked, interactive)
+            .map_err(|e| format!("设置显示器 {} 背景层的输入区域失败: {}", monitor.id, e))
+    }
+
+    fn watch_displays(&self, listener: DisplayListener) {
+        *self.listener.lock().unwrap() = Some(listener);
+    }
+
+    // 合成器关闭背景层表面时通知，重新嵌入会创建新的表面
+    fn watch_embed(&self, listener: EmbedListener) {
+        *self.lost_listener.lock().unwrap() = Some(listener);
+    }
+
+    fn notify(&self, summary: &str, body: &str) -> Result<(), String> {
+        super::freedesktop::notify(summary, body, super::freedesktop::URGENCY_NORMAL)
+    }
+
+    fn show_error(&self, message: &str) {
+        super::notify_error(message);
+    }
+}
+
+impl CompositorHandler for State {
+    fn scale_factor_changed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: i32) {}
+
+    fn transform_changed(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        _: &wl_surface::WlSurface,
+        _: wl_output::Transform,
+    ) {
+    }
+
+    fn frame(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: u32) {}
+
+    fn surface_enter(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: &wl_output::WlOutput) {}
+
+    fn surface_leave(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: &wl_output::WlOutput) {}
+}
+
+impl OutputHandler for State {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {
+        self.outputs_changed();
+    }
+
+    fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {
+        self.outputs_changed();
+    }
+
+    fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {
+        self.outputs_changed();
+    }
+}
+
+impl LayerShellHandler for State {
+    fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, layer: &LayerSurface) {
+        // 输出被移除等情况下合成器关闭表面，下次嵌入时重新创建
+        if let Some(surface) = self.surface_of(layer) {
+            let mut surface = surface.lock().unwrap();
+            log::warn!("[ClassPaper] 合成器关闭了显示器 {} 的背景层表面", surface.monitor_id);
+            surface.layer = None;
+            surface.buffer = None;
+            surface.input = None;
+            drop(surface);
+            if let Some(ref listener) = *self.lost_listener.lock().unwrap() {
+                listener(EmbedEvent::EmbedLost);
+            }
+        }
+    }
+
+    fn configure(
+        &mut self,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+        layer: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _: u32,
+    ) {
+        let Some(surface) = self.surface_of(layer) else {
+            return;
+        };
+        let mut surface = surface.lock().unwrap();
+        surface.size = configure.new_size;
+        surface.configured = true;
+        surface.draw();
+    }
+}
+
+impl SeatHandler for State {
+    fn seat_state(&mut self) -> &mut SeatState {
+        &mut self.seat_state
+    }
+
+    fn new_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_seat::WlSeat) {}
+
+    fn new_capability(&mut self, _: &Connection, qh: &QueueHandle<Self>, seat: wl_seat::WlSeat, capability: Capability) {
+        if capability == Capability::Pointer && self.pointer.is_none() {
+            match self.seat_state.get_pointer(qh, &seat) {
+                Ok(pointer) => self.pointer = Some(pointer),
+                Err(e) => log::warn!("[ClassPaper] 无法获取鼠标，背景层不能接收点击: {}", e),
+            }
+        }
+    }
+
+    fn remove_capability(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_seat::WlSeat, capability: Capability) {
+        if capability == Capability::Pointer {
+            if let Some(pointer) = self.pointer.take() {
+                pointer.release();
+            }
+        }
+    }
+
+    fn remove_seat(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_seat::WlSeat) {}
+}
+
+impl PointerHandler for State {
+    fn pointer_frame(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_pointer::WlPointer, events: &[PointerEvent]) {
+        for event in events {
+            let Some(input) = self.surface_with(&event.surface).and_then(|s| s.lock().unwrap().input.clone()) else {
+                continue;
+            };
+            if let Some(mouse_event) = self.mouse_event(event) {
+                let _ = input.send(mouse_event);
+            }
+        }
+    }
+}
+
+impl ShmHandler for State {
+    fn shm_state(&mut self) -> &mut Shm {
+        &mut self.shm
+    }
+}
+
+impl ProvidesRegistryState for State {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry
+    }
+
+    registry_handlers![OutputState, SeatState];
+}
+
+delegate_compositor!(State);
+delegate_output!(State);
+delegate_shm!(State);
+delegate_layer!(State);
+delegate_registry!(State);
+delegate_seat!(State);
+delegate_pointer!(State);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // 需要支持 wlr-layer-shell 的合成器和浏览器，例如在 WLR_BACKENDS=headless 的 sway 中运行 cargo test
+    #[test]
+    fn test_embed() {
+        if std::env::var_os("WAYLAND_DISPLAY").is_none() {
+            eprintln!("跳过测试，未设置 WAYLAND_DISPLAY");
+            return;
+        }
+        let wayland = Wayland::connect().expect("合成器不支持 wlr-layer-shell");
+        let Some(ui) = crate::test_util::launch() else {
+            return;
+        };
+        let monitor = wayland.monitors().into_iter().next().expect("没有可用的输出");
+        wayland.embed_as_wallpaper(&ui, "classpaper-test", &monitor).unwrap();
+        // 页面变化后才会截取新的画面帧
+        ui.eval("document.body.style.background = 'rgb(255, 0, 0)'").unwrap();
+
+        let surface = wayland.ensure_layer(&monitor).unwrap();
+        let drawn = (0..50).any(|_| {
+            thread::sleep(Duration::from_millis(100));
+            surface.lock().unwrap().buffer.is_some()
+        });
+        assert!(drawn, "背景层上没有绘制画面");
+        let surface = surface.lock().unwrap();
+        assert!(surface.size.0 > 0 && surface.size.1 > 0);
+        // JPEG 有损压缩，只检查大致颜色
+        let frame = surface.frame.as_ref().unwrap();
+        let (r, g, b) = (frame.pixels[0], frame.pixels[1], frame.pixels[2]);
+        assert!(r > 200 && g < 50 && b < 50, "rgb({}, {}, {})", r, g, b);
+        drop(surface);
+        wayland.set_interactive(&ui, "classpaper-test", &monitor, true).unwrap();
+        wayland.set_interactive(&ui, "classpaper-test", &monitor, false).unwrap();
+    }
+}
@@ -0,0 +1,83 @@
+// Win32 实现：把浏览器窗口嵌入 WorkerW/Progman 之下并移到所在显示器，错误用消息框提示
+
+use super::{DesktopBackend, DisplayListener, EmbedListener, Hotkey, HotkeyListener, Monitor, WidgetStyle};
+use crate::winapi;
+use alcro::UI;
+
+pub struct Win32;
+
+impl DesktopBackend for Win32 {
+    fn name(&self) -> &'static str {
+        "Win32"
+    }
+
+    fn screen_size(&self) -> (i32, i32) {
+        (winapi::get_screen_width(), winapi::get_screen_height())
+    }
+
+    fn monitors(&self) -> Vec<Monitor> {
+        winapi::get_monitors()
+    }
+
+    fn set_dpi_aware(&self) -> bool {
+        winapi::set_dpi_aware()
+    }
+
+    fn embed_as_wallpaper(&self, _ui: &UI, window_name: &str, monitor: &Monitor) -> Result<(), String> {
+        if winapi::setup_desktop_penetration(window_name, monitor) {
+            winapi::set_window_interactive(winapi::find_window_by_title(window_name), super::interactive());
+            Ok(())
+        } else {
+            Err("桌面穿透设置失败\n\n可能原因：\n• 系统权限不足\n• 桌面窗口被其他程序占用\n• Windows版本兼容性问题\n\n请尝试以管理员身份运行程序，或查看app.log获取详细信息。".to_string())
+        }
+    }
+
+    // 嵌入后窗口仍是原来的窗口，直接移到显示器的新位置；找不到时重新嵌入
+    fn fit_to_monitor(&self, ui: &UI, window_name: &str, monitor: &Monitor) -> Result<(), String> {
+        let hwnd = winapi::find_window_by_title(window_name);
+        if hwnd.is_null() || !winapi::setup_fullscreen_position(hwnd, monitor) {
+            return self.embed_as_wallpaper(ui, window_name, monitor);
+        }
+        Ok(())
+    }
+
+    fn set_interactive(&self, _ui: &UI, window_name: &str, _monitor: &Monitor, interactive: bool) -> Result<(), String> {
+        if winapi::set_window_interactive(winapi::find_window_by_title(window_name), interactive) {
+            Ok(())
+        } else {
+            Err(format!("未找到标题为 {} 的浏览器窗口", window_name))
+        }
+    }
+
+    fn show_as_widget(&self, _ui: &UI, window_name: &str, style: &WidgetStyle) -> Result<(), String> {
+        if winapi::setup_widget(window_name, style.opacity, style.always_on_top) {
+            Ok(())
+        } else {
+            Err(format!("未找到标题为 {} 的浏览器窗口", window_name))
+        }
+    }
+
+    // 热键在监听窗口线程中注册，被其他程序占用时只写入日志
+    fn watch_hotkey(&self, hotkey: &Hotkey, listener: HotkeyListener) -> Result<(), String> {
+        winapi::watch_hotkey(hotkey, listener);
+        Ok(())
+    }
+
+    fn watch_displays(&self, listener: DisplayListener) {
+        winapi::watch_display_changes(listener);
+    }
+
+    // 资源管理器重启时收到 TaskbarCreated，嵌入窗口被遮挡时收到叠放顺序的 WinEvent
+    fn watch_embed(&self, listener: EmbedListener) {
+        winapi::watch_embed(listener);
+    }
+
+    // 通过临时托盘图标的气泡显示
+    fn notify(&self, summary: &str, body: &str) -> Result<(), String> {
+        winapi::show_notification(summary, body)
+    }
+
+    fn show_error(&self, message: &str) {
+        winapi::show_error_notification(message);
+    }
+}
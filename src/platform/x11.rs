@@ -0,0 +1,658 @@
+// X11 实现：按标题找到浏览器窗口，设置 EWMH 的桌面窗口类型和状态，让它铺满屏幕显示在桌面图标之下。
+// 需要遵循 EWMH 的窗口管理器（GNOME、KDE、Xfce、Openbox 等）；点击穿透通过 Shape 扩展的输入区域实现
+
+use super::{DesktopBackend, DisplayListener, EmbedEvent, EmbedListener, Hotkey, HotkeyListener, Key, Monitor, WidgetStyle};
+use alcro::UI;
+use std::thread;
+use std::time::Duration;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::randr::{ConnectionExt as _, NotifyMask};
+use x11rb::protocol::shape::{self, ConnectionExt as _, SK, SO};
+use x11rb::protocol::xproto::{
+    AtomEnum, ChangeWindowAttributesAux, ClientMessageEvent, ClipOrdering, ConfigureWindowAux, ConnectionExt,
+    EventMask, GrabMode, ModMask, PropMode, Property, StackMode, Window,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+// 浏览器窗口出现并设置好标题之前的等待：每次间隔和最多次数
+const FIND_INTERVAL: Duration = Duration::from_millis(250);
+const FIND_ATTEMPTS: u32 = 40;
+
+// _NET_WM_STATE 客户端消息中的“添加”操作
+const NET_WM_STATE_ADD: u32 = 1;
+// 嵌入后窗口具有的状态：置底、所有工作区可见、不显示在任务栏和工作区切换器中
+const WALLPAPER_STATES: [&str; 4] = [
+    "_NET_WM_STATE_BELOW",
+    "_NET_WM_STATE_STICKY",
+    "_NET_WM_STATE_SKIP_TASKBAR",
+    "_NET_WM_STATE_SKIP_PAGER",
+];
+
+// 小窗口的状态：所有工作区可见、不显示在任务栏和工作区切换器中，置顶时另加 _NET_WM_STATE_ABOVE
+const WIDGET_STATES: [&str; 3] = [
+    "_NET_WM_STATE_STICKY",
+    "_NET_WM_STATE_SKIP_TASKBAR",
+    "_NET_WM_STATE_SKIP_PAGER",
+];
+// _MOTIF_WM_HINTS 中表示 decorations 字段有效的标志，decorations 为 0 时窗口管理器不画边框
+const MWM_HINTS_DECORATIONS: u32 = 2;
+
+type Error = Box<dyn std::error::Error>;
+
+pub struct X11 {
+    conn: RustConnection,
+    root: Window,
+    width: u16,
+    height: u16,
+}
+
+impl X11 {
+    // 连接 DISPLAY 指定的 X 服务器，失败时返回 None，由调用方改用其他实现
+    pub fn connect() -> Option<Self> {
+        match x11rb::connect(None) {
+            Ok((conn, screen_num)) => {
+                let screen = &conn.setup().roots[screen_num];
+                let (root, width, height) = (screen.root, screen.width_in_pixels, screen.height_in_pixels);
+                Some(Self {
+                    conn,
+                    root,
+                    width,
+                    height,
+                })
+            }
+            Err(e) => {
+                log::warn!("[ClassPaper] 无法连接 X 服务器: {}", e);
+                None
+            }
+        }
+    }
+
+    fn atom(&self, name: &str) -> Result<u32, Error> {
+        Ok(self.conn.intern_atom(false, name.as_bytes())?.reply()?.atom)
+    }
+
+    // 窗口标题，优先读取 UTF-8 的 _NET_WM_NAME
+    fn title(&self, window: Window) -> Result<String, Error> {
+        let utf8_string = self.atom("UTF8_STRING")?;
+        let net_wm_name = self.atom("_NET_WM_NAME")?;
+        let mut value = self
+            .conn
+            .get_property(false, window, net_wm_name, utf8_string, 0, 1024)?
+            .reply()?
+            .value;
+        if value.is_empty() {
+            value = self
+                .conn
+                .get_property(false, window, AtomEnum::WM_NAME, AtomEnum::STRING, 0, 1024)?
+                .reply()?
+                .value;
+        }
+        Ok(String::from_utf8_lossy(&value).into_owned())
+    }
+
+    // 顶层窗口：优先使用窗口管理器维护的 _NET_CLIENT_LIST，没有窗口管理器时取根窗口的子窗口
+    fn top_level_windows(&self) -> Result<Vec<Window>, Error> {
+        let client_list = self.atom("_NET_CLIENT_LIST")?;
+        let reply = self
+            .conn
+            .get_property(false, self.root, client_list, AtomEnum::WINDOW, 0, u32::MAX)?
+            .reply()?;
+        if let Some(windows) = reply.value32() {
+            let windows: Vec<Window> = windows.collect();
+            if !windows.is_empty() {
+                return Ok(windows);
+            }
+        }
+        Ok(self.conn.query_tree(self.root)?.reply()?.children)
+    }
+
+    // 查找标题包含 title 的顶层窗口；浏览器会在页面标题后追加浏览器名称，所以不要求完全相同
+    fn find_window(&self, title: &str) -> Result<Option<Window>, Error> {
+        for window in self.top_level_windows()? {
+            if self.title(window)?.contains(title) {
+                return Ok(Some(window));
+            }
+        }
+        Ok(None)
+    }
+
+    // 通过 RandR 枚举显示器，每个显示器以输出名称（如 HDMI-1）作为 id
+    fn randr_monitors(&self) -> Result<Vec<Monitor>, Error> {
+        let reply = self.conn.randr_get_monitors(self.root, true)?.reply()?;
+        let mut monitors = Vec::new();
+        for info in reply.monitors {
+            let name = self.conn.get_atom_name(info.name)?.reply()?.name;
+            monitors.push(Monitor {
+                id: String::from_utf8_lossy(&name).into_owned(),
+                x: i32::from(info.x),
+                y: i32::from(info.y),
+                width: i32::from(info.width),
+                height: i32::from(info.height),
+                primary: info.primary,
+            });
+        }
+        // 没有设置主显示器时以第一个为主显示器
+        if !monitors.iter().any(|m| m.primary) {
+            if let Some(first) = monitors.first_mut() {
+                first.primary = true;
+            }
+        }
+        Ok(monitors)
+    }
+
+    fn embed(&self, window: Window, monitor: &Monitor) -> Result<(), Error> {
+        let net_wm_state = self.atom("_NET_WM_STATE")?;
+        let states = WALLPAPER_STATES
+            .iter()
+            .map(|name| self.atom(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        // 窗口管理器只在窗口映射时读取窗口类型，先取消映射，设置好属性后重新映射
+        self.conn.unmap_window(window)?;
+        self.conn.change_property32(
+            PropMode::REPLACE,
+            window,
+            self.atom("_NET_WM_WINDOW_TYPE")?,
+            AtomEnum::ATOM,
+            &[self.atom("_NET_WM_WINDOW_TYPE_DESKTOP")?],
+        )?;
+        // 同时去掉 kiosk 模式的全屏状态
+        self.conn
+            .change_property32(PropMode::REPLACE, window, net_wm_state, AtomEnum::ATOM, &states)?;
+        self.conn.change_property32(
+            PropMode::REPLACE,
+            window,
+            self.atom("_NET_WM_DESKTOP")?,
+            AtomEnum::CARDINAL,
+            &[u32::MAX],
+        )?;
+        self.conn.map_window(window)?;
+        self.add_states(window, net_wm_state, &states)?;
+        self.place(window, monitor)
+    }
+
+    // 部分窗口管理器重新映射时不读取 _NET_WM_STATE，再通过客户端消息添加一次，每条消息最多两个状态
+    fn add_states(&self, window: Window, net_wm_state: u32, states: &[u32]) -> Result<(), Error> {
+        for pair in states.chunks(2) {
+            let event = ClientMessageEvent::new(
+                32,
+                window,
+                net_wm_state,
+                [NET_WM_STATE_ADD, pair[0], pair.get(1).copied().unwrap_or(0), 1, 0],
+            );
+            self.conn.send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn widget(&self, window: Window, style: &WidgetStyle) -> Result<(), Error> {
+        let net_wm_state = self.atom("_NET_WM_STATE")?;
+        let mut states = WIDGET_STATES
+            .iter()
+            .map(|name| self.atom(name))
+            .collect::<Result<Vec<_>, _>>()?;
+        if style.always_on_top {
+            states.push(self.atom("_NET_WM_STATE_ABOVE")?);
+        }
+        let motif_hints = self.atom("_MOTIF_WM_HINTS")?;
+        let opacity = self.atom("_NET_WM_WINDOW_OPACITY")?;
+        // 与嵌入时一样，窗口管理器只在窗口映射时读取边框设置
+        self.conn.unmap_window(window)?;
+        self.conn.change_property32(
+            PropMode::REPLACE,
+            window,
+            motif_hints,
+            motif_hints,
+            &[MWM_HINTS_DECORATIONS, 0, 0, 0, 0],
+        )?;
+        self.conn
+            .change_property32(PropMode::REPLACE, window, net_wm_state, AtomEnum::ATOM, &states)?;
+        self.conn.change_property32(
+            PropMode::REPLACE,
+            window,
+            self.atom("_NET_WM_DESKTOP")?,
+            AtomEnum::CARDINAL,
+            &[u32::MAX],
+        )?;
+        // 由合成器按 _NET_WM_WINDOW_OPACITY 绘制半透明，0xffffffff 为不透明
+        if style.opacity < 1.0 {
+            let value = (style.opacity.clamp(0.0, 1.0) * f64::from(u32::MAX)) as u32;
+            self.conn
+                .change_property32(PropMode::REPLACE, window, opacity, AtomEnum::CARDINAL, &[value])?;
+        } else {
+            self.conn.delete_property(window, opacity)?;
+        }
+        self.conn.map_window(window)?;
+        self.add_states(window, net_wm_state, &states)?;
+        self.conn.sync()?;
+        Ok(())
+    }
+
+    // 窗口及窗口管理器为它创建的外框（根窗口的直接子窗口），没有外框时只有窗口本身
+    fn with_frame(&self, window: Window) -> Result<Vec<Window>, Error> {
+        let mut frame = window;
+        loop {
+            let parent = self.conn.query_tree(frame)?.reply()?.parent;
+            if parent == self.root || parent == x11rb::NONE {
+                break;
+            }
+            frame = parent;
+        }
+        Ok(if frame == window { vec![window] } else { vec![window, frame] })
+    }
+
+    // 设置窗口的输入区域：接收输入时恢复为整个窗口，否则设为空，点击落到下面的桌面
+    fn set_input(&self, window: Window, interactive: bool) -> Result<(), Error> {
+        if self.conn.extension_information(shape::X11_EXTENSION_NAME)?.is_none() {
+            return Err("X 服务器不支持 Shape 扩展".into());
+        }
+        for window in self.with_frame(window)? {
+            if interactive {
+                self.conn.shape_mask(SO::SET, SK::INPUT, window, 0, 0, x11rb::NONE)?;
+            } else {
+                self.conn
+                    .shape_rectangles(SO::SET, SK::INPUT, ClipOrdering::UNSORTED, window, 0, 0, &[])?;
+            }
+        }
+        self.conn.sync()?;
+        Ok(())
+    }
+
+    // 把窗口移到 monitor 的位置并铺满，放在其他窗口之下
+    fn place(&self, window: Window, monitor: &Monitor) -> Result<(), Error> {
+        self.conn.configure_window(
+            window,
+            &ConfigureWindowAux::new()
+                .x(monitor.x)
+                .y(monitor.y)
+                .width(monitor.width.max(1) as u32)
+                .height(monitor.height.max(1) as u32)
+                .stack_mode(StackMode::BELOW),
+        )?;
+        self.conn.sync()?;
+        Ok(())
+    }
+}
+
+// 在单独的连接上订阅 RandR 通知，显示器接入、移除或分辨率改变时调用 listener，连接断开时返回错误
+fn watch_randr(listener: &DisplayListener) -> Result<(), Error> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    // 服务器只向声明了 1.2 及以上版本的客户端发送输出和 CRTC 的变化通知
+    conn.randr_query_version(1, 5)?.reply()?;
+    conn.randr_select_input(
+        root,
+        NotifyMask::SCREEN_CHANGE | NotifyMask::CRTC_CHANGE | NotifyMask::OUTPUT_CHANGE,
+    )?;
+    conn.flush()?;
+    loop {
+        match conn.wait_for_event()? {
+            Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_) => listener(),
+            _ => {}
+        }
+    }
+}
+
+// 按键对应的 X keysym：小写字母和数字与 ASCII 码相同，F1 起依次递增
+fn keysym(key: Key) -> u32 {
+    match key {
+        Key::Char(c) => u32::from(c.to_ascii_lowercase()),
+        Key::Function(n) => 0xffbe + u32::from(n) - 1,
+    }
+}
+
+// 在单独的连接上抓取根窗口上的热键，之后按下热键时该连接收到 KeyPress；已被其他程序抓取时返回错误
+fn grab_hotkey(hotkey: &Hotkey) -> Result<RustConnection, Error> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let (min, max) = (conn.setup().min_keycode, conn.setup().max_keycode);
+    let mapping = conn.get_keyboard_mapping(min, max - min + 1)?.reply()?;
+    let keysym = keysym(hotkey.key);
+    let keycode = mapping
+        .keysyms
+        .chunks(usize::from(mapping.keysyms_per_keycode).max(1))
+        .position(|keysyms| keysyms.contains(&keysym))
+        .map(|i| min + i as u8)
+        .ok_or("当前键盘布局中没有该按键")?;
+    let mut modifiers = ModMask::from(0u16);
+    for (held, modifier) in [
+        (hotkey.ctrl, ModMask::CONTROL),
+        (hotkey.alt, ModMask::M1),
+        (hotkey.shift, ModMask::SHIFT),
+        (hotkey.super_key, ModMask::M4),
+    ] {
+        if held {
+            modifiers |= modifier;
+        }
+    }
+    // 大写锁定和数字锁定（通常是 Mod2）打开时同样响应
+    for locks in [ModMask::from(0u16), ModMask::LOCK, ModMask::M2, ModMask::LOCK | ModMask::M2] {
+        conn.grab_key(false, root, modifiers | locks, keycode, GrabMode::ASYNC, GrabMode::ASYNC)?
+            .check()?;
+    }
+    Ok(conn)
+}
+
+impl DesktopBackend for X11 {
+    fn name(&self) -> &'static str {
+        "X11"
+    }
+
+    fn screen_size(&self) -> (i32, i32) {
+        (i32::from(self.width), i32::from(self.height))
+    }
+
+    fn monitors(&self) -> Vec<Monitor> {
+        match self.randr_monitors() {
+            Ok(monitors) => monitors,
+            Err(e) => {
+                log::warn!("[ClassPaper] 无法通过 RandR 获取显示器: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    // X11 下缩放由浏览器按 Xft.dpi 处理，无需声明
+    fn set_dpi_aware(&self) -> bool {
+        false
+    }
+
+    fn embed_as_wallpaper(&self, _ui: &UI, window_name: &str, monitor: &Monitor) -> Result<(), String> {
+        log::info!("[ClassPaper] 开始设置桌面嵌入 (X11): {} ({})", window_name, monitor.id);
+        for _ in 0..FIND_ATTEMPTS {
+            match self.find_window(window_name) {
+                Ok(Some(window)) => {
+                    let embedded = self
+                        .embed(window, monitor)
+                        .and_then(|()| self.set_input(window, super::interactive()));
+                    return match embedded {
+                        Ok(()) => {
+                            log::info!("[ClassPaper] 桌面嵌入设置成功，窗口 0x{:x}", window);
+                            Ok(())
+                        }
+                        Err(e) => {
+                            log::error!("[ClassPaper] 桌面嵌入设置失败: {}", e);
+                            Err(format!("桌面嵌入设置失败\n\n错误信息: {}\n\n请确认正在使用支持 EWMH 的窗口管理器，或查看app.log获取详细信息。", e))
+                        }
+                    };
+                }
+                Ok(None) => thread::sleep(FIND_INTERVAL),
+                Err(e) => {
+                    log::error!("[ClassPaper] 查找浏览器窗口失败: {}", e);
+                    return Err(format!("桌面嵌入设置失败\n\n查找网页显示程序的窗口失败: {}\n\n请查看app.log获取详细信息。", e));
+                }
+            }
+        }
+        log::error!("[ClassPaper] 未找到标题为 {} 的浏览器窗口", window_name);
+        Err("桌面嵌入设置失败\n\n未找到网页显示程序的窗口。\n\n请查看app.log获取详细信息。".to_string())
+    }
+
+    fn fit_to_monitor(&self, ui: &UI, window_name: &str, monitor: &Monitor) -> Result<(), String> {
+        match self.find_window(window_name) {
+            Ok(Some(window)) => self.place(window, monitor).map_err(|e| {
+                log::warn!("[ClassPaper] 调整窗口 {} 到显示器 {} 失败: {}", window_name, monitor.id, e);
+                format!("调整窗口到显示器 {} 失败: {}", monitor.id, e)
+            }),
+            Ok(None) => self.embed_as_wallpaper(ui, window_name, monitor),
+            Err(e) => Err(format!("查找网页显示程序的窗口失败: {}", e)),
+        }
+    }
+
+    fn show_as_widget(&self, _ui: &UI, window_name: &str, style: &WidgetStyle) -> Result<(), String> {
+        for _ in 0..FIND_ATTEMPTS {
+            match self.find_window(window_name) {
+                Ok(Some(window)) => {
+                    return self.widget(window, style).map_err(|e| format!("设置小窗口失败: {}", e));
+                }
+                Ok(None) => thread::sleep(FIND_INTERVAL),
+                Err(e) => return Err(format!("查找网页显示程序的窗口失败: {}", e)),
+            }
+        }
+        Err(format!("未找到标题为 {} 的浏览器窗口", window_name))
+    }
+
+    fn watch_displays(&self, listener: DisplayListener) {
+        thread::spawn(move || {
+            log::info!("[ClassPaper] 开始监听显示器变化 (RandR)");
+            if let Err(e) = watch_randr(&listener) {
+                log::warn!("[ClassPaper] 监听显示器变化失败: {}", e);
+            }
+        });
+    }
+
+    fn set_interactive(&self, _ui: &UI, window_name: &str, _monitor: &Monitor, interactive: bool) -> Result<(), String> {
+        match self.find_window(window_name) {
+            Ok(Some(window)) => self
+                .set_input(window, interactive)
+                .map_err(|e| format!("设置窗口 {} 的输入区域失败: {}", window_name, e)),
+            Ok(None) => Err(format!("未找到标题为 {} 的浏览器窗口", window_name)),
+            Err(e) => Err(format!("查找网页显示程序的窗口失败: {}", e)),
+        }
+    }
+
+    fn watch_hotkey(&self, hotkey: &Hotkey, listener: HotkeyListener) -> Result<(), String> {
+        let conn = grab_hotkey(hotkey).map_err(|e| format!("注册热键 {} 失败: {}", hotkey, e))?;
+        log::info!("[ClassPaper] 已注册热键 {}", hotkey);
+        thread::spawn(move || loop {
+            match conn.wait_for_event() {
+                Ok(Event::KeyPress(_)) => listener(),
+                Ok(_) => {}
+                Err(e) => {
+                    log::warn!("[ClassPaper] 监听热键失败: {}", e);
+                    break;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    // 窗口管理器重新启动时会更新根窗口的 _NET_SUPPORTING_WM_CHECK
+    fn watch_embed(&self, listener: EmbedListener) {
+        thread::spawn(move || {
+            if let Err(e) = watch_window_manager(&listener) {
+                log::warn!("[ClassPaper] 监听窗口管理器重启失败: {}", e);
+            }
+        });
+    }
+
+    fn notify(&self, summary: &str, body: &str) -> Result<(), String> {
+        super::freedesktop::notify(summary, body, super::freedesktop::URGENCY_NORMAL)
+    }
+
+    fn show_error(&self, message: &str) {
+        super::notify_error(message);
+    }
+}
+
+// 在单独的连接上监听根窗口的属性变化，窗口管理器替换或重启后调用 listener，连接断开时返回错误
+fn watch_window_manager(listener: &EmbedListener) -> Result<(), Error> {
+    let (conn, screen_num) = x11rb::connect(None)?;
+    let root = conn.setup().roots[screen_num].root;
+    let wm_check = conn.intern_atom(false, b"_NET_SUPPORTING_WM_CHECK")?.reply()?.atom;
+    conn.change_window_attributes(root, &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE))?;
+    conn.flush()?;
+    loop {
+        if let Event::PropertyNotify(event) = conn.wait_for_event()? {
+            // 窗口管理器退出时删除该属性，新的窗口管理器启动后重新设置
+            if event.atom == wm_check && event.state == Property::NEW_VALUE {
+                log::warn!("[ClassPaper] 窗口管理器已重新启动，桌面嵌入需要重新设置");
+                listener(EmbedEvent::ShellRestarted);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::protocol::xproto::{CreateWindowAux, WindowClass};
+    use x11rb::COPY_DEPTH_FROM_PARENT;
+
+    // 创建并映射一个标题为 title 的窗口，等待 find_window 能找到它
+    fn create_window(x11: &X11, title: &str) -> Window {
+        let window = x11.conn.generate_id().unwrap();
+        x11.conn
+            .create_window(
+                COPY_DEPTH_FROM_PARENT,
+                window,
+                x11.root,
+                10,
+                10,
+                200,
+                100,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                0,
+                &CreateWindowAux::new(),
+            )
+            .unwrap();
+        x11.conn
+            .change_property8(
+                PropMode::REPLACE,
+                window,
+                AtomEnum::WM_NAME,
+                AtomEnum::STRING,
+                format!("{} - Chromium", title).as_bytes(),
+            )
+            .unwrap();
+        x11.conn.map_window(window).unwrap();
+        x11.conn.sync().unwrap();
+
+        // 窗口管理器异步地把新窗口加入 _NET_CLIENT_LIST
+        let found = (0..20).find_map(|_| {
+            let found = x11.find_window(title).unwrap();
+            if found.is_none() {
+                thread::sleep(Duration::from_millis(100));
+            }
+            found
+        });
+        assert_eq!(found, Some(window));
+        window
+    }
+
+    fn property32(x11: &X11, window: Window, name: &str, kind: impl Into<u32>) -> Vec<u32> {
+        x11.conn
+            .get_property(false, window, x11.atom(name).unwrap(), kind, 0, 16)
+            .unwrap()
+            .reply()
+            .unwrap()
+            .value32()
+            .map_or_else(Vec::new, |values| values.collect())
+    }
+
+    // 需要 X 服务器，例如 xvfb-run -a cargo test；同时运行窗口管理器时检查的是窗口管理器处理后的结果
+    #[test]
+    fn test_embed() {
+        if std::env::var_os("DISPLAY").is_none() {
+            eprintln!("跳过测试，未设置 DISPLAY");
+            return;
+        }
+        let x11 = X11::connect().expect("无法连接 X 服务器");
+        let window = create_window(&x11, "classpaper-test");
+
+        let monitor = crate::platform::single_monitor(x11.screen_size());
+        x11.embed(window, &monitor).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        let window_type = x11
+            .conn
+            .get_property(false, window, x11.atom("_NET_WM_WINDOW_TYPE").unwrap(), AtomEnum::ATOM, 0, 8)
+            .unwrap()
+            .reply()
+            .unwrap();
+        let desktop = x11.atom("_NET_WM_WINDOW_TYPE_DESKTOP").unwrap();
+        assert_eq!(window_type.value32().unwrap().collect::<Vec<_>>(), vec![desktop]);
+        let state = x11
+            .conn
+            .get_property(false, window, x11.atom("_NET_WM_STATE").unwrap(), AtomEnum::ATOM, 0, 16)
+            .unwrap()
+            .reply()
+            .unwrap();
+        let state: Vec<u32> = state.value32().unwrap().collect();
+        assert!(state.contains(&x11.atom("_NET_WM_STATE_BELOW").unwrap()));
+        assert!(state.contains(&x11.atom("_NET_WM_STATE_SKIP_TASKBAR").unwrap()));
+        let geometry = x11.conn.get_geometry(window).unwrap().reply().unwrap();
+        assert_eq!((geometry.width, geometry.height), (x11.width, x11.height));
+
+        // 点击穿透时输入区域为空，可交互时为整个窗口
+        let input_rectangles = |window| {
+            x11.conn
+                .shape_get_rectangles(window, SK::INPUT)
+                .unwrap()
+                .reply()
+                .unwrap()
+                .rectangles
+                .len()
+        };
+        x11.set_input(window, false).unwrap();
+        assert_eq!(input_rectangles(window), 0);
+        x11.set_input(window, true).unwrap();
+        assert_eq!(input_rectangles(window), 1);
+        x11.conn.destroy_window(window).unwrap();
+        x11.conn.sync().unwrap();
+    }
+
+    #[test]
+    fn test_widget() {
+        if std::env::var_os("DISPLAY").is_none() {
+            eprintln!("跳过测试，未设置 DISPLAY");
+            return;
+        }
+        let x11 = X11::connect().expect("无法连接 X 服务器");
+        let window = create_window(&x11, "classpaper-widget-test");
+        let style = WidgetStyle {
+            opacity: 0.5,
+            always_on_top: true,
+        };
+        x11.widget(window, &style).unwrap();
+        thread::sleep(Duration::from_millis(200));
+        let motif_hints = x11.atom("_MOTIF_WM_HINTS").unwrap();
+        assert_eq!(property32(&x11, window, "_MOTIF_WM_HINTS", motif_hints), vec![MWM_HINTS_DECORATIONS, 0, 0, 0, 0]);
+        let state = property32(&x11, window, "_NET_WM_STATE", AtomEnum::ATOM);
+        assert!(state.contains(&x11.atom("_NET_WM_STATE_ABOVE").unwrap()));
+        assert!(state.contains(&x11.atom("_NET_WM_STATE_SKIP_TASKBAR").unwrap()));
+        assert_eq!(
+            property32(&x11, window, "_NET_WM_WINDOW_OPACITY", AtomEnum::CARDINAL),
+            vec![u32::MAX / 2]
+        );
+
+        // 不透明且不置顶时去掉不透明度属性
+        let style = WidgetStyle {
+            opacity: 1.0,
+            always_on_top: false,
+        };
+        x11.widget(window, &style).unwrap();
+        assert!(property32(&x11, window, "_NET_WM_WINDOW_OPACITY", AtomEnum::CARDINAL).is_empty());
+        x11.conn.destroy_window(window).unwrap();
+        x11.conn.sync().unwrap();
+    }
+
+    // 刷新页面后 <title> 会覆盖窗口标题，keep_window_title 改回后仍能按标题找到窗口重新嵌入
+    #[test]
+    fn test_embed_after_reload() {
+        if std::env::var_os("DISPLAY").is_none() {
+            eprintln!("跳过测试，未设置 DISPLAY");
+            return;
+        }
+        let Some(ui) = crate::test_util::launch_with(&[]) else {
+            return;
+        };
+        let x11 = X11::connect().expect("无法连接 X 服务器");
+        let window_name = "[classpaper-reload-test-1]";
+        let monitor = crate::platform::single_monitor(x11.screen_size());
+        ui.load(alcro::Content::Html("<html><head><title>背景课表</title></head><body></body></html>"))
+            .unwrap();
+        crate::platform::keep_window_title(&ui, window_name).unwrap();
+        x11.embed_as_wallpaper(&ui, window_name, &monitor).unwrap();
+
+        ui.reload(Duration::from_secs(5)).unwrap();
+        crate::test_util::wait_for(&ui, "document.title", |v| v.as_str() == Some(window_name));
+        x11.embed_as_wallpaper(&ui, window_name, &monitor).unwrap();
+        ui.close();
+    }
+}
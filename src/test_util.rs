@@ -0,0 +1,46 @@
+// 需要浏览器的测试共用的部分：启动浏览器（没有安装时跳过测试）、轮询等待页面状态。
+// 单元测试作为 crate::test_util 使用，tests 下的前端测试通过 tests/common 引入同一个文件
+
+use alcro::{JSObject, UIBuilder, UILaunchError, UI};
+use std::time::Duration;
+
+// 没有安装浏览器时返回 None 跳过测试，其他启动错误直接失败
+pub fn launched(result: Result<UI, UILaunchError>) -> Option<UI> {
+    match result {
+        Ok(ui) => Some(ui),
+        Err(UILaunchError::LocateChromeError(e)) => {
+            eprintln!("跳过测试，未找到浏览器: {}", e);
+            None
+        }
+        Err(e) => panic!("启动浏览器失败: {}", e),
+    }
+}
+
+// 以 args 启动浏览器，显示空白页
+pub fn launch_with(args: &[&str]) -> Option<UI> {
+    launched(
+        UIBuilder::new()
+            .custom_args(args)
+            .interactive(false)
+            .timeout(Duration::from_secs(10))
+            .run(),
+    )
+}
+
+// 启动无头浏览器
+pub fn launch() -> Option<UI> {
+    launch_with(&["--headless"])
+}
+
+// 每 100 毫秒执行一次 js，直到结果满足 f，最多等 5 秒
+pub fn wait_for(ui: &UI, js: &str, f: impl Fn(&JSObject) -> bool) -> JSObject {
+    for _ in 0..50 {
+        if let Ok(value) = ui.eval(js) {
+            if f(&value) {
+                return value;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    panic!("等待超时: {}", js);
+}
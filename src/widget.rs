@@ -0,0 +1,118 @@
+// 小窗口模式：主窗口不嵌入桌面，而是主显示器上一个无边框、可置顶、可半透明的小窗口。
+// 页面顶部注入一条拖动条，拖动时通过 moveWidget 移动窗口，松开后记住位置，下次启动时恢复
+
+use crate::platform::{self, Monitor, WidgetStyle};
+use crate::window_state::{self, WindowKind};
+use crate::WidgetConfig;
+use alcro::{Bounds, WindowState, UI};
+use std::sync::Arc;
+
+// 没有保存过位置时，小窗口与主显示器右上角的距离
+const DEFAULT_MARGIN: i32 = 24;
+
+// 页面顶部的拖动条：鼠标移入时显示，按住拖动时按屏幕坐标移动窗口，松开时保存位置
+const DRAG_SCRIPT: &str = r#"
+(() => {
+  if (window.__classpaperWidgetHandle) return;
+  window.__classpaperWidgetHandle = true;
+  const install = () => {
+    const handle = document.createElement('div');
+    handle.title = '拖动以移动小窗口';
+    handle.style.cssText = 'position:fixed;top:0;left:0;right:0;height:10px;z-index:2147483647;' +
+      'cursor:move;background:transparent;transition:background .2s;';
+    handle.addEventListener('mouseenter', () => handle.style.background = 'rgba(127,127,127,.4)');
+    handle.addEventListener('mouseleave', () => handle.style.background = 'transparent');
+    let offset = null;
+    const move = (e, save) => window.moveWidget &&
+      window.moveWidget(Math.round(e.screenX - offset[0]), Math.round(e.screenY - offset[1]), save)
+        .catch(err => console.warn('[ClassPaper] 移动小窗口失败', err));
+    handle.addEventListener('pointerdown', e => {
+      offset = [e.screenX - window.screenX, e.screenY - window.screenY];
+      handle.setPointerCapture(e.pointerId);
+    });
+    handle.addEventListener('pointermove', e => offset && move(e, false));
+    const end = e => {
+      if (!offset) return;
+      move(e, true);
+      offset = null;
+    };
+    handle.addEventListener('pointerup', end);
+    handle.addEventListener('pointercancel', end);
+    document.documentElement.appendChild(handle);
+  };
+  if (document.readyState === 'loading') {
+    document.addEventListener('DOMContentLoaded', install);
+  } else {
+    install();
+  }
+})();
+"#;
+
+// 小窗口的位置和大小：上次保存的位置，没有时放在 monitor 的右上角；大小总是按配置，并保证在某个显示器内
+pub fn bounds(config: &WidgetConfig, monitor: &Monitor) -> Bounds {
+    let (left, top) = window_state::saved(WindowKind::Widget).map_or(
+        (monitor.x + monitor.width - config.width - DEFAULT_MARGIN, monitor.y + DEFAULT_MARGIN),
+        |saved| (saved.left, saved.top),
+    );
+    let bounds = Bounds {
+        left,
+        top,
+        width: config.width,
+        height: config.height,
+        window_state: WindowState::Normal,
+    };
+    window_state::clamp(bounds, &platform::monitors())
+}
+
+// 浏览器启动后注入拖动条，页面刷新后仍然有效
+pub fn prepare(ui: &UI) {
+    if let Err(e) = ui.load_js(DRAG_SCRIPT) {
+        log::warn!("[ClassPaper] 注入小窗口拖动条失败: {}", e);
+    }
+}
+
+// 设置小窗口的外观并移到保存的位置，之后记录位置变化；平台不支持时显示为普通窗口
+pub fn show(ui: &Arc<UI>, window_name: &str, config: &WidgetConfig, monitor: &Monitor) {
+    let style = WidgetStyle {
+        opacity: config.opacity,
+        always_on_top: config.always_on_top,
+    };
+    match platform::backend().show_as_widget(ui, window_name, &style) {
+        Ok(()) => log::info!("[ClassPaper] 主窗口 {} 已设置为小窗口", window_name),
+        Err(e) => log::warn!("[ClassPaper] {}，小窗口将显示为普通窗口", e),
+    }
+    // 去掉边框后窗口大小可能改变，再设置一次
+    if let Err(e) = ui.set_bounds(bounds(config, monitor)) {
+        log::warn!("[ClassPaper] 设置小窗口 {} 的位置失败: {}", window_name, e);
+    }
+    window_state::track(WindowKind::Widget, Arc::downgrade(ui));
+}
+
+// 显示器变化后把小窗口移回仍连接的显示器内
+pub fn keep_visible(ui: &UI, monitors: &[Monitor]) {
+    let Ok(bounds) = ui.bounds() else {
+        return;
+    };
+    let clamped = window_state::clamp(bounds, monitors);
+    if clamped == bounds {
+        return;
+    }
+    log::info!("[ClassPaper] 小窗口不在显示器内，移到 {}+{}", clamped.left, clamped.top);
+    match ui.set_bounds(clamped) {
+        Ok(()) => window_state::save(WindowKind::Widget, clamped),
+        Err(e) => log::warn!("[ClassPaper] 移动小窗口失败: {}", e),
+    }
+}
+
+// 把小窗口移到 left, top；save 为 true（拖动结束）时记住位置
+pub fn move_to(ui: &UI, left: i32, top: i32, save: bool) -> Result<(), String> {
+    let mut bounds = ui.bounds().map_err(|e| format!("获取小窗口位置失败: {}", e))?;
+    bounds.left = left;
+    bounds.top = top;
+    bounds.window_state = WindowState::Normal;
+    ui.set_bounds(bounds).map_err(|e| format!("移动小窗口失败: {}", e))?;
+    if save {
+        window_state::save(WindowKind::Widget, bounds);
+    }
+    Ok(())
+}
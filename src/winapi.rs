@@ -1,36 +1,46 @@
+use crate::platform::{DisplayListener, EmbedEvent, EmbedListener, Hotkey, HotkeyListener, Key, Monitor};
 use once_cell::sync::Lazy;
 use winapi::um::winuser::WS_CHILD;
 use std::ffi::{CString, OsStr};
 use std::mem;
 use std::os::windows::ffi::OsStrExt;
 use std::ptr;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Mutex, Once};
 use std::thread;
-use std::time::Duration;
-use winapi::shared::minwindef::{DWORD, FALSE, TRUE};
-use winapi::shared::windef::{HWND, RECT};
+use winapi::shared::minwindef::{BOOL, DWORD, FALSE, LPARAM, LRESULT, TRUE, UINT, WPARAM};
+use winapi::shared::windef::{HDC, HMONITOR, HWINEVENTHOOK, HWND, LPRECT, RECT};
 use winapi::um::{
     dwmapi::{
         DwmEnableBlurBehindWindow, DwmExtendFrameIntoClientArea, DwmSetWindowAttribute,
         DWM_BB_BLURREGION, DWM_BB_ENABLE, DWM_BLURBEHIND,
     },
     handleapi::CloseHandle,
+    libloaderapi::GetModuleHandleW,
     processthreadsapi::OpenProcess,
     psapi::GetModuleBaseNameW,
+    shellapi::{
+        Shell_NotifyIconW, NIF_ICON, NIF_INFO, NIF_MESSAGE, NIF_TIP, NIIF_INFO, NIM_ADD, NIM_DELETE, NIM_MODIFY,
+        NIN_BALLOONHIDE, NIN_BALLOONTIMEOUT, NIN_BALLOONUSERCLICK, NOTIFYICONDATAW,
+    },
     uxtheme::MARGINS,
     wingdi::CreateRectRgn,
-    winnt::{PROCESS_QUERY_INFORMATION, PROCESS_VM_READ},
+    winnt::{LONG, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ},
     winuser::{
-        AdjustWindowRect, EnumWindows, FindWindowA, FindWindowExA, FindWindowExW, FindWindowW,
-        GetClassNameW, GetSystemMetrics, GetWindow, GetWindowLongPtrW, GetWindowTextW,
-        GetWindowThreadProcessId, MoveWindow, SendMessageTimeoutA, SendMessageTimeoutW,
-        SetLayeredWindowAttributes, SetParent, SetProcessDPIAware, SetWindowLongPtrW,
-        SetWindowPlacement, SetWindowPos, ShowWindow, GWL_EXSTYLE, GWL_STYLE, GW_HWNDPREV,
-        HWND_BOTTOM, HWND_TOP, LWA_ALPHA, SMTO_NORMAL, SM_CXSCREEN, SM_CYSCREEN, SWP_DRAWFRAME,
-        SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SW_HIDE, SW_SHOW, SW_SHOWNORMAL, WINDOWPLACEMENT,
-        WPF_SETMINPOSITION, WS_BORDER, WS_CAPTION, WS_CHILDWINDOW, WS_EX_LAYERED, WS_EX_TOOLWINDOW,
-        WS_OVERLAPPED, WS_POPUP, WS_SYSMENU, WS_THICKFRAME,
+        AdjustWindowRect, CreateWindowExW, DefWindowProcW, DispatchMessageW, EnumDisplayMonitors, EnumWindows,
+        FindWindowA, FindWindowExA, FindWindowExW, FindWindowW, GetClassNameW, GetMessageW, GetMonitorInfoW,
+        GetParent, GetSystemMetrics, GetWindow, GetWindowLongPtrW, GetWindowTextW, GetWindowThreadProcessId,
+        IsWindow, LoadIconW, MoveWindow, PostMessageW, RegisterClassW, RegisterHotKey, RegisterWindowMessageW,
+        SendMessageTimeoutA, SendMessageTimeoutW, SetLayeredWindowAttributes, SetParent, SetProcessDPIAware,
+        SetWinEventHook, SetWindowLongPtrW, SetWindowPlacement, SetWindowPos, ShowWindow, TranslateMessage,
+        EVENT_OBJECT_REORDER, EVENT_SYSTEM_FOREGROUND, GWL_EXSTYLE, GWL_STYLE, GW_HWNDPREV, HWND_BOTTOM,
+        HWND_NOTOPMOST, HWND_TOP, HWND_TOPMOST, IDI_INFORMATION, LWA_ALPHA, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN,
+        MONITORINFO, MONITORINFOEXW, MONITORINFOF_PRIMARY, MSG, SMTO_NORMAL, SM_CXSCREEN, SM_CYSCREEN,
+        SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN, SPI_SETWORKAREA, SWP_DRAWFRAME, SWP_FRAMECHANGED, SWP_NOACTIVATE,
+        SWP_NOMOVE, SWP_NOSIZE, SW_HIDE, SW_SHOW, SW_SHOWNORMAL, VK_F1, WINDOWPLACEMENT, WINEVENT_OUTOFCONTEXT,
+        WM_APP, WM_DISPLAYCHANGE, WM_DPICHANGED, WM_HOTKEY, WM_SETTINGCHANGE, WNDCLASSW, WPF_SETMINPOSITION,
+        WS_BORDER, WS_CAPTION, WS_CHILDWINDOW, WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_EX_TRANSPARENT, WS_OVERLAPPED,
+        WS_POPUP, WS_SYSMENU, WS_THICKFRAME,
     },
 };
 
@@ -52,6 +62,291 @@ pub fn set_dpi_aware() -> bool {
     unsafe { SetProcessDPIAware() != 0 }
 }
 
+unsafe extern "system" fn enum_monitors_proc(hmonitor: HMONITOR, _: HDC, _: LPRECT, data: LPARAM) -> BOOL {
+    let monitors = &mut *(data as *mut Vec<Monitor>);
+    let mut info: MONITORINFOEXW = mem::zeroed();
+    info.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
+    if GetMonitorInfoW(hmonitor, &mut info as *mut MONITORINFOEXW as *mut MONITORINFO) != 0 {
+        let rc = info.rcMonitor;
+        monitors.push(Monitor {
+            id: String::from_utf16_lossy(&info.szDevice).trim_matches('\0').to_string(),
+            x: rc.left,
+            y: rc.top,
+            width: rc.right - rc.left,
+            height: rc.bottom - rc.top,
+            primary: info.dwFlags & MONITORINFOF_PRIMARY != 0,
+        });
+    }
+    TRUE
+}
+
+// 枚举所有显示器，坐标以主显示器左上角为原点
+pub fn get_monitors() -> Vec<Monitor> {
+    let mut monitors: Vec<Monitor> = Vec::new();
+    unsafe {
+        EnumDisplayMonitors(
+            ptr::null_mut(),
+            ptr::null(),
+            Some(enum_monitors_proc),
+            &mut monitors as *mut Vec<Monitor> as LPARAM,
+        );
+    }
+    monitors
+}
+
+static DISPLAY_LISTENER: Lazy<Mutex<Option<DisplayListener>>> = Lazy::new(|| Mutex::new(None));
+static EMBED_LISTENER: Lazy<Mutex<Option<EmbedListener>>> = Lazy::new(|| Mutex::new(None));
+// 资源管理器启动（包括崩溃后重启）时广播给所有顶层窗口的消息
+static TASKBAR_CREATED: AtomicU32 = AtomicU32::new(0);
+static LISTENER_WINDOW: Once = Once::new();
+
+// 需要保持在 ShellDefView 之下的嵌入窗口：(ShellDefView, 嵌入窗口, 连续被同一窗口遮挡的次数, 遮挡的窗口)
+static EMBEDDED: Lazy<Mutex<Vec<(usize, usize, u32, usize)>>> = Lazy::new(|| Mutex::new(Vec::new()));
+// 同一个窗口连续遮挡嵌入窗口达到该次数时不再就地修正，交给调用方重新嵌入
+const MAX_CONSECUTIVE_FIXES: u32 = 5;
+
+// 嵌入窗口接收输入时位于桌面图标之上，不再保持在 ShellDefView 之下
+static INPUT_ENABLED: AtomicBool = AtomicBool::new(false);
+
+// 全局热键 (修饰键, 虚拟键码, 回调)，在监听窗口线程中注册，WM_HOTKEY 也发给该窗口
+static HOTKEY: Lazy<Mutex<Option<(u32, u32, HotkeyListener)>>> = Lazy::new(|| Mutex::new(None));
+static HOTKEY_REGISTERED: AtomicBool = AtomicBool::new(false);
+static LISTENER_HWND: AtomicUsize = AtomicUsize::new(0);
+const HOTKEY_ID: i32 = 1;
+// 其他线程请求监听窗口线程注册热键
+const WM_REGISTER_HOTKEY: UINT = WM_APP + 1;
+// 系统通知用的临时托盘图标，挂在监听窗口上，气泡消失或被点击后删除
+const NOTIFY_ICON_ID: UINT = 1;
+const WM_NOTIFY_ICON: UINT = WM_APP + 2;
+
+fn emit_embed_event(event: EmbedEvent) {
+    if let Some(ref listener) = *EMBED_LISTENER.lock().unwrap() {
+        listener(event);
+    }
+}
+
+unsafe extern "system" fn listener_window_proc(hwnd: HWND, msg: UINT, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    let display_changed = match msg {
+        WM_DISPLAYCHANGE | WM_DPICHANGED => true,
+        // 任务栏移动或大小改变时工作区变化
+        WM_SETTINGCHANGE => wparam == SPI_SETWORKAREA as WPARAM,
+        _ => false,
+    };
+    if display_changed {
+        if let Some(ref listener) = *DISPLAY_LISTENER.lock().unwrap() {
+            listener();
+        }
+    }
+    match msg {
+        WM_HOTKEY if wparam == HOTKEY_ID as WPARAM => {
+            if let Some((_, _, ref listener)) = *HOTKEY.lock().unwrap() {
+                listener();
+            }
+            return 0;
+        }
+        WM_REGISTER_HOTKEY => {
+            register_hotkey(hwnd);
+            return 0;
+        }
+        WM_NOTIFY_ICON => {
+            if matches!(lparam as UINT, NIN_BALLOONTIMEOUT | NIN_BALLOONUSERCLICK | NIN_BALLOONHIDE) {
+                remove_notification_icon(hwnd);
+            }
+            return 0;
+        }
+        _ => {}
+    }
+    let taskbar_created = TASKBAR_CREATED.load(Ordering::Relaxed);
+    if taskbar_created != 0 && msg == taskbar_created {
+        log::warn!("[ClassPaper] 资源管理器已重新启动，桌面嵌入需要重新设置");
+        // 原来的 WorkerW 已随资源管理器销毁
+        EMBEDDED.lock().unwrap().clear();
+        emit_embed_event(EmbedEvent::ShellRestarted);
+    }
+    DefWindowProcW(hwnd, msg, wparam, lparam)
+}
+
+// 桌面子窗口的叠放顺序改变或前台窗口切换时检查嵌入窗口是否仍在 ShellDefView 之下，
+// 代替定时轮询；被同一窗口反复遮挡时通知调用方重新嵌入
+unsafe extern "system" fn z_order_event_proc(
+    _: HWINEVENTHOOK,
+    _: DWORD,
+    _: HWND,
+    _: LONG,
+    _: LONG,
+    _: DWORD,
+    _: DWORD,
+) {
+    if INPUT_ENABLED.load(Ordering::SeqCst) {
+        return;
+    }
+    // 修正叠放顺序本身也会产生事件，修正期间嵌套进来的事件直接忽略
+    let Ok(mut embedded) = EMBEDDED.try_lock() else {
+        return;
+    };
+    let mut lost = false;
+    embedded.retain_mut(|(shell_def_view, embed_wnd, fixes, last_conflict)| {
+        let (h_shell_def_view, h_embed_wnd) = (*shell_def_view as HWND, *embed_wnd as HWND);
+        // 浏览器被关闭或重启后窗口已不存在
+        if IsWindow(h_embed_wnd) == 0 || IsWindow(h_shell_def_view) == 0 {
+            return false;
+        }
+        let (ok, conflict_hwnd) = ensure_embed_window_below(h_shell_def_view, h_embed_wnd);
+        if ok || conflict_hwnd.is_null() {
+            *fixes = 0;
+            *last_conflict = 0;
+            return true;
+        }
+        if conflict_hwnd as usize == *last_conflict {
+            *fixes += 1;
+        } else {
+            *last_conflict = conflict_hwnd as usize;
+            *fixes = 1;
+        }
+        println!(
+            "[winapi debug] 检测到冲突窗口: {:?}, 连续修复次数: {}",
+            conflict_hwnd, fixes
+        );
+        if *fixes >= MAX_CONSECUTIVE_FIXES {
+            log::warn!("[ClassPaper] 嵌入窗口被 {:?} 反复遮挡，需要重新设置桌面嵌入", conflict_hwnd);
+            lost = true;
+            return false;
+        }
+        true
+    });
+    drop(embedded);
+    if lost {
+        emit_embed_event(EmbedEvent::EmbedLost);
+    }
+}
+
+// 在单独的线程中创建一个不显示的顶层窗口，接收只广播给顶层窗口的 WM_DISPLAYCHANGE 和 TaskbarCreated，
+// 并在同一线程的消息循环中接收叠放顺序的 WinEvent；只创建一次，显示器和嵌入的监听共用
+fn start_listener_window() {
+    LISTENER_WINDOW.call_once(|| {
+        thread::spawn(|| unsafe {
+            let class_name: Vec<u16> = OsStr::new("ClassPaperListener")
+                .encode_wide()
+                .chain(Some(0))
+                .collect();
+            let instance = GetModuleHandleW(ptr::null());
+            let mut class: WNDCLASSW = mem::zeroed();
+            class.lpfnWndProc = Some(listener_window_proc);
+            class.hInstance = instance;
+            class.lpszClassName = class_name.as_ptr();
+            if RegisterClassW(&class) == 0 {
+                log::warn!("[ClassPaper] 注册监听窗口类失败，无法检测显示器变化和资源管理器重启");
+                return;
+            }
+            let hwnd = CreateWindowExW(
+                0,
+                class_name.as_ptr(),
+                class_name.as_ptr(),
+                WS_OVERLAPPED,
+                0,
+                0,
+                0,
+                0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                instance,
+                ptr::null_mut(),
+            );
+            if hwnd.is_null() {
+                log::warn!("[ClassPaper] 创建监听窗口失败，无法检测显示器变化和资源管理器重启");
+                return;
+            }
+            LISTENER_HWND.store(hwnd as usize, Ordering::SeqCst);
+            register_hotkey(hwnd);
+            let taskbar_created: Vec<u16> = OsStr::new("TaskbarCreated").encode_wide().chain(Some(0)).collect();
+            TASKBAR_CREATED.store(RegisterWindowMessageW(taskbar_created.as_ptr()), Ordering::Relaxed);
+            for event in [EVENT_OBJECT_REORDER, EVENT_SYSTEM_FOREGROUND] {
+                if SetWinEventHook(event, event, ptr::null_mut(), Some(z_order_event_proc), 0, 0, WINEVENT_OUTOFCONTEXT)
+                    .is_null()
+                {
+                    log::warn!("[ClassPaper] 注册叠放顺序事件 0x{:x} 失败", event);
+                }
+            }
+            log::info!("[ClassPaper] 开始监听显示器变化和资源管理器重启");
+            let mut msg: MSG = mem::zeroed();
+            while GetMessageW(&mut msg, ptr::null_mut(), 0, 0) > 0 {
+                TranslateMessage(&msg);
+                DispatchMessageW(&msg);
+            }
+        });
+    });
+}
+
+// 监听显示器变化
+pub fn watch_display_changes(listener: DisplayListener) {
+    *DISPLAY_LISTENER.lock().unwrap() = Some(listener);
+    start_listener_window();
+}
+
+// 监听资源管理器重启和嵌入窗口被遮挡
+pub fn watch_embed(listener: EmbedListener) {
+    *EMBED_LISTENER.lock().unwrap() = Some(listener);
+    start_listener_window();
+}
+
+// 在监听窗口线程中注册已设置的热键，只注册一次；热键被其他程序占用时写入日志
+fn register_hotkey(hwnd: HWND) {
+    let hotkey = HOTKEY.lock().unwrap();
+    let Some((modifiers, vk, _)) = *hotkey else {
+        return;
+    };
+    if HOTKEY_REGISTERED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    if unsafe { RegisterHotKey(hwnd, HOTKEY_ID, modifiers | MOD_NOREPEAT as u32, vk) } == 0 {
+        log::warn!("[ClassPaper] 注册热键失败，可能已被其他程序占用");
+        HOTKEY_REGISTERED.store(false, Ordering::SeqCst);
+    } else {
+        log::info!("[ClassPaper] 已注册热键");
+    }
+}
+
+// 监听全局热键，注册在监听窗口线程中进行
+pub fn watch_hotkey(hotkey: &Hotkey, listener: HotkeyListener) {
+    let mut modifiers = 0;
+    for (held, modifier) in [
+        (hotkey.ctrl, MOD_CONTROL),
+        (hotkey.alt, MOD_ALT),
+        (hotkey.shift, MOD_SHIFT),
+        (hotkey.super_key, MOD_WIN),
+    ] {
+        if held {
+            modifiers |= modifier as u32;
+        }
+    }
+    // 字母和数字的虚拟键码与大写 ASCII 码相同
+    let vk = match hotkey.key {
+        Key::Char(c) => c as u32,
+        Key::Function(n) => VK_F1 as u32 + u32::from(n) - 1,
+    };
+    *HOTKEY.lock().unwrap() = Some((modifiers, vk, listener));
+    let hwnd = LISTENER_HWND.load(Ordering::SeqCst) as HWND;
+    if hwnd.is_null() {
+        start_listener_window();
+    } else {
+        unsafe {
+            PostMessageW(hwnd, WM_REGISTER_HOTKEY, 0, 0);
+        }
+    }
+}
+
+// 显示器在 Progman/WorkerW 中的位置：它们覆盖整个虚拟屏幕，原点是虚拟屏幕的左上角，
+// 主显示器左边或上边还有显示器时与屏幕坐标不同
+fn desktop_rect(monitor: &Monitor) -> RECT {
+    let (left, top) = unsafe { (GetSystemMetrics(SM_XVIRTUALSCREEN), GetSystemMetrics(SM_YVIRTUALSCREEN)) };
+    RECT {
+        left: monitor.x - left,
+        top: monitor.y - top,
+        right: monitor.x - left + monitor.width,
+        bottom: monitor.y - top + monitor.height,
+    }
+}
+
 // pub fn get_windows_dark_mode() -> bool {
 //     let reg_path = OsStr::new(r"Software\Microsoft\Windows\CurrentVersion\Themes\Personalize")
 //         .encode_wide().chain(Some(0)).collect::<Vec<u16>>();
@@ -387,27 +682,19 @@ pub fn setup_transparency(hwnd: HWND) -> bool {
     }
 }
 
-pub fn setup_fullscreen_position(hwnd: HWND) -> bool {
+pub fn setup_fullscreen_position(hwnd: HWND, monitor: &Monitor) -> bool {
     if hwnd.is_null() {
         return false;
     }
 
     unsafe {
-        let rcx = GetSystemMetrics(SM_CXSCREEN);
-        let rcy = GetSystemMetrics(SM_CYSCREEN);
-
-        if rcx <= 0 || rcy <= 0 {
+        if monitor.width <= 0 || monitor.height <= 0 {
             println!("[winapi debug] 获取屏幕尺寸失败");
             return false;
         }
 
         let style = GetWindowLongPtrW(hwnd, GWL_STYLE) as u32;
-        let mut rc_full_screen = RECT {
-            left: 0,
-            top: 0,
-            right: rcx,
-            bottom: rcy,
-        };
+        let mut rc_full_screen = desktop_rect(monitor);
 
         AdjustWindowRect(&mut rc_full_screen, style, FALSE);
 
@@ -446,7 +733,7 @@ pub fn setup_fullscreen_position(hwnd: HWND) -> bool {
 }
 
 // 新版本桌面穿透方案 (Win10/11 24H2兼容)
-pub fn setup_wallpaper_new(window_title: &str) -> bool {
+pub fn setup_wallpaper_new(window_title: &str, monitor: &Monitor) -> bool {
     let hwnd = find_window_by_title(window_title);
     if hwnd.is_null() {
         println!("[winapi debug] 未找到窗口包含 '{}'", window_title);
@@ -660,8 +947,8 @@ pub fn setup_wallpaper_new(window_title: &str) -> bool {
         );
     }
 
-    // 8. 设置全屏位置
-    setup_fullscreen_position(hwnd);
+    // 8. 铺满所在显示器
+    setup_fullscreen_position(hwnd, monitor);
 
     // 9. 显示窗口
     unsafe {
@@ -670,68 +957,133 @@ pub fn setup_wallpaper_new(window_title: &str) -> bool {
         ShowWindow(h_worker, SW_SHOW);
     }
 
-    // 10. 启动Z顺序监控 (仅24H2需要)
+    // 10. 叠放顺序改变时保持在 ShellDefView 之下 (仅24H2需要)
     if !is_version_1_2 {
-        start_z_order_monitor(h_shell_def_view, hwnd);
+        keep_below_shell_view(h_shell_def_view, hwnd);
     }
 
     println!("[winapi debug] 新版本桌面穿透设置完成");
     true
 }
 
-pub fn start_z_order_monitor(h_shell_def_view: HWND, h_embed_wnd: HWND) {
-    let shell_def_view_ptr = h_shell_def_view as usize;
-    let embed_wnd_ptr = h_embed_wnd as usize;
+unsafe extern "system" fn enum_shell_def_view_proc(hwnd: HWND, result: LPARAM) -> i32 {
+    let defview = FindWindowExA(
+        hwnd,
+        ptr::null_mut(),
+        CString::new("SHELLDLL_DefView").unwrap().as_ptr(),
+        ptr::null_mut(),
+    );
+    if defview.is_null() {
+        return 1;
+    }
+    *(result as *mut HWND) = defview;
+    0
+}
 
-    thread::spawn(move || {
-        println!("[winapi debug] 开始监控Z顺序...");
-        let max_consecutive_fixes = 5;
-        let mut consecutive_fix_count = 0;
-        let mut last_conflict_hwnd = ptr::null_mut();
+// 桌面图标所在的 SHELLDLL_DefView，它的父窗口可能是 Progman 或某个 WorkerW
+fn find_shell_def_view() -> HWND {
+    let mut defview: HWND = ptr::null_mut();
+    unsafe {
+        EnumWindows(Some(enum_shell_def_view_proc), &mut defview as *mut HWND as LPARAM);
+    }
+    defview
+}
 
-        let h_shell_def_view = shell_def_view_ptr as HWND;
-        let h_embed_wnd = embed_wnd_ptr as HWND;
+// 切换嵌入窗口是否接收输入。接收时去掉 WS_EX_TRANSPARENT，移到桌面图标所在窗口中的最上面，桌面图标被页面遮住；
+// 点击穿透时放回 ShellDefView 之下，点击落在桌面图标上
+pub fn set_window_interactive(hwnd: HWND, interactive: bool) -> bool {
+    if hwnd.is_null() {
+        return false;
+    }
+    INPUT_ENABLED.store(interactive, Ordering::SeqCst);
+    unsafe {
+        let mut ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE) as u32;
+        if interactive {
+            ex_style &= !WS_EX_TRANSPARENT;
+        } else {
+            ex_style |= WS_EX_TRANSPARENT;
+        }
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style as isize);
 
-        loop {
-            let (ok, conflict_hwnd) = ensure_embed_window_below(h_shell_def_view, h_embed_wnd);
+        let h_shell_def_view = find_shell_def_view();
+        let h_icons = if h_shell_def_view.is_null() {
+            ptr::null_mut()
+        } else {
+            GetParent(h_shell_def_view)
+        };
+        // 旧版本方案中页面和桌面图标不在同一个窗口中，移过去才能位于图标之上
+        if interactive && !h_icons.is_null() && GetParent(hwnd) != h_icons {
+            SetParent(hwnd, h_icons);
+        }
+        let insert_after = if interactive {
+            HWND_TOP
+        } else if !h_icons.is_null() && GetParent(hwnd) == h_icons {
+            h_shell_def_view
+        } else {
+            HWND_BOTTOM
+        };
+        SetWindowPos(
+            hwnd,
+            insert_after,
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE | SWP_FRAMECHANGED,
+        );
+    }
+    log::debug!("[ClassPaper] 嵌入窗口 {:?} 接收输入: {}", hwnd, interactive);
+    true
+}
 
-            if !ok {
-                if conflict_hwnd.is_null() {
-                    consecutive_fix_count = 0;
-                } else if conflict_hwnd == last_conflict_hwnd {
-                    consecutive_fix_count += 1;
-                } else {
-                    last_conflict_hwnd = conflict_hwnd;
-                    consecutive_fix_count = 1;
-                }
+// 小窗口模式：去掉标题栏和边框，不显示在任务栏中，按 always_on_top 置顶，opacity 为不透明度
+pub fn setup_widget(window_title: &str, opacity: f64, always_on_top: bool) -> bool {
+    let hwnd = find_window_by_title(window_title);
+    if hwnd.is_null() {
+        log::debug!("[ClassPaper] 未找到窗口包含 '{}'", window_title);
+        return false;
+    }
+    unsafe {
+        let mut style = GetWindowLongPtrW(hwnd, GWL_STYLE) as u32;
+        style &= !(WS_CAPTION | WS_BORDER | WS_SYSMENU | WS_THICKFRAME);
+        style |= WS_POPUP;
+        SetWindowLongPtrW(hwnd, GWL_STYLE, style as isize);
 
-                if !conflict_hwnd.is_null() {
-                    println!(
-                        "[winapi debug] 检测到冲突窗口: {:?}, 连续修复次数: {}",
-                        conflict_hwnd, consecutive_fix_count
-                    );
-                }
+        let mut ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE) as u32;
+        ex_style |= WS_EX_TOOLWINDOW | WS_EX_LAYERED;
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, ex_style as isize);
+        SetLayeredWindowAttributes(hwnd, 0, (opacity.clamp(0.0, 1.0) * 255.0).round() as u8, LWA_ALPHA);
 
-                if consecutive_fix_count >= max_consecutive_fixes {
-                    println!("[winapi debug] 检测到重复的Z顺序冲突! 退出监控");
-                    break;
-                }
-            } else {
-                consecutive_fix_count = 0;
-                last_conflict_hwnd = ptr::null_mut();
-            }
+        SetWindowPos(
+            hwnd,
+            if always_on_top { HWND_TOPMOST } else { HWND_NOTOPMOST },
+            0,
+            0,
+            0,
+            0,
+            SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE | SWP_FRAMECHANGED,
+        );
+    }
+    log::debug!("[ClassPaper] 小窗口设置完成: 不透明度 {}, 置顶 {}", opacity, always_on_top);
+    true
+}
 
-            thread::sleep(Duration::from_millis(500));
-        }
-    });
+// 记录需要保持在 ShellDefView 之下的嵌入窗口，由监听窗口线程在叠放顺序改变时检查
+pub fn keep_below_shell_view(h_shell_def_view: HWND, h_embed_wnd: HWND) {
+    let mut embedded = EMBEDDED.lock().unwrap();
+    embedded.retain(|(_, embed_wnd, _, _)| *embed_wnd != h_embed_wnd as usize);
+    embedded.push((h_shell_def_view as usize, h_embed_wnd as usize, 0, 0));
+    drop(embedded);
+    start_listener_window();
 }
 
 // 旧版本桌面穿透方案 (保留兼容性)
-pub fn setup_wallpaper(window_title: &str) -> bool {
+pub fn setup_wallpaper(window_title: &str, monitor: &Monitor) -> bool {
     let hwnd = find_window_by_title(window_title);
     if !hwnd.is_null() {
         println!("[winapi debug] 找到窗口 HWND: {:?}, 开始设置桌面穿透", hwnd);
         set_desktop(hwnd);
+        setup_fullscreen_position(hwnd, monitor);
         unsafe {
             let val: u32 = 1;
             let dwm_result = DwmSetWindowAttribute(
@@ -784,19 +1136,63 @@ pub fn show_error_notification(message: &str) {
     }
 }
 
+// 以 0 结尾的定长 UTF-16 字符串，过长时截断；NOTIFYICONDATAW 在 32 位下是紧凑布局，字段只能整体赋值
+fn wide_array<const N: usize>(text: &str) -> [u16; N] {
+    let mut buffer = [0u16; N];
+    for (slot, unit) in buffer.iter_mut().zip(OsStr::new(text).encode_wide().take(N - 1)) {
+        *slot = unit;
+    }
+    buffer
+}
 
+// 在监听窗口上添加一个临时托盘图标并显示气泡，Windows 10 起显示为系统通知；
+// 上一条通知的图标还在时直接替换内容
+pub fn show_notification(summary: &str, body: &str) -> Result<(), String> {
+    start_listener_window();
+    // 监听窗口在单独的线程中创建，第一次通知时可能还没有创建好
+    let mut hwnd = LISTENER_HWND.load(Ordering::SeqCst);
+    for _ in 0..20 {
+        if hwnd != 0 {
+            break;
+        }
+        thread::sleep(std::time::Duration::from_millis(50));
+        hwnd = LISTENER_HWND.load(Ordering::SeqCst);
+    }
+    if hwnd == 0 {
+        return Err("监听窗口未创建，无法显示系统通知".to_string());
+    }
+    unsafe {
+        let mut data: NOTIFYICONDATAW = mem::zeroed();
+        data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as DWORD;
+        data.hWnd = hwnd as HWND;
+        data.uID = NOTIFY_ICON_ID;
+        data.uFlags = NIF_ICON | NIF_MESSAGE | NIF_TIP | NIF_INFO;
+        data.uCallbackMessage = WM_NOTIFY_ICON;
+        data.hIcon = LoadIconW(ptr::null_mut(), IDI_INFORMATION);
+        data.dwInfoFlags = NIIF_INFO;
+        data.szTip = wide_array("ClassPaper");
+        data.szInfoTitle = wide_array(summary);
+        // 正文为空时不显示气泡
+        data.szInfo = wide_array(if body.is_empty() { " " } else { body });
+        if Shell_NotifyIconW(NIM_MODIFY, &mut data) == 0 && Shell_NotifyIconW(NIM_ADD, &mut data) == 0 {
+            return Err("添加通知图标失败".to_string());
+        }
+    }
+    Ok(())
+}
 
-// 通用的错误处理函数
-pub fn handle_window_creation_error(error: &str) {
-    let error_message = format!(
-        "无法创建alcro窗口:\n{}\n\n可能的原因:\n- Chrome/Edge浏览器未安装\n- 浏览器路径配置错误\n- 系统资源不足\n- 权限问题",
-        error
-    );
-    
-    log::error!("[ClassPaper] {}", error_message);
-    show_error_notification(&error_message);
+fn remove_notification_icon(hwnd: HWND) {
+    unsafe {
+        let mut data: NOTIFYICONDATAW = mem::zeroed();
+        data.cbSize = mem::size_of::<NOTIFYICONDATAW>() as DWORD;
+        data.hWnd = hwnd;
+        data.uID = NOTIFY_ICON_ID;
+        Shell_NotifyIconW(NIM_DELETE, &mut data);
+    }
 }
 
+
+
 /// 检测Windows版本并选择合适的桌面穿透方案
 pub fn get_windows_version() -> (u32, u32, u32) {
     // 由于GetVersionExW在新版本Windows中可能被弃用，我们使用更简单的方法
@@ -846,29 +1242,30 @@ pub fn should_use_new_wallpaper_method() -> bool {
 }
 
 /// 统一的桌面穿透设置函数，根据Windows版本自动选择方案
-pub fn setup_desktop_penetration(window_name: &str) {
-    log::info!("[ClassPaper] 开始设置桌面穿透: {}", window_name);
+/// 返回是否设置成功，失败时由调用方决定是否提示用户
+pub fn setup_desktop_penetration(window_name: &str, monitor: &Monitor) -> bool {
+    log::info!("[ClassPaper] 开始设置桌面穿透: {} ({})", window_name, monitor.id);
     
     let success = if should_use_new_wallpaper_method() {
         // 尝试新版本方案
         log::debug!("[ClassPaper] 尝试新版本桌面穿透方案");
-        let result = setup_wallpaper_new(window_name);
+        let result = setup_wallpaper_new(window_name, monitor);
         if !result {
             log::warn!("[ClassPaper] 新版本方案失败，回退到旧版本方案");
-            setup_wallpaper(window_name)
+            setup_wallpaper(window_name, monitor)
         } else {
             result
         }
     } else {
         // 使用旧版本方案
         log::debug!("[ClassPaper] 使用旧版本桌面穿透方案");
-        setup_wallpaper(window_name)
+        setup_wallpaper(window_name, monitor)
     };
     
     if success {
         log::info!("[ClassPaper] 桌面穿透设置成功");
     } else {
         log::error!("[ClassPaper] 桌面穿透设置失败");
-        show_error_notification("桌面穿透设置失败\n\n可能原因：\n• 系统权限不足\n• 桌面窗口被其他程序占用\n• Windows版本兼容性问题\n\n请尝试以管理员身份运行程序，或查看app.log获取详细信息。");
     }
+    success
 }
\ No newline at end of file
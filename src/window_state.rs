@@ -0,0 +1,269 @@
+// 记住非全屏窗口（设置窗口、小窗口）的位置、大小和最大化/全屏状态，程序重新启动后恢复。
+// 保存在工作目录的 window_state.toml 中，每种窗口一段；恢复时调整到当前连接的显示器内，
+// 拔掉显示器或降低分辨率后窗口不会出现在看不到的地方
+
+use crate::platform::{self, Monitor};
+use alcro::{Bounds, JSError, WindowState, UI};
+use once_cell::sync::Lazy;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+use toml::Value as TomlValue;
+
+const STATE_FILE: &str = "window_state.toml";
+// 检查窗口位置是否改变的间隔
+const TRACK_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowKind {
+    Settings,
+    Widget,
+}
+
+impl WindowKind {
+    // window_state.toml 中的段名
+    fn name(self) -> &'static str {
+        match self {
+            WindowKind::Settings => "settings",
+            WindowKind::Widget => "widget",
+        }
+    }
+}
+
+// 读写状态文件时持有，避免多个线程同时保存时互相覆盖
+static FILE_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+// 格式不对的段单独忽略，不影响其他窗口
+fn load() -> BTreeMap<String, Bounds> {
+    let Ok(content) = std::fs::read_to_string(STATE_FILE) else {
+        return BTreeMap::new();
+    };
+    let sections: BTreeMap<String, TomlValue> = toml::from_str(&content).unwrap_or_else(|e| {
+        log::warn!("[ClassPaper] {} 格式错误，忽略已保存的窗口位置: {}", STATE_FILE, e);
+        BTreeMap::new()
+    });
+    sections
+        .into_iter()
+        .filter_map(|(name, value)| match value.try_into::<Bounds>() {
+            Ok(bounds) => Some((name, bounds)),
+            Err(e) => {
+                log::warn!("[ClassPaper] 忽略 {} 中 {} 的窗口位置: {}", STATE_FILE, name, e);
+                None
+            }
+        })
+        .collect()
+}
+
+// kind 种类的窗口上次保存的位置和状态，未经调整
+pub fn saved(kind: WindowKind) -> Option<Bounds> {
+    let _guard = FILE_LOCK.lock().unwrap();
+    load().get(kind.name()).copied()
+}
+
+pub fn save(kind: WindowKind, bounds: Bounds) {
+    let _guard = FILE_LOCK.lock().unwrap();
+    let mut all = load();
+    if all.get(kind.name()) == Some(&bounds) {
+        return;
+    }
+    all.insert(kind.name().to_string(), bounds);
+    let result = toml::to_string(&all)
+        .map_err(|e| e.to_string())
+        .and_then(|content| std::fs::write(STATE_FILE, content).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => log::debug!(
+            "[ClassPaper] 已保存 {} 的位置: {}x{}+{}+{} ({:?})",
+            kind.name(),
+            bounds.width,
+            bounds.height,
+            bounds.left,
+            bounds.top,
+            bounds.window_state
+        ),
+        Err(e) => log::warn!("[ClassPaper] 保存窗口位置到 {} 失败: {}", STATE_FILE, e),
+    }
+}
+
+// 上次保存的位置和状态，没有时为 default；调整到当前的显示器内，不恢复最小化
+pub fn restore(kind: WindowKind, default: Bounds) -> Bounds {
+    restored(saved(kind), default, &platform::monitors())
+}
+
+fn restored(saved: Option<Bounds>, default: Bounds, monitors: &[Monitor]) -> Bounds {
+    let mut bounds = saved.unwrap_or(default);
+    if bounds.window_state == WindowState::Minimized {
+        bounds.window_state = WindowState::Normal;
+    }
+    clamp(bounds, monitors)
+}
+
+// 把窗口调整到一个显示器内：中心所在的显示器，其次是重叠面积最大的，都没有时为主显示器（monitors 的第一个）；
+// 比显示器大时缩小到显示器的大小
+pub fn clamp(bounds: Bounds, monitors: &[Monitor]) -> Bounds {
+    let (center_x, center_y) = (bounds.left + bounds.width / 2, bounds.top + bounds.height / 2);
+    let overlap = |m: &Monitor| {
+        let width = (bounds.left + bounds.width).min(m.x + m.width) - bounds.left.max(m.x);
+        let height = (bounds.top + bounds.height).min(m.y + m.height) - bounds.top.max(m.y);
+        i64::from(width.max(0)) * i64::from(height.max(0))
+    };
+    let monitor = monitors
+        .iter()
+        .find(|m| (m.x..m.x + m.width).contains(&center_x) && (m.y..m.y + m.height).contains(&center_y))
+        .or_else(|| monitors.iter().filter(|m| overlap(m) > 0).max_by_key(|m| overlap(m)))
+        .or_else(|| monitors.first());
+    let Some(monitor) = monitor else {
+        return bounds;
+    };
+    let width = bounds.width.clamp(1, monitor.width.max(1));
+    let height = bounds.height.clamp(1, monitor.height.max(1));
+    Bounds {
+        left: bounds.left.clamp(monitor.x, monitor.x + monitor.width - width),
+        top: bounds.top.clamp(monitor.y, monitor.y + monitor.height - height),
+        width,
+        height,
+        window_state: bounds.window_state,
+    }
+}
+
+// 按 bounds 设置窗口。最大化或全屏时先设置普通状态下的位置，之后取消最大化会回到这里
+pub fn apply(ui: &UI, bounds: Bounds) -> Result<(), JSError> {
+    ui.set_bounds(Bounds {
+        window_state: WindowState::Normal,
+        ..bounds
+    })?;
+    if bounds.window_state != WindowState::Normal {
+        ui.set_bounds(bounds.window_state.to_bounds())?;
+    }
+    Ok(())
+}
+
+// 在后台定期记录 ui 的位置和状态，窗口关闭或被丢弃后结束。最大化、全屏时保留普通状态下的位置，最小化时不记录
+pub fn track(kind: WindowKind, ui: Weak<UI>) {
+    thread::spawn(move || {
+        let mut normal = saved(kind).filter(|b| b.window_state == WindowState::Normal);
+        loop {
+            thread::sleep(TRACK_INTERVAL);
+            let Some(ui) = ui.upgrade() else {
+                return;
+            };
+            if ui.done() {
+                return;
+            }
+            let Ok(current) = ui.bounds() else {
+                continue;
+            };
+            drop(ui);
+            let bounds = match current.window_state {
+                WindowState::Normal => {
+                    normal = Some(current);
+                    current
+                }
+                WindowState::Minimized => continue,
+                state => match normal {
+                    Some(normal) => Bounds {
+                        window_state: state,
+                        ..normal
+                    },
+                    None => current,
+                },
+            };
+            save(kind, bounds);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(id: &str, x: i32, y: i32, width: i32, height: i32, primary: bool) -> Monitor {
+        Monitor {
+            id: id.to_string(),
+            x,
+            y,
+            width,
+            height,
+            primary,
+        }
+    }
+
+    fn bounds(left: i32, top: i32, width: i32, height: i32) -> Bounds {
+        Bounds {
+            left,
+            top,
+            width,
+            height,
+            window_state: WindowState::Normal,
+        }
+    }
+
+    // 主显示器 1920x1080，右边接一个 1280x1024 的副显示器
+    fn monitors() -> Vec<Monitor> {
+        vec![
+            monitor("primary", 0, 0, 1920, 1080, true),
+            monitor("right", 1920, 0, 1280, 1024, false),
+        ]
+    }
+
+    #[test]
+    fn test_clamp_inside() {
+        // 完全在显示器内时不变
+        assert_eq!(clamp(bounds(100, 100, 800, 600), &monitors()), bounds(100, 100, 800, 600));
+        assert_eq!(clamp(bounds(2000, 50, 800, 600), &monitors()), bounds(2000, 50, 800, 600));
+        // 中心在副显示器上，跨出的部分移回副显示器内
+        assert_eq!(clamp(bounds(1700, 600, 800, 600), &monitors()), bounds(1920, 424, 800, 600));
+    }
+
+    #[test]
+    fn test_clamp_largest_overlap() {
+        // 中心在两个显示器之外（副显示器下方的空白处），移到重叠面积更大的副显示器上
+        assert_eq!(clamp(bounds(1800, 900, 600, 300), &monitors()), bounds(1920, 724, 600, 300));
+        // 中心在主显示器下方，与主显示器重叠更多
+        assert_eq!(clamp(bounds(1000, 1000, 400, 300), &monitors()), bounds(1000, 780, 400, 300));
+    }
+
+    #[test]
+    fn test_clamp_no_overlap() {
+        // 拔掉的显示器上的窗口回到主显示器（monitors 的第一个）
+        assert_eq!(clamp(bounds(4000, 200, 800, 600), &monitors()), bounds(1120, 200, 800, 600));
+        assert_eq!(clamp(bounds(-3000, -3000, 800, 600), &monitors()), bounds(0, 0, 800, 600));
+        // 没有显示器时不调整
+        assert_eq!(clamp(bounds(4000, 200, 800, 600), &[]), bounds(4000, 200, 800, 600));
+    }
+
+    #[test]
+    fn test_clamp_larger_than_monitor() {
+        assert_eq!(clamp(bounds(2000, -50, 1600, 1200), &monitors()), bounds(1920, 0, 1280, 1024));
+        assert_eq!(clamp(bounds(0, 0, 0, 0), &monitors()), bounds(0, 0, 1, 1));
+        // 保留最大化、全屏状态
+        let maximized = Bounds {
+            window_state: WindowState::Maximized,
+            ..bounds(-10, -10, 1940, 1100)
+        };
+        assert_eq!(
+            clamp(maximized, &monitors()),
+            Bounds {
+                window_state: WindowState::Maximized,
+                ..bounds(0, 0, 1920, 1080)
+            }
+        );
+    }
+
+    #[test]
+    fn test_restore() {
+        let default = bounds(100, 100, 1000, 720);
+        // 没有保存过时为 default
+        assert_eq!(restored(None, default, &monitors()), default);
+        assert_eq!(restored(Some(bounds(2000, 100, 640, 480)), default, &monitors()), bounds(2000, 100, 640, 480));
+        // 最小化恢复为普通窗口
+        let minimized = Bounds {
+            window_state: WindowState::Minimized,
+            ..bounds(200, 200, 640, 480)
+        };
+        assert_eq!(restored(Some(minimized), default, &monitors()), bounds(200, 200, 640, 480));
+        // 保存时的显示器已拔掉
+        let only_primary = &monitors()[..1];
+        assert_eq!(restored(Some(bounds(2000, 100, 640, 480)), default, only_primary), bounds(1280, 100, 640, 480));
+    }
+}
@@ -0,0 +1,37 @@
+// 前端测试的公共部分：定位 res 下的页面、等待页面渲染；启动浏览器和轮询等待与单元测试共用 src/test_util.rs
+
+#![allow(dead_code)]
+
+#[path = "../../src/test_util.rs"]
+mod test_util;
+
+pub use test_util::{launch, wait_for};
+
+use alcro::{JSObject, JSResult, UI};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+pub fn page_url(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("res").join(name);
+    url::Url::from_file_path(path).unwrap().to_string()
+}
+
+// 等待 js 返回非空字符串
+pub fn wait_for_text(ui: &UI, js: &str) -> String {
+    let value = wait_for(ui, js, |v| v.as_str().is_some_and(|s| !s.is_empty()));
+    value.as_str().unwrap().to_string()
+}
+
+// 记录每次调用参数的绑定，返回固定的结果
+pub type Calls = Arc<Mutex<Vec<Vec<JSObject>>>>;
+
+pub fn stub(ui: &UI, name: &str, result: JSResult) -> Calls {
+    let calls = Calls::default();
+    let recorded = calls.clone();
+    ui.bind(name, move |args| {
+        recorded.lock().unwrap().push(args.to_vec());
+        result.clone()
+    })
+    .unwrap();
+    calls
+}
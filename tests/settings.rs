@@ -0,0 +1,97 @@
+// 在无头浏览器中打开设置页面，用记录调用的绑定代替后端，检查读取和保存配置的流程
+
+mod common;
+
+use alcro::{Content, JSResult, UI};
+use common::{launch, page_url, stub, wait_for, wait_for_text, Calls};
+use serde_json::json;
+
+// 页面右上角的状态提示
+const STATUS: &str = "document.querySelector('.status')?.textContent";
+
+struct Backend {
+    save_config: Calls,
+    write_file: Calls,
+    reload_main_window: Calls,
+}
+
+// 绑定设置页面用到的后端函数后打开设置页面，writeFile 返回 write_file
+fn open_settings(ui: &UI, write_file: JSResult) -> Backend {
+    stub(
+        ui,
+        "readConfig",
+        Ok(json!({ "Default": { "URL": "./res/index.html", "BrowserPath": "" } })),
+    );
+    stub(ui, "scanWallpaperDir", Ok(json!([])));
+    stub(ui, "listBrowsers", Ok(json!([])));
+    stub(ui, "getBuildDate", Ok(json!("2025-09-01")));
+    let backend = Backend {
+        save_config: stub(ui, "saveConfig", Ok(json!(null))),
+        write_file: stub(ui, "writeFile", write_file),
+        reload_main_window: stub(ui, "reloadMainWindow", Ok(json!(null))),
+    };
+    ui.load(Content::Url(&page_url("settings.html"))).unwrap();
+    wait_for_text(ui, "document.getElementById('url').value");
+    backend
+}
+
+#[test]
+fn test_load_config() {
+    let Some(ui) = launch() else { return };
+    open_settings(&ui, Ok(json!(null)));
+    assert_eq!(
+        ui.eval("document.getElementById('url').value").unwrap(),
+        "./res/index.html"
+    );
+    assert_eq!(
+        ui.eval("document.getElementById('semester-begin').value")
+            .unwrap(),
+        "2023-07-31"
+    );
+}
+
+#[test]
+fn test_save_config() {
+    let Some(ui) = launch() else { return };
+    let backend = open_settings(&ui, Ok(json!(null)));
+    ui.eval(
+        "document.getElementById('url').value = 'https://example.com/';
+         document.getElementById('semester-end').value = '2026-07-01';
+         handleSave()",
+    )
+    .unwrap();
+    wait_for(&ui, STATUS, |v| v == "配置已保存并刷新主窗口");
+
+    // config.js 写入的是完整的 CONFIG 和旧版变量
+    let write_file = backend.write_file.lock().unwrap();
+    assert_eq!(write_file.len(), 1);
+    assert_eq!(write_file[0][0], "res/config/config.js");
+    let content = write_file[0][1].as_str().unwrap();
+    assert!(content.starts_with("const CONFIG = {"));
+    assert!(content.contains("\"end\": \"2026-07-01\""));
+    assert!(content.contains("const events = "));
+
+    // config.toml 只保存 Default 段
+    let save_config = backend.save_config.lock().unwrap();
+    assert_eq!(save_config.len(), 1);
+    let saved: serde_json::Value =
+        serde_json::from_str(save_config[0][0].as_str().unwrap()).unwrap();
+    assert_eq!(
+        saved,
+        json!({ "Default": { "URL": "https://example.com/", "BrowserPath": "" } })
+    );
+
+    assert_eq!(backend.reload_main_window.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn test_save_config_failure() {
+    let Some(ui) = launch() else { return };
+    let backend = open_settings(&ui, Err(json!("磁盘已满")));
+    ui.eval("handleSave()").unwrap();
+    wait_for(&ui, STATUS, |v| {
+        v.as_str()
+            .is_some_and(|s| s.starts_with("保存config.js失败"))
+    });
+    assert_eq!(backend.save_config.lock().unwrap().len(), 0);
+}
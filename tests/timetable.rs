@@ -0,0 +1,69 @@
+// 用虚拟时钟在无头浏览器中打开课程表页面，检查指定时间显示的课程和倒计日
+
+mod common;
+
+use alcro::{Content, UI};
+use common::{launch, page_url, wait_for_text};
+
+// 把时钟拨到 time（北京时间）后打开主页面
+fn open_at(ui: &UI, time: &str) {
+    ui.set_timezone("Asia/Shanghai").unwrap();
+    ui.set_virtual_time(time, false).unwrap();
+    ui.load(Content::Url(&page_url("index.html"))).unwrap();
+}
+
+// 滚动模式中间位置显示的课程
+fn current_class(ui: &UI) -> String {
+    wait_for_text(ui, "document.getElementById('c_b6')?.innerText ?? ''")
+}
+
+#[test]
+fn test_monday_morning() {
+    let Some(ui) = launch() else { return };
+    open_at(&ui, "2025-09-01T07:54:00");
+    // 周一第1节 07:20-07:55 是升旗
+    assert_eq!(current_class(&ui), "升旗");
+}
+
+#[test]
+fn test_tuesday_third_period() {
+    let Some(ui) = launch() else { return };
+    open_at(&ui, "2025-09-02T09:00:00");
+    assert_eq!(current_class(&ui), "数学");
+    // 前一节和后一节依次排在两边
+    assert_eq!(
+        ui.eval("document.getElementById('c_b5').innerText")
+            .unwrap(),
+        "语文"
+    );
+    assert_eq!(
+        ui.eval("document.getElementById('c_b7').innerText")
+            .unwrap(),
+        "英语"
+    );
+}
+
+#[test]
+fn test_lunch_break() {
+    let Some(ui) = launch() else { return };
+    open_at(&ui, "2025-09-01T13:00:00");
+    assert_eq!(current_class(&ui), "休息");
+}
+
+#[test]
+fn test_outside_semester() {
+    let Some(ui) = launch() else { return };
+    open_at(&ui, "2026-07-01T09:00:00");
+    assert_eq!(current_class(&ui), "无");
+}
+
+#[test]
+fn test_event_countdown() {
+    let Some(ui) = launch() else { return };
+    open_at(&ui, "2025-09-01T07:54:00");
+    let events = wait_for_text(&ui, "document.getElementById('evcal').innerText");
+    // 距 2026-06-07 的高考还有 278.7 天
+    assert!(events.contains("高考 279 (39周6天)"), "{}", events);
+    // 已过期的事件不显示
+    assert!(!events.contains("明天"), "{}", events);
+}